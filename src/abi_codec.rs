@@ -0,0 +1,333 @@
+use candid::{Int, Nat};
+use cketh_common::eth_rpc::{LogEntry, ValidationError};
+use ethers_core::{
+    abi::{Event, Function, HumanReadableParser, ParamType, RawLog, Token},
+    types::{Address, H256, U256},
+};
+use num::{BigInt, One, Signed};
+
+use crate::*;
+
+/// Converts a Candid ABI value to a token of the given type.
+pub fn abi_value_to_token(value: &AbiValue, kind: &ParamType) -> Result<Token, String> {
+    let mismatch = || format!("Expected a value of type {}, got {:?}", kind, value);
+    Ok(match (kind, value) {
+        (ParamType::Address, AbiValue::Address(address)) => {
+            Token::Address(parse_address(address).map_err(|_| mismatch())?)
+        }
+        (ParamType::Uint(size), AbiValue::Uint(value)) => {
+            if value.0.bits() > *size as u64 {
+                return Err(mismatch());
+            }
+            Token::Uint(U256::from_big_endian(&value.0.to_bytes_be()))
+        }
+        (ParamType::Int(size), AbiValue::Int(value)) => {
+            let bound = BigInt::one() << (*size - 1);
+            if value.0 >= bound || value.0 < -bound {
+                return Err(mismatch());
+            }
+            // Two's complement
+            let value = if value.0.is_negative() {
+                (BigInt::one() << 256) + &value.0
+            } else {
+                value.0.clone()
+            };
+            Token::Int(U256::from_big_endian(&value.to_bytes_be().1))
+        }
+        (ParamType::Bool, AbiValue::Bool(value)) => Token::Bool(*value),
+        (ParamType::Bytes, AbiValue::Bytes(bytes)) => Token::Bytes(bytes.clone()),
+        (ParamType::FixedBytes(size), AbiValue::FixedBytes(bytes)) if bytes.len() == *size => {
+            Token::FixedBytes(bytes.clone())
+        }
+        (ParamType::String, AbiValue::String(value)) => Token::String(value.clone()),
+        (ParamType::Array(kind), AbiValue::Array(values)) => Token::Array(
+            values
+                .iter()
+                .map(|value| abi_value_to_token(value, kind))
+                .collect::<Result<_, _>>()?,
+        ),
+        (ParamType::FixedArray(kind, size), AbiValue::Array(values)) if values.len() == *size => {
+            Token::FixedArray(
+                values
+                    .iter()
+                    .map(|value| abi_value_to_token(value, kind))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        (ParamType::Tuple(kinds), AbiValue::Tuple(values)) if values.len() == kinds.len() => {
+            Token::Tuple(
+                values
+                    .iter()
+                    .zip(kinds)
+                    .map(|(value, kind)| abi_value_to_token(value, kind))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        _ => return Err(mismatch()),
+    })
+}
+
+pub fn token_to_abi_value(token: Token) -> AbiValue {
+    match token {
+        Token::Address(address) => AbiValue::Address(format!("{:#x}", address)),
+        Token::Uint(value) => AbiValue::Uint(u256_to_nat(value)),
+        Token::Int(value) => {
+            let is_negative = value.bit(255);
+            let value = BigInt::from(u256_to_nat(value).0);
+            // Two's complement
+            AbiValue::Int(Int(if is_negative {
+                value - (BigInt::one() << 256)
+            } else {
+                value
+            }))
+        }
+        Token::Bool(value) => AbiValue::Bool(value),
+        Token::Bytes(bytes) => AbiValue::Bytes(bytes),
+        Token::FixedBytes(bytes) => AbiValue::FixedBytes(bytes),
+        Token::String(value) => AbiValue::String(value),
+        Token::Array(tokens) | Token::FixedArray(tokens) => {
+            AbiValue::Array(tokens.into_iter().map(token_to_abi_value).collect())
+        }
+        Token::Tuple(tokens) => {
+            AbiValue::Tuple(tokens.into_iter().map(token_to_abi_value).collect())
+        }
+    }
+}
+
+pub fn parse_function_signature(signature: &str) -> RpcResult<Function> {
+    HumanReadableParser::parse_function(signature).map_err(|err| {
+        ValidationError::Custom(format!("Invalid function signature: {}", err)).into()
+    })
+}
+
+/// Encodes the input of a function call, including its selector.
+pub fn encode_function_call(function: &Function, args: &[AbiValue]) -> RpcResult<Vec<u8>> {
+    if args.len() != function.inputs.len() {
+        return Err(ValidationError::Custom(format!(
+            "Expected {} arguments, got {}",
+            function.inputs.len(),
+            args.len()
+        ))
+        .into());
+    }
+    let tokens = args
+        .iter()
+        .zip(&function.inputs)
+        .map(|(arg, param)| abi_value_to_token(arg, &param.kind))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ValidationError::Custom)?;
+    function.encode_input(&tokens).map_err(|err| {
+        ValidationError::Custom(format!("Unable to encode arguments: {}", err)).into()
+    })
+}
+
+pub fn decode_function_output(function: &Function, output: &[u8]) -> RpcResult<Vec<AbiValue>> {
+    match function.decode_output(output) {
+        Ok(tokens) => Ok(tokens.into_iter().map(token_to_abi_value).collect()),
+        Err(_) => Err(ValidationError::Custom(format!(
+            "Unable to decode the output of {}",
+            function.signature()
+        ))
+        .into()),
+    }
+}
+
+/// Calls a contract function with arguments and outputs described by its human-readable signature.
+pub async fn eth_call_abi(
+    client: &CandidRpcClient,
+    args: CallAbiArgs,
+) -> MultiRpcResult<Vec<AbiValue>> {
+    let prepared = parse_address(&args.to).and_then(|to| {
+        let function = parse_function_signature(&args.signature)?;
+        let data = encode_function_call(&function, &args.args)?;
+        Ok((to, function, data))
+    });
+    let (to, function, data) = match prepared {
+        Ok(prepared) => prepared,
+        Err(err) => return MultiRpcResult::Consistent(Err(err)),
+    };
+    client
+        .eth_call(&to, &data, args.block.unwrap_or_default())
+        .await
+        .and_then(|output| decode_function_output(&function, &output))
+}
+
+pub fn parse_event_signature(signature: &str) -> Result<Event, ValidationError> {
+    HumanReadableParser::parse_event(signature)
+        .map_err(|err| ValidationError::Custom(format!("Invalid event signature: {}", err)))
+}
+
+/// Decodes a log entry emitted by the given event, or returns `None` for other logs.
+pub fn decode_log(event: &Event, log: &LogEntry) -> Option<DecodedLog> {
+    let raw_log = RawLog {
+        topics: log.topics.iter().map(|topic| H256(topic.0)).collect(),
+        data: log.data.0.clone(),
+    };
+    let decoded = event.parse_log(raw_log).ok()?;
+    Some(DecodedLog {
+        address: format!("{:#x}", Address::from_slice(log.address.as_ref())),
+        params: decoded
+            .params
+            .into_iter()
+            .map(|param| AbiParam {
+                name: param.name,
+                value: token_to_abi_value(param.value),
+            })
+            .collect(),
+        block_number: log.block_number.map(Nat::from),
+        transaction_hash: log.transaction_hash.as_ref().map(|hash| hash.to_string()),
+        log_index: log.log_index.map(Nat::from),
+        removed: log.removed,
+    })
+}
+
+pub fn do_decode_logs(
+    signature: &str,
+    logs: &[LogEntry],
+) -> Result<Vec<DecodedLog>, ValidationError> {
+    let event = parse_event_signature(signature)?;
+    Ok(logs
+        .iter()
+        .filter_map(|log| decode_log(&event, log))
+        .collect())
+}
+
+#[test]
+fn test_abi_value_tokens() {
+    let values = vec![
+        (ParamType::Uint(112), AbiValue::Uint(Nat::from(123_u64))),
+        (ParamType::Int(32), AbiValue::Int(Int::from(-5))),
+        (
+            ParamType::Address,
+            AbiValue::Address("0xdac17f958d2ee523a2206206994597c13d831ec7".to_string()),
+        ),
+        (
+            ParamType::Tuple(vec![ParamType::Bool, ParamType::FixedBytes(2)]),
+            AbiValue::Tuple(vec![AbiValue::Bool(true), AbiValue::FixedBytes(vec![1, 2])]),
+        ),
+        (
+            ParamType::FixedArray(Box::new(ParamType::String), 1),
+            AbiValue::Array(vec![AbiValue::String("abc".to_string())]),
+        ),
+    ];
+    for (kind, value) in values {
+        let token = abi_value_to_token(&value, &kind).unwrap();
+        assert_eq!(token_to_abi_value(token), value);
+    }
+    assert_eq!(
+        abi_value_to_token(&AbiValue::Int(Int::from(-1)), &ParamType::Int(8)),
+        Ok(Token::Int(U256::MAX))
+    );
+
+    // Values which do not fit their type
+    assert!(abi_value_to_token(&AbiValue::Uint(Nat::from(256_u64)), &ParamType::Uint(8)).is_err());
+    assert!(abi_value_to_token(&AbiValue::Int(Int::from(128)), &ParamType::Int(8)).is_err());
+    assert!(abi_value_to_token(&AbiValue::Int(Int::from(-129)), &ParamType::Int(8)).is_err());
+    assert!(abi_value_to_token(&AbiValue::Bool(true), &ParamType::Uint(8)).is_err());
+    assert!(abi_value_to_token(&AbiValue::FixedBytes(vec![1]), &ParamType::FixedBytes(2)).is_err());
+}
+
+#[test]
+fn test_encode_function_call() {
+    let function =
+        parse_function_signature("function balanceOf(address owner) view returns (uint256)")
+            .unwrap();
+    let data = encode_function_call(
+        &function,
+        &[AbiValue::Address(
+            "0xa9d1e08c7793af67e9d92fe308d5697fb81d3e43".to_string(),
+        )],
+    )
+    .unwrap();
+    assert_eq!(
+        hex::encode(data),
+        "70a08231000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43"
+    );
+    assert!(encode_function_call(&function, &[]).is_err());
+
+    let function =
+        parse_function_signature("function getReserves() view returns (uint112,uint112,uint32)")
+            .unwrap();
+    let output = hex::decode(format!("{:064x}{:064x}{:064x}", 1, 2, 3)).unwrap();
+    assert_eq!(
+        decode_function_output(&function, &output),
+        Ok(vec![
+            AbiValue::Uint(Nat::from(1_u64)),
+            AbiValue::Uint(Nat::from(2_u64)),
+            AbiValue::Uint(Nat::from(3_u64)),
+        ])
+    );
+    assert!(decode_function_output(&function, &[]).is_err());
+}
+
+#[test]
+fn test_decode_log() {
+    use cketh_common::eth_rpc::{Data, FixedSizeData};
+    use std::str::FromStr;
+
+    let event = parse_event_signature(
+        "event Transfer(address indexed from, address indexed to, uint256 value)",
+    )
+    .unwrap();
+    let topic = |hex: &str| FixedSizeData::from_str(hex).unwrap();
+    let log = LogEntry {
+        address: cketh_common::address::Address::from_str(
+            "0xdac17f958d2ee523a2206206994597c13d831ec7",
+        )
+        .unwrap(),
+        topics: vec![
+            topic("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"),
+            topic("0x000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43"),
+            topic("0x00000000000000000000000078cccfb3d517cd4ed6d045e263e134712288ace2"),
+        ],
+        data: Data(hex::decode(format!("{:064x}", 0x3b9c6433)).unwrap()),
+        block_number: None,
+        transaction_hash: None,
+        transaction_index: None,
+        block_hash: None,
+        log_index: None,
+        removed: false,
+    };
+    let decoded = decode_log(&event, &log).unwrap();
+    assert_eq!(
+        decoded.address,
+        "0xdac17f958d2ee523a2206206994597c13d831ec7"
+    );
+    assert_eq!(
+        decoded.params,
+        vec![
+            AbiParam {
+                name: "from".to_string(),
+                value: AbiValue::Address("0xa9d1e08c7793af67e9d92fe308d5697fb81d3e43".to_string()),
+            },
+            AbiParam {
+                name: "to".to_string(),
+                value: AbiValue::Address("0x78cccfb3d517cd4ed6d045e263e134712288ace2".to_string()),
+            },
+            AbiParam {
+                name: "value".to_string(),
+                value: AbiValue::Uint(Nat::from(0x3b9c6433_u64)),
+            },
+        ]
+    );
+
+    let approval = parse_event_signature(
+        "event Approval(address indexed owner, address indexed spender, uint256 value)",
+    )
+    .unwrap();
+    assert_eq!(decode_log(&approval, &log), None);
+    assert!(parse_event_signature("event Transfer(").is_err());
+    assert_eq!(
+        do_decode_logs(
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+            &[log.clone(), log.clone()],
+        )
+        .unwrap()
+        .len(),
+        2
+    );
+    assert!(matches!(
+        do_decode_logs("event Transfer(", &[log]),
+        Err(ValidationError::Custom(_))
+    ));
+}
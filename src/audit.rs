@@ -0,0 +1,86 @@
+use candid::Principal;
+use cketh_common::eth_rpc_client::providers::RpcService;
+
+use crate::*;
+
+/// Appends an entry for an administrative action to the audit log.
+pub fn record_audit_entry(
+    caller: Principal,
+    action: &str,
+    target: impl ToString,
+    before: Option<String>,
+    after: Option<String>,
+) {
+    append_audit_entry(AuditLogEntry {
+        timestamp: ic_cdk::api::time(),
+        caller,
+        action: action.to_string(),
+        target: target.to_string(),
+        before,
+        after,
+    });
+}
+
+pub fn append_audit_entry(entry: AuditLogEntry) -> u64 {
+    AUDIT_LOG.with(|log| {
+        log.borrow_mut()
+            .append(&entry)
+            .expect("Unable to append audit log entry")
+    })
+}
+
+/// Returns up to `limit` audit log entries (oldest first), starting at the given offset.
+pub fn get_audit_entries(offset: u64, limit: u64) -> Vec<AuditLogEntry> {
+    let limit = limit.min(AUDIT_LOG_MAX_PAGE_SIZE);
+    AUDIT_LOG.with(|log| {
+        let log = log.borrow();
+        (offset..offset.saturating_add(limit).min(log.len()))
+            .filter_map(|index| log.get(index))
+            .collect()
+    })
+}
+
+pub fn describe_authorizations(principal: &Principal) -> Option<String> {
+    let auth_set = AUTH.with(|a| a.borrow().get(&PrincipalStorable(*principal)))?;
+    let grants = AUTH_GRANTS
+        .with(|g| g.borrow().get(&PrincipalStorable(*principal)))
+        .unwrap_or_default();
+    Some(format!("{:?}, grants: {:?}", auth_set, grants.0))
+}
+
+/// Describes a provider (without credentials) and the services which are mapped to it.
+pub fn describe_provider(provider_id: u64) -> Option<String> {
+    let provider: ProviderView = PROVIDERS.with(|p| p.borrow().get(&provider_id))?.into();
+    let services: Vec<RpcService> = SERVICE_PROVIDER_MAP.with(|map| {
+        map.borrow()
+            .iter()
+            .filter(|(_, id)| *id == provider_id)
+            .filter_map(|(service, _)| service.try_into().ok())
+            .collect()
+    });
+    Some(format!("{:?}, services: {:?}", provider, services))
+}
+
+#[test]
+fn test_audit_log_pagination() {
+    let caller =
+        Principal::from_text("k5dlc-ijshq-lsyre-qvvpq-2bnxr-pb26c-ag3sc-t6zo5-rdavy-recje-zqe")
+            .unwrap();
+    let entries: Vec<_> = (0..5)
+        .map(|i| AuditLogEntry {
+            timestamp: i,
+            caller,
+            action: "setOpenRpcAccess".to_string(),
+            target: "openRpcAccess".to_string(),
+            before: Some((i % 2 == 0).to_string()),
+            after: Some((i % 2 == 1).to_string()),
+        })
+        .collect();
+    for (i, entry) in entries.iter().enumerate() {
+        assert_eq!(append_audit_entry(entry.clone()), i as u64);
+    }
+    assert_eq!(get_audit_entries(0, 2), entries[0..2]);
+    assert_eq!(get_audit_entries(3, 10), entries[3..5]);
+    assert_eq!(get_audit_entries(5, 10), vec![]);
+    assert_eq!(get_audit_entries(u64::MAX, u64::MAX), vec![]);
+}
@@ -1,8 +1,11 @@
 use candid::Principal;
+use cketh_common::logs::INFO;
+use ic_canister_log::log;
+use ic_stable_structures::Storable;
 
 use crate::*;
 
-pub fn is_authorized(principal: &Principal, auth: Auth) -> bool {
+fn has_auth(principal: &Principal, auth: Auth) -> bool {
     AUTH.with(|a| {
         if let Some(v) = a.borrow().get(&PrincipalStorable(*principal)) {
             v.is_authorized(auth)
@@ -12,6 +15,38 @@ pub fn is_authorized(principal: &Principal, auth: Auth) -> bool {
     })
 }
 
+pub fn get_auth_grant(principal: &Principal, auth: Auth) -> Option<AuthGrant> {
+    AUTH_GRANTS.with(|g| {
+        g.borrow()
+            .get(&PrincipalStorable(*principal))?
+            .get(auth)
+            .cloned()
+    })
+}
+
+pub fn is_authorized(principal: &Principal, auth: Auth) -> bool {
+    has_auth(principal, auth)
+        && match get_auth_grant(principal, auth) {
+            Some(grant) => !grant.is_expired(ic_cdk::api::time()),
+            None => true,
+        }
+}
+
+/// Checks whether the principal is this canister, which makes RPC calls from timers
+/// (e.g. to track chain heads) and is therefore exempt from RPC permissions and quotas.
+pub fn is_canister_self(principal: &Principal) -> bool {
+    cfg!(target_arch = "wasm32") && *principal == ic_cdk::id()
+}
+
+/// Checks an authorization, including its scope, for a specific RPC request.
+pub fn is_authorized_for(principal: &Principal, auth: Auth, context: &AuthContext) -> bool {
+    is_authorized(principal, auth)
+        && match get_auth_grant(principal, auth).and_then(|grant| grant.scope) {
+            Some(scope) => scope.allows(context),
+            None => true,
+        }
+}
+
 pub fn require_manage_or_controller() -> Result<(), String> {
     let caller = ic_cdk::caller();
     if is_authorized(&caller, Auth::Manage) || ic_cdk::api::is_controller(&caller) {
@@ -21,6 +56,23 @@ pub fn require_manage_or_controller() -> Result<(), String> {
     }
 }
 
+/// Allows principals which may make RPC calls, as well as managers and controllers.
+pub fn require_rpc_access_or_manage() -> Result<(), String> {
+    if is_rpc_allowed(&ic_cdk::caller()) {
+        Ok(())
+    } else {
+        require_manage_or_controller()
+    }
+}
+
+pub fn require_controller() -> Result<(), String> {
+    if ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        Ok(())
+    } else {
+        Err("You are not authorized".to_string())
+    }
+}
+
 pub fn require_register_provider() -> Result<(), String> {
     if is_authorized(&ic_cdk::caller(), Auth::RegisterProvider) {
         Ok(())
@@ -30,14 +82,52 @@ pub fn require_register_provider() -> Result<(), String> {
 }
 
 pub fn is_rpc_allowed(caller: &Principal) -> bool {
-    METADATA.with(|m| m.borrow().get().open_rpc_access) || is_authorized(caller, Auth::PriorityRpc)
+    is_canister_self(caller)
+        || METADATA.with(|m| m.borrow().get().open_rpc_access)
+        || is_authorized(caller, Auth::PriorityRpc)
+        // Principals with a dedicated quota have limited access
+        || get_quota(&QuotaTarget::Principal(*caller)).is_some()
+}
+
+/// Same as `is_rpc_allowed`, additionally taking into account the scope of `Auth::PriorityRpc`.
+pub fn is_rpc_allowed_for(caller: &Principal, context: &AuthContext) -> bool {
+    is_canister_self(caller)
+        || METADATA.with(|m| m.borrow().get().open_rpc_access)
+        || is_authorized_for(caller, Auth::PriorityRpc, context)
+        || get_quota(&QuotaTarget::Principal(*caller)).is_some()
 }
 
 pub fn do_authorize(principal: Principal, auth: Auth) -> bool {
+    do_authorize_with(principal, auth, None, None)
+}
+
+/// Authorizes a principal, optionally until the given timestamp and within the given scope.
+pub fn do_authorize_with(
+    principal: Principal,
+    auth: Auth,
+    expires_at: Option<u64>,
+    scope: Option<AuthScope>,
+) -> bool {
     if principal == Principal::anonymous() {
         false
     } else {
-        AUTH.with(|a| {
+        let grant = if expires_at.is_some() || scope.is_some() {
+            Some(AuthGrant {
+                auth,
+                expires_at,
+                scope,
+            })
+        } else {
+            None
+        };
+        let mut grants = AUTH_GRANTS
+            .with(|g| g.borrow().get(&PrincipalStorable(principal)))
+            .unwrap_or_default();
+        grants.set(auth, grant.clone());
+        if grants.to_bytes().len() > AUTH_GRANTS_STORABLE_MAX_SIZE as usize {
+            ic_cdk::trap("Authorization scope exceeds the maximum size");
+        }
+        let authorized = AUTH.with(|a| {
             let mut auth_map = a.borrow_mut();
             let principal = PrincipalStorable(principal);
             let mut auth_set = auth_map.get(&principal).unwrap_or_default();
@@ -47,11 +137,29 @@ pub fn do_authorize(principal: Principal, auth: Auth) -> bool {
             } else {
                 false
             }
-        })
+        });
+        let grant_changed = set_auth_grant(principal, auth, grant);
+        authorized || grant_changed
     }
 }
 
+fn set_auth_grant(principal: Principal, auth: Auth, grant: Option<AuthGrant>) -> bool {
+    AUTH_GRANTS.with(|g| {
+        let mut grant_map = g.borrow_mut();
+        let principal = PrincipalStorable(principal);
+        let mut grants = grant_map.get(&principal).unwrap_or_default();
+        let changed = grants.set(auth, grant);
+        if grants.0.is_empty() {
+            grant_map.remove(&principal);
+        } else if changed {
+            grant_map.insert(principal, grants);
+        }
+        changed
+    })
+}
+
 pub fn do_deauthorize(principal: Principal, auth: Auth) -> bool {
+    set_auth_grant(principal, auth, None);
     AUTH.with(|a| {
         let mut auth_map = a.borrow_mut();
         let principal = PrincipalStorable(principal);
@@ -69,6 +177,42 @@ pub fn do_deauthorize(principal: Principal, auth: Auth) -> bool {
     })
 }
 
+/// Removes all authorizations which expired before the given timestamp, recording each removal
+/// in the audit log as an action of this canister.
+pub fn prune_expired_authorizations(canister_id: Principal, now: u64) -> usize {
+    let expired: Vec<_> = AUTH_GRANTS.with(|g| {
+        g.borrow()
+            .iter()
+            .flat_map(|(principal, grants)| {
+                grants
+                    .0
+                    .into_iter()
+                    .filter(|grant| grant.is_expired(now))
+                    .map(move |grant| (principal.0, grant.auth))
+            })
+            .collect()
+    });
+    for (principal, auth) in &expired {
+        log!(
+            INFO,
+            "Removing expired `{:?}` authorization for principal: {}",
+            auth,
+            principal
+        );
+        let before = describe_authorizations(principal);
+        do_deauthorize(*principal, *auth);
+        append_audit_entry(AuditLogEntry {
+            timestamp: now,
+            caller: canister_id,
+            action: "expireAuthorization".to_string(),
+            target: principal.to_string(),
+            before,
+            after: describe_authorizations(principal),
+        });
+    }
+    expired.len()
+}
+
 #[test]
 fn test_authorization() {
     let principal1 =
@@ -102,3 +246,111 @@ fn test_authorization() {
     assert!(!is_authorized(&principal2, Auth::PriorityRpc));
     assert!(!is_authorized(&principal2, Auth::RegisterProvider));
 }
+
+#[test]
+fn test_expiring_authorization() {
+    let principal =
+        Principal::from_text("k5dlc-ijshq-lsyre-qvvpq-2bnxr-pb26c-ag3sc-t6zo5-rdavy-recje-zqe")
+            .unwrap();
+    assert!(do_authorize_with(
+        principal,
+        Auth::PriorityRpc,
+        Some(1000),
+        None
+    ));
+    assert!(do_authorize(principal, Auth::FreeRpc));
+    assert_eq!(
+        get_auth_grant(&principal, Auth::PriorityRpc).map(|grant| grant.expires_at),
+        Some(Some(1000))
+    );
+    assert_eq!(get_auth_grant(&principal, Auth::FreeRpc), None);
+
+    // Extending an authorization replaces the grant
+    assert!(do_authorize_with(
+        principal,
+        Auth::PriorityRpc,
+        Some(2000),
+        None
+    ));
+    assert!(!do_authorize_with(
+        principal,
+        Auth::PriorityRpc,
+        Some(2000),
+        None
+    ));
+    let canister_id = Principal::management_canister();
+    assert_eq!(prune_expired_authorizations(canister_id, 1999), 0);
+    assert_eq!(prune_expired_authorizations(canister_id, 2000), 1);
+    let entry = get_audit_entries(0, 1).pop().unwrap();
+    assert_eq!(entry.caller, canister_id);
+    assert_eq!(entry.action, "expireAuthorization");
+    assert_eq!(entry.target, principal.to_string());
+    assert_eq!(get_auth_grant(&principal, Auth::PriorityRpc), None);
+    assert!(!has_auth(&principal, Auth::PriorityRpc));
+    assert!(is_authorized(&principal, Auth::FreeRpc));
+}
+
+#[test]
+fn test_scoped_authorization() {
+    let principal =
+        Principal::from_text("yxhtl-jlpgx-wqnzc-ysego-h6yqe-3zwfo-o3grn-gvuhm-nz3kv-ainub-6ae")
+            .unwrap();
+    do_authorize_with(
+        principal,
+        Auth::FreeRpc,
+        None,
+        Some(AuthScope {
+            chain_ids: Some(vec![ETH_MAINNET_CHAIN_ID]),
+            methods: Some(vec!["eth_getLogs".to_string()]),
+            provider_ids: None,
+        }),
+    );
+    assert!(is_authorized_for(
+        &principal,
+        Auth::FreeRpc,
+        &AuthContext {
+            chain_id: Some(ETH_MAINNET_CHAIN_ID),
+            method: "eth_getLogs",
+            provider_id: Some(3),
+        }
+    ));
+    assert!(!is_authorized_for(
+        &principal,
+        Auth::FreeRpc,
+        &AuthContext {
+            chain_id: Some(ETH_SEPOLIA_CHAIN_ID),
+            method: "eth_getLogs",
+            provider_id: Some(3),
+        }
+    ));
+    assert!(!is_authorized_for(
+        &principal,
+        Auth::FreeRpc,
+        &AuthContext {
+            chain_id: Some(ETH_MAINNET_CHAIN_ID),
+            method: "eth_sendRawTransaction",
+            provider_id: Some(3),
+        }
+    ));
+    assert!(!is_authorized_for(
+        &principal,
+        Auth::FreeRpc,
+        &AuthContext {
+            chain_id: None,
+            method: "eth_getLogs",
+            provider_id: None,
+        }
+    ));
+
+    // Re-authorizing without a scope removes the restrictions
+    do_authorize(principal, Auth::FreeRpc);
+    assert!(is_authorized_for(
+        &principal,
+        Auth::FreeRpc,
+        &AuthContext {
+            chain_id: None,
+            method: "eth_sendRawTransaction",
+            provider_id: None,
+        }
+    ));
+}
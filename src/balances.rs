@@ -0,0 +1,136 @@
+use candid::{CandidType, Principal};
+use cketh_common::{
+    eth_rpc::{ProviderError, RpcError},
+    logs::INFO,
+};
+use ic_canister_log::log;
+
+use crate::*;
+
+/// Returns the prepaid cycles balance of a principal.
+pub fn get_balance(principal: &Principal) -> u128 {
+    BALANCES.with(|b| {
+        b.borrow()
+            .get(&PrincipalStorable(*principal))
+            .unwrap_or_default()
+            .0
+    })
+}
+
+fn set_balance(principal: Principal, balance: u128) {
+    BALANCES.with(|b| {
+        let mut balances = b.borrow_mut();
+        if balance == 0 {
+            balances.remove(&PrincipalStorable(principal));
+        } else {
+            balances.insert(PrincipalStorable(principal), CyclesStorable(balance));
+        }
+    });
+}
+
+/// Adds cycles to the prepaid balance of a principal and returns the new balance.
+pub fn do_deposit(principal: Principal, amount: u128) -> u128 {
+    if principal == Principal::anonymous() {
+        ic_cdk::trap("Cannot deposit cycles for the anonymous principal");
+    }
+    let balance = get_balance(&principal)
+        .checked_add(amount)
+        .expect("Cycles balance overflow");
+    set_balance(principal, balance);
+    balance
+}
+
+/// Deducts cycles from the prepaid balance of a principal.
+/// Returns the current balance as an error if it does not cover the given amount.
+pub fn charge_balance(principal: &Principal, amount: u128) -> Result<(), u128> {
+    let balance = get_balance(principal);
+    if balance < amount {
+        return Err(balance);
+    }
+    set_balance(*principal, balance - amount);
+    Ok(())
+}
+
+/// Charges a caller for a request, using the cycles attached to the call if they suffice and
+/// the caller's prepaid balance otherwise.
+pub fn charge_cycles(caller: &Principal, cycles_cost: u128) -> Result<(), RpcError> {
+    // Background tasks of the canister pay for their outcalls from the canister's own cycles
+    if is_canister_self(caller) {
+        return Ok(());
+    }
+    let cycles_available = ic_cdk::api::call::msg_cycles_available128();
+    if cycles_available >= cycles_cost {
+        ic_cdk::api::call::msg_cycles_accept128(cycles_cost);
+        return Ok(());
+    }
+    // Both the attached cycles and the prepaid balance fall short of the cost: report the
+    // larger of the two
+    charge_balance(caller, cycles_cost).map_err(|balance| {
+        ProviderError::TooFewCycles {
+            expected: cycles_cost,
+            received: cycles_available.max(balance),
+        }
+        .into()
+    })
+}
+
+/// Sends cycles from the caller's prepaid balance to the given canister.
+pub async fn do_withdraw_balance(caller: Principal, amount: u128, canister_id: Principal) {
+    if caller == Principal::anonymous() {
+        ic_cdk::trap("Cannot withdraw the balance of the anonymous principal");
+    }
+    if amount < MINIMUM_WITHDRAWAL_CYCLES {
+        ic_cdk::trap("Too few cycles to withdraw");
+    }
+    if charge_balance(&caller, amount).is_err() {
+        ic_cdk::trap("Insufficient cycles balance");
+    }
+    log!(
+        INFO,
+        "[{}] Withdrawing {} prepaid cycles to canister: {}",
+        caller,
+        amount,
+        canister_id,
+    );
+    #[derive(CandidType)]
+    struct DepositCyclesArgs {
+        canister_id: Principal,
+    }
+    if let Err(err) = ic_cdk::api::call::call_with_payment128::<_, ()>(
+        Principal::management_canister(),
+        "deposit_cycles",
+        (DepositCyclesArgs { canister_id },),
+        amount,
+    )
+    .await
+    {
+        // Refund on failure to send cycles
+        log!(
+            INFO,
+            "[{}] Unable to send {} prepaid cycles: {:?}",
+            canister_id,
+            amount,
+            err
+        );
+        do_deposit(caller, amount);
+    }
+}
+
+#[test]
+fn test_prepaid_balance() {
+    let principal =
+        Principal::from_text("k5dlc-ijshq-lsyre-qvvpq-2bnxr-pb26c-ag3sc-t6zo5-rdavy-recje-zqe")
+            .unwrap();
+    assert_eq!(get_balance(&principal), 0);
+    assert_eq!(charge_balance(&principal, 1), Err(0));
+
+    assert_eq!(do_deposit(principal, 1000), 1000);
+    assert_eq!(do_deposit(principal, 500), 1500);
+    assert_eq!(charge_balance(&principal, 2000), Err(1500));
+    assert_eq!(charge_balance(&principal, 1000), Ok(()));
+    assert_eq!(get_balance(&principal), 500);
+
+    assert_eq!(charge_balance(&principal, 500), Ok(()));
+    assert_eq!(get_balance(&principal), 0);
+    assert!(BALANCES.with(|b| b.borrow().is_empty()));
+}
@@ -0,0 +1,257 @@
+use candid::{CandidType, Decode, Encode};
+use cketh_common::numeric::BlockNumber;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+pub fn get_response_cache_max_entries() -> u64 {
+    METADATA.with(|m| {
+        m.borrow()
+            .get()
+            .response_cache_max_entries
+            .unwrap_or_default()
+    })
+}
+
+pub fn is_response_cache_enabled() -> bool {
+    get_response_cache_max_entries() > 0
+}
+
+/// Sets the maximum number of cached responses (zero disables the cache), evicting the oldest
+/// entries if the cache is larger than the new limit.
+pub fn set_response_cache_max_entries(max_entries: u64) {
+    if max_entries > RESPONSE_CACHE_MAX_ENTRIES_LIMIT {
+        ic_cdk::trap(&format!(
+            "Response cache size is limited to {} entries",
+            RESPONSE_CACHE_MAX_ENTRIES_LIMIT
+        ));
+    }
+    METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        metadata.response_cache_max_entries = Some(max_entries);
+        m.borrow_mut().set(metadata).unwrap();
+    });
+    evict_response_cache_entries(max_entries);
+}
+
+pub fn get_response_cache_stats() -> ResponseCacheStats {
+    ResponseCacheStats {
+        entries: RESPONSE_CACHE.with(|c| c.borrow().len()),
+        max_entries: get_response_cache_max_entries(),
+    }
+}
+
+/// Returns the highest finalized block number seen in responses or tracked by the chain head tracker.
+pub fn get_finalized_block_number(chain_id: u64) -> Option<BlockNumber> {
+    let tracked = get_chain_head(chain_id)
+        .and_then(|head| head.finalized)
+        .map(|block| block.number);
+    UNSTABLE_FINALIZED_BLOCKS
+        .with(|b| b.borrow().get(&chain_id).copied())
+        .max(tracked)
+}
+
+/// Records the finalized head of a chain; the finalized head never moves backwards.
+pub fn update_finalized_block_number(chain_id: u64, block_number: BlockNumber) {
+    UNSTABLE_FINALIZED_BLOCKS.with(|b| {
+        let mut blocks = b.borrow_mut();
+        let finalized = blocks.entry(chain_id).or_insert(block_number);
+        if block_number > *finalized {
+            *finalized = block_number;
+        }
+    });
+}
+
+pub fn is_finalized_block(chain_id: u64, block_number: &BlockNumber) -> bool {
+    get_finalized_block_number(chain_id).map_or(false, |finalized| *block_number <= finalized)
+}
+
+/// Returns the cache key of a request to the given (sorted) providers, since providers may disagree.
+pub fn response_cache_key<P: CandidType>(
+    chain_id: u64,
+    provider_ids: &[u64],
+    method: RpcMethod,
+    params: &P,
+) -> ResponseCacheKey {
+    let mut hasher = Sha256::new();
+    hasher.update(method.name().as_bytes());
+    hasher.update([0]);
+    hasher.update((provider_ids.len() as u64).to_be_bytes());
+    for provider_id in provider_ids {
+        hasher.update(provider_id.to_be_bytes());
+    }
+    hasher.update(Encode!(params).expect("Unable to encode response cache parameters"));
+    let mut key = [0; RESPONSE_CACHE_KEY_SIZE as usize];
+    key[..8].copy_from_slice(&chain_id.to_be_bytes());
+    key[8..].copy_from_slice(&hasher.finalize());
+    ResponseCacheKey(key)
+}
+
+/// Returns `eth_getLogs` arguments with addresses and topics in lowercase.
+pub fn canonical_get_logs_args(args: &candid_types::GetLogsArgs) -> candid_types::GetLogsArgs {
+    candid_types::GetLogsArgs {
+        from_block: args.from_block.clone(),
+        to_block: args.to_block.clone(),
+        addresses: args.addresses.iter().map(|a| a.to_lowercase()).collect(),
+        topics: args.topics.as_ref().map(|topics| {
+            topics
+                .iter()
+                .map(|topic| topic.iter().map(|t| t.to_lowercase()).collect())
+                .collect()
+        }),
+    }
+}
+
+pub fn get_cached_response<T: CandidType + DeserializeOwned>(key: &ResponseCacheKey) -> Option<T> {
+    let entry = RESPONSE_CACHE.with(|c| c.borrow().get(key))?;
+    Decode!(&entry.data, T).ok()
+}
+
+/// Caches a response if the cache is enabled and the encoded response is small enough.
+pub fn insert_cached_response<T: CandidType>(key: ResponseCacheKey, value: &T) {
+    let max_entries = get_response_cache_max_entries();
+    if max_entries == 0 {
+        return;
+    }
+    let data = Encode!(value).expect("Unable to encode cached response");
+    if data.len() > RESPONSE_CACHE_ENTRY_MAX_SIZE as usize {
+        return;
+    }
+    let sequence = METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        let sequence = metadata.next_response_cache_sequence.unwrap_or_default();
+        metadata.next_response_cache_sequence = Some(sequence + 1);
+        m.borrow_mut().set(metadata).unwrap();
+        sequence
+    });
+    let previous = RESPONSE_CACHE.with(|c| {
+        c.borrow_mut()
+            .insert(key.clone(), ResponseCacheEntry { sequence, data })
+    });
+    RESPONSE_CACHE_ORDER.with(|o| {
+        let mut order = o.borrow_mut();
+        if let Some(previous) = previous {
+            order.remove(&previous.sequence);
+        }
+        order.insert(sequence, key);
+    });
+    evict_response_cache_entries(max_entries);
+}
+
+/// Removes the oldest cached responses until at most `max_entries` remain.
+fn evict_response_cache_entries(max_entries: u64) {
+    while RESPONSE_CACHE.with(|c| c.borrow().len()) > max_entries {
+        let oldest = RESPONSE_CACHE_ORDER.with(|o| o.borrow().iter().next());
+        match oldest {
+            Some((sequence, key)) => {
+                RESPONSE_CACHE_ORDER.with(|o| o.borrow_mut().remove(&sequence));
+                RESPONSE_CACHE.with(|c| c.borrow_mut().remove(&key));
+            }
+            None => break,
+        }
+    }
+}
+
+/// Removes the cached responses for the given chain (or all chains) and returns how many were removed.
+pub fn clear_response_cache(chain_id: Option<u64>) -> u64 {
+    let entries: Vec<(ResponseCacheKey, u64)> = RESPONSE_CACHE.with(|c| {
+        c.borrow()
+            .iter()
+            .filter(|(key, _)| chain_id.map_or(true, |id| key.chain_id() == id))
+            .map(|(key, entry)| (key, entry.sequence))
+            .collect()
+    });
+    for (key, sequence) in &entries {
+        RESPONSE_CACHE.with(|c| c.borrow_mut().remove(key));
+        RESPONSE_CACHE_ORDER.with(|o| o.borrow_mut().remove(sequence));
+    }
+    entries.len() as u64
+}
+
+#[test]
+fn test_response_cache_key() {
+    let args = |address: &str| candid_types::GetLogsArgs {
+        from_block: Some(candid_types::BlockTag::Number(BlockNumber::new(100))),
+        to_block: Some(candid_types::BlockTag::Number(BlockNumber::new(200))),
+        addresses: vec![address.to_string()],
+        topics: None,
+    };
+    let key = |chain_id, args: &candid_types::GetLogsArgs| {
+        response_cache_key(
+            chain_id,
+            &[0, 1],
+            RpcMethod::EthGetLogs,
+            &canonical_get_logs_args(args),
+        )
+    };
+    let lowercase = args("0xdac17f958d2ee523a2206206994597c13d831ec7");
+    let mixed_case = args("0xdAC17F958D2ee523a2206206994597C13D831ec7");
+    assert_eq!(key(1, &lowercase), key(1, &mixed_case));
+    assert_ne!(key(1, &lowercase), key(11155111, &lowercase));
+    assert_ne!(
+        key(1, &lowercase),
+        response_cache_key(1, &[0, 1], RpcMethod::EthGetBlockByNumber, &lowercase)
+    );
+    assert_ne!(
+        key(1, &lowercase),
+        response_cache_key(
+            1,
+            &[0],
+            RpcMethod::EthGetLogs,
+            &canonical_get_logs_args(&lowercase)
+        )
+    );
+    assert_eq!(key(11155111, &lowercase).chain_id(), 11155111);
+}
+
+#[test]
+fn test_response_cache() {
+    let key =
+        |chain_id, n: u64| response_cache_key(chain_id, &[0], RpcMethod::EthGetBlockByNumber, &n);
+
+    // Disabled by default
+    insert_cached_response(key(1, 1), &"a".to_string());
+    assert_eq!(get_cached_response::<String>(&key(1, 1)), None);
+
+    set_response_cache_max_entries(2);
+    insert_cached_response(key(1, 1), &"a".to_string());
+    insert_cached_response(key(1, 2), &"b".to_string());
+    assert_eq!(get_cached_response(&key(1, 1)), Some("a".to_string()));
+
+    // Evicts the oldest entry
+    insert_cached_response(key(2, 3), &"c".to_string());
+    assert_eq!(get_cached_response::<String>(&key(1, 1)), None);
+    assert_eq!(get_cached_response(&key(1, 2)), Some("b".to_string()));
+    assert_eq!(
+        get_response_cache_stats(),
+        ResponseCacheStats {
+            entries: 2,
+            max_entries: 2
+        }
+    );
+
+    // Skips responses which are too large
+    insert_cached_response(
+        key(1, 4),
+        &"x".repeat(RESPONSE_CACHE_ENTRY_MAX_SIZE as usize),
+    );
+    assert_eq!(get_cached_response::<String>(&key(1, 4)), None);
+
+    assert_eq!(clear_response_cache(Some(1)), 1);
+    assert_eq!(get_cached_response::<String>(&key(1, 2)), None);
+    assert_eq!(get_cached_response(&key(2, 3)), Some("c".to_string()));
+    assert_eq!(clear_response_cache(None), 1);
+    assert_eq!(get_response_cache_stats().entries, 0);
+}
+
+#[test]
+fn test_finalized_block_number() {
+    assert!(!is_finalized_block(1, &BlockNumber::new(10)));
+    update_finalized_block_number(1, BlockNumber::new(100));
+    update_finalized_block_number(1, BlockNumber::new(90));
+    assert_eq!(get_finalized_block_number(1), Some(BlockNumber::new(100)));
+    assert!(is_finalized_block(1, &BlockNumber::new(100)));
+    assert!(!is_finalized_block(1, &BlockNumber::new(101)));
+    assert!(!is_finalized_block(2, &BlockNumber::new(10)));
+}
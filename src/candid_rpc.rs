@@ -1,10 +1,12 @@
+use std::future::Future;
 use std::str::FromStr;
 
 use async_trait::async_trait;
+use candid::CandidType;
 use cketh_common::{
     eth_rpc::{
-        into_nat, Block, FeeHistory, GetLogsParam, Hash, LogEntry, ProviderError, RpcError,
-        SendRawTransactionResult, ValidationError,
+        into_nat, Block, BlockSpec, FeeHistory, GetLogsParam, Hash, HttpOutcallError, JsonRpcError,
+        LogEntry, ProviderError, RpcError, SendRawTransactionResult, ValidationError,
     },
     eth_rpc_client::{
         providers::{RpcApi, RpcService},
@@ -12,9 +14,12 @@ use cketh_common::{
         EthRpcClient as CkEthRpcClient, MultiCallError, RpcConfig, RpcTransport,
     },
     lifecycle::EthereumNetwork,
+    numeric::BlockNumber,
 };
-use ethers_core::{types::Transaction, utils::rlp};
+use ethers_core::types::{Address, Bytes, U256};
 use ic_cdk::api::management_canister::http_request::{CanisterHttpRequestArgument, HttpResponse};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
 
 use crate::*;
 
@@ -23,6 +28,12 @@ use self::candid_types::BlockTag;
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct CanisterTransport;
 
+/// Fields of an `eth_getTransactionByHash` response which are compared across providers.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+struct KnownTransaction {
+    hash: String,
+}
+
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 impl RpcTransport for CanisterTransport {
@@ -47,10 +58,28 @@ impl RpcTransport for CanisterTransport {
             effective_response_size_estimate,
         );
         let rpc_method = MetricRpcMethod(method.to_string());
-        do_http_request(ic_cdk::caller(), rpc_method, service, request, cycles_cost).await
+        do_http_request(rpc_caller(), rpc_method, service, request, cycles_cost).await
     }
 }
 
+/// Returns the principal which is authorized and charged for RPC calls. This is the caller,
+/// unless the calls are made on behalf of another principal (e.g. for a subscription).
+pub fn rpc_caller() -> Principal {
+    UNSTABLE_RPC_CALLER
+        .with(|c| c.get())
+        .unwrap_or_else(ic_cdk::caller)
+}
+
+/// Returns the chain ID of the RPC services being called, if known.
+pub fn rpc_chain_id() -> Option<u64> {
+    UNSTABLE_RPC_CHAIN_ID.with(|c| c.get())
+}
+
+/// Makes the RPC calls of a future on behalf of the given principal.
+pub async fn with_rpc_caller<F: Future>(caller: Principal, future: F) -> F::Output {
+    WithContext::new(&UNSTABLE_RPC_CALLER, caller, future).await
+}
+
 fn check_services<T>(services: Vec<T>) -> RpcResult<Vec<T>> {
     if services.is_empty() {
         Err(ProviderError::ProviderNotFound)?;
@@ -58,82 +87,286 @@ fn check_services<T>(services: Vec<T>) -> RpcResult<Vec<T>> {
     Ok(services)
 }
 
-fn get_rpc_client(
-    source: RpcServices,
-    config: RpcConfig,
-) -> RpcResult<CkEthRpcClient<CanisterTransport>> {
-    if !is_rpc_allowed(&ic_cdk::caller()) {
-        add_metric!(err_no_permission, 1);
-        return Err(ProviderError::NoPermission.into());
-    }
+fn get_rpc_services(source: RpcServices) -> RpcResult<(EthereumNetwork, Vec<RpcService>)> {
     Ok(match source {
-        RpcServices::EthMainnet(services) => CkEthRpcClient::new(
+        RpcServices::EthMainnet(services) => (
             EthereumNetwork::MAINNET,
-            Some(
-                check_services(services.unwrap_or_else(|| DEFAULT_ETH_MAINNET_SERVICES.to_vec()))?
-                    .into_iter()
-                    .map(RpcService::EthMainnet)
-                    .collect(),
-            ),
-            config,
+            check_services(services.unwrap_or_else(|| DEFAULT_ETH_MAINNET_SERVICES.to_vec()))?
+                .into_iter()
+                .map(RpcService::EthMainnet)
+                .collect(),
         ),
-        RpcServices::EthSepolia(services) => CkEthRpcClient::new(
+        RpcServices::EthSepolia(services) => (
             EthereumNetwork::SEPOLIA,
-            Some(
-                check_services(services.unwrap_or_else(|| DEFAULT_ETH_SEPOLIA_SERVICES.to_vec()))?
-                    .into_iter()
-                    .map(RpcService::EthSepolia)
-                    .collect(),
-            ),
-            config,
+            check_services(services.unwrap_or_else(|| DEFAULT_ETH_SEPOLIA_SERVICES.to_vec()))?
+                .into_iter()
+                .map(RpcService::EthSepolia)
+                .collect(),
         ),
-        RpcServices::Custom { chain_id, services } => CkEthRpcClient::new(
+        RpcServices::Custom { chain_id, services } => (
             EthereumNetwork(chain_id),
-            Some(
-                check_services(services)?
-                    .into_iter()
-                    .map(RpcService::Custom)
-                    .collect(),
-            ),
-            config,
+            check_services(services)?
+                .into_iter()
+                .map(RpcService::Custom)
+                .collect(),
         ),
     })
 }
 
+/// Returns the sorted IDs of the providers of the given services, or `None` if a service
+/// does not resolve to a registered provider.
+fn resolve_provider_ids(services: &[RpcService]) -> Option<Vec<u64>> {
+    let mut provider_ids = services
+        .iter()
+        .map(|service| match resolve_rpc_service(service.clone()) {
+            Ok(ResolvedRpcService::Provider(provider)) => Some(provider.provider_id),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    provider_ids.sort_unstable();
+    provider_ids.dedup();
+    Some(provider_ids)
+}
+
+fn get_rpc_client(
+    network: EthereumNetwork,
+    services: Vec<RpcService>,
+    config: RpcConfig,
+) -> RpcResult<CkEthRpcClient<CanisterTransport>> {
+    if !is_rpc_allowed(&rpc_caller()) {
+        add_metric!(err_no_permission, 1);
+        return Err(ProviderError::NoPermission.into());
+    }
+    check_caller_quota(&rpc_caller())?;
+    Ok(CkEthRpcClient::new(network, Some(services), config))
+}
+
+/// Parses the result of a JSON-RPC response. A `null` result is passed on to `R` (e.g. for an
+/// unknown transaction), whereas a missing result is an error.
+fn parse_json_rpc_response<R: DeserializeOwned>(response: HttpResponse) -> RpcResult<R> {
+    fn present<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<serde_json::Value>, D::Error> {
+        serde_json::Value::deserialize(deserializer).map(Some)
+    }
+
+    #[derive(Deserialize)]
+    struct JsonRpcReply {
+        #[serde(default, deserialize_with = "present")]
+        result: Option<serde_json::Value>,
+        error: Option<JsonRpcError>,
+    }
+
+    let status = get_http_response_status(response.status.clone());
+    let body = get_http_response_body(response)?;
+    let invalid_response = |parsing_error| HttpOutcallError::InvalidHttpJsonRpcResponse {
+        status,
+        body: body.clone(),
+        parsing_error,
+    };
+    if !(200..300).contains(&status) {
+        return Err(invalid_response(None).into());
+    }
+    match serde_json::from_str::<JsonRpcReply>(&body) {
+        Ok(JsonRpcReply {
+            error: Some(error), ..
+        }) => Err(RpcError::JsonRpcError(error)),
+        Ok(JsonRpcReply {
+            result: Some(result),
+            ..
+        }) => serde_json::from_value(result)
+            .map_err(|err| invalid_response(Some(err.to_string())).into()),
+        Ok(_) => Err(invalid_response(Some("missing result".to_string())).into()),
+        Err(err) => Err(invalid_response(Some(err.to_string())).into()),
+    }
+}
+
 fn process_result<T>(method: RpcMethod, result: Result<T, MultiCallError<T>>) -> MultiRpcResult<T> {
     match result {
         Ok(value) => MultiRpcResult::Consistent(Ok(value)),
         Err(err) => match err {
             MultiCallError::ConsistentError(err) => MultiRpcResult::Consistent(Err(err)),
             MultiCallError::InconsistentResults(multi_call_results) => {
-                multi_call_results.results.iter().for_each(|(service, _)| {
-                    if let Ok(ResolvedRpcService::Provider(provider)) =
-                        resolve_rpc_service(service.clone())
-                    {
-                        add_metric_entry!(
-                            inconsistent_responses,
-                            (method.into(), MetricRpcHost(provider.hostname)),
-                            1
-                        )
-                    }
-                });
+                add_inconsistent_response_metrics(
+                    method,
+                    multi_call_results
+                        .results
+                        .iter()
+                        .map(|(service, _)| service),
+                );
                 MultiRpcResult::Inconsistent(multi_call_results.results.into_iter().collect())
             }
         },
     }
 }
 
+/// Records the providers which returned inconsistent responses.
+fn add_inconsistent_response_metrics<'a>(
+    method: RpcMethod,
+    services: impl Iterator<Item = &'a RpcService>,
+) {
+    services.for_each(|service| {
+        if let Ok(ResolvedRpcService::Provider(provider)) = resolve_rpc_service(service.clone()) {
+            add_metric_entry!(
+                inconsistent_responses,
+                (method.into(), MetricRpcHost(provider.hostname)),
+                1
+            )
+        }
+    });
+}
+
+/// Records the errors in a result which is returned to the caller.
+pub fn add_rpc_error_metrics<T>(caller: Principal, method: RpcMethod, result: &MultiRpcResult<T>) {
+    match result {
+        MultiRpcResult::Consistent(Err(err)) => add_rpc_error_metric(caller, method.into(), err),
+        MultiRpcResult::Consistent(Ok(_)) => {}
+        MultiRpcResult::Inconsistent(results) => results
+            .iter()
+            .filter_map(|(_, result)| result.as_ref().err())
+            .for_each(|err| add_rpc_error_metric(caller, method.into(), err)),
+    }
+}
+
+pub fn add_rpc_error_metric(caller: Principal, method: MetricRpcMethod, error: &RpcError) {
+    add_metric_entry!(err_rpc, (method, error.into()), 1);
+    add_caller_usage(caller, |usage| usage.errors += 1);
+}
+
 pub struct CandidRpcClient {
     client: CkEthRpcClient<CanisterTransport>,
+    /// Services called for JSON-RPC methods which are not supported by the client.
+    services: Vec<RpcService>,
+    response_size_estimate: Option<u64>,
+    /// Chain ID of the services, against which signed transactions are validated.
+    chain_id: u64,
+    /// Chain ID used for the response cache, which only holds results from built-in providers.
+    cache_chain_id: Option<u64>,
+    /// Sorted IDs of the providers called, which are part of the response cache key.
+    cache_provider_ids: Vec<u64>,
 }
 
 impl CandidRpcClient {
     pub fn new(source: RpcServices, config: Option<RpcConfig>) -> RpcResult<Self> {
+        let cache_chain_id = match source {
+            RpcServices::EthMainnet(_) | RpcServices::EthSepolia(_) => Some(source.chain_id()),
+            RpcServices::Custom { .. } => None,
+        };
+        let chain_id = source.chain_id();
+        let config = config.unwrap_or_default();
+        let (network, services) = get_rpc_services(source)?;
+        let provider_ids = resolve_provider_ids(&services);
+        let cache_chain_id = cache_chain_id.filter(|_| provider_ids.is_some());
         Ok(Self {
-            client: get_rpc_client(source, config.unwrap_or_default())?,
+            response_size_estimate: config.response_size_estimate,
+            client: get_rpc_client(network, services.clone(), config)?,
+            services,
+            chain_id,
+            cache_chain_id,
+            cache_provider_ids: provider_ids.unwrap_or_default(),
         })
     }
 
+    /// Calls a JSON-RPC method which is not supported by the client on all services in parallel,
+    /// and compares their results.
+    ///
+    /// The ckETH client comes from the `ic-cketh-minter` crate pinned in `Cargo.toml` and only
+    /// implements the methods needed by the minter, so other methods cannot be added to it from
+    /// this canister. Results are compared like the client does (all providers must return equal
+    /// results) and inconsistencies are recorded like in `process_result`.
+    async fn multi_json_rpc_call<P: Serialize, R: DeserializeOwned + PartialEq>(
+        &self,
+        method: RpcMethod,
+        params: P,
+        default_response_size: u64,
+    ) -> MultiRpcResult<R> {
+        let payload = serde_json::to_string(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method.name(),
+            "params": params,
+        }))
+        .expect("Unable to serialize JSON-RPC request");
+        let max_response_bytes = self.response_size_estimate.unwrap_or(default_response_size);
+        let calls = futures::future::join_all(self.services.iter().map(|service| {
+            let payload = &payload;
+            async move {
+                let result = match resolve_rpc_service(service.clone()) {
+                    Ok(resolved) => do_json_rpc_request(
+                        rpc_caller(),
+                        resolved,
+                        method.into(),
+                        payload,
+                        max_response_bytes,
+                    )
+                    .await
+                    .and_then(parse_json_rpc_response),
+                    Err(err) => Err(err.into()),
+                };
+                (service.clone(), result)
+            }
+        }));
+        let results = self.with_chain_id(calls).await;
+        let consistent = results.iter().all(|(_, result)| result == &results[0].1);
+        if consistent {
+            let (_, result) = results.into_iter().next().expect("No RPC services");
+            return MultiRpcResult::Consistent(result);
+        }
+        add_inconsistent_response_metrics(method, results.iter().map(|(service, _)| service));
+        MultiRpcResult::Inconsistent(results)
+    }
+
+    /// Makes the RPC calls of a future with the chain ID of the services, which is checked against
+    /// the scope of authorizations when calling custom services.
+    async fn with_chain_id<F: Future>(&self, future: F) -> F::Output {
+        WithContext::new(&UNSTABLE_RPC_CHAIN_ID, self.chain_id, future).await
+    }
+
+    /// Returns the response cache key for a request if the response cache is enabled.
+    fn cache_key<P: CandidType>(&self, method: RpcMethod, params: &P) -> Option<ResponseCacheKey> {
+        let chain_id = self.cache_chain_id?;
+        if !is_response_cache_enabled() {
+            return None;
+        }
+        Some(response_cache_key(
+            chain_id,
+            &self.cache_provider_ids,
+            method,
+            params,
+        ))
+    }
+
+    fn is_finalized(&self, block_number: &BlockNumber) -> bool {
+        self.cache_chain_id
+            .map_or(false, |chain_id| is_finalized_block(chain_id, block_number))
+    }
+
+    fn is_finalized_tag(&self, block: &Option<BlockTag>) -> bool {
+        matches!(block, Some(BlockTag::Number(n)) if self.is_finalized(n))
+    }
+
+    fn get_cached<T: CandidType + DeserializeOwned>(
+        &self,
+        method: RpcMethod,
+        key: &Option<ResponseCacheKey>,
+    ) -> Option<MultiRpcResult<T>> {
+        let key = key.as_ref()?;
+        match get_cached_response(key) {
+            Some(value) => {
+                add_metric_entry!(cache_hits, method.into(), 1);
+                // Cached responses are subject to the same permissions, charges and quotas as outcalls
+                let result =
+                    charge_cached_response(rpc_caller(), method.into(), &self.cache_provider_ids)
+                        .map(|_| value);
+                Some(MultiRpcResult::Consistent(result))
+            }
+            None => {
+                add_metric_entry!(cache_misses, method.into(), 1);
+                None
+            }
+        }
+    }
+
     pub async fn eth_get_logs(
         &self,
         args: candid_types::GetLogsArgs,
@@ -151,53 +384,123 @@ impl CandidRpcClient {
                 .into()));
             }
         }
+        let cache_key =
+            if self.is_finalized_tag(&args.from_block) && self.is_finalized_tag(&args.to_block) {
+                self.cache_key(RpcMethod::EthGetLogs, &canonical_get_logs_args(&args))
+            } else {
+                None
+            };
+        if let Some(result) = self.get_cached(RpcMethod::EthGetLogs, &cache_key) {
+            return result;
+        }
         let args: GetLogsParam = match args.try_into() {
             Ok(args) => args,
             Err(err) => return MultiRpcResult::Consistent(Err(RpcError::from(err))),
         };
-        process_result(RpcMethod::EthGetLogs, self.client.eth_get_logs(args).await)
+        let result = process_result(
+            RpcMethod::EthGetLogs,
+            self.with_chain_id(self.client.eth_get_logs(args)).await,
+        );
+        if let (Some(key), MultiRpcResult::Consistent(Ok(logs))) = (cache_key, &result) {
+            insert_cached_response(key, logs);
+        }
+        result
     }
 
     pub async fn eth_get_block_by_number(
         &self,
         block: candid_types::BlockTag,
     ) -> MultiRpcResult<Block> {
-        process_result(
+        let cache_key = match &block {
+            BlockTag::Number(n) if self.is_finalized(n) => {
+                self.cache_key(RpcMethod::EthGetBlockByNumber, n)
+            }
+            _ => None,
+        };
+        if let Some(result) = self.get_cached(RpcMethod::EthGetBlockByNumber, &cache_key) {
+            return result;
+        }
+        let is_finalized_tag = block == BlockTag::Finalized;
+        let result = process_result(
             RpcMethod::EthGetBlockByNumber,
-            self.client.eth_get_block_by_number(block.into()).await,
-        )
+            self.with_chain_id(self.client.eth_get_block_by_number(block.into()))
+                .await,
+        );
+        if let MultiRpcResult::Consistent(Ok(block)) = &result {
+            // Only learn the finalized head from the consensus of several providers
+            if let (true, Some(chain_id)) = (is_finalized_tag, self.cache_chain_id) {
+                if self.cache_provider_ids.len() > 1 {
+                    update_finalized_block_number(chain_id, block.number);
+                }
+            }
+            if let Some(key) = cache_key {
+                insert_cached_response(key, block);
+            }
+        }
+        result
     }
 
     pub async fn eth_get_transaction_receipt(
         &self,
         hash: String,
     ) -> MultiRpcResult<Option<candid_types::TransactionReceipt>> {
-        match Hash::from_str(&hash) {
-            Ok(hash) => process_result(
-                RpcMethod::EthGetTransactionReceipt,
-                self.client.eth_get_transaction_receipt(hash).await,
-            )
-            .map(|option| option.map(|r| r.into())),
-            Err(_) => MultiRpcResult::Consistent(Err(ValidationError::InvalidHex(hash).into())),
+        let hash = match Hash::from_str(&hash) {
+            Ok(hash) => hash,
+            Err(_) => {
+                return MultiRpcResult::Consistent(Err(ValidationError::InvalidHex(hash).into()))
+            }
+        };
+        let cache_key =
+            self.cache_key(RpcMethod::EthGetTransactionReceipt, &format!("{:#x}", hash));
+        if let Some(result) = self.get_cached(RpcMethod::EthGetTransactionReceipt, &cache_key) {
+            return result.map(Some);
+        }
+        let result = process_result(
+            RpcMethod::EthGetTransactionReceipt,
+            self.with_chain_id(self.client.eth_get_transaction_receipt(hash))
+                .await,
+        )
+        .map(|option| option.map(candid_types::TransactionReceipt::from));
+        if let (Some(key), MultiRpcResult::Consistent(Ok(Some(receipt)))) = (cache_key, &result) {
+            if self.is_finalized(&receipt.block_number) {
+                insert_cached_response(key, receipt);
+            }
         }
+        result
     }
 
     pub async fn eth_get_transaction_count(
         &self,
         args: candid_types::GetTransactionCountArgs,
     ) -> MultiRpcResult<candid::Nat> {
+        let cache_key = match &args.block {
+            BlockTag::Number(n) if self.is_finalized(n) => self.cache_key(
+                RpcMethod::EthGetTransactionCount,
+                &candid_types::GetTransactionCountArgs {
+                    address: args.address.to_lowercase(),
+                    block: args.block.clone(),
+                },
+            ),
+            _ => None,
+        };
+        if let Some(result) = self.get_cached(RpcMethod::EthGetTransactionCount, &cache_key) {
+            return result;
+        }
         let args: GetTransactionCountParams = match args.try_into() {
             Ok(args) => args,
             Err(err) => return MultiRpcResult::Consistent(Err(RpcError::from(err))),
         };
-        process_result(
+        let result = process_result(
             RpcMethod::EthGetTransactionCount,
-            self.client
-                .eth_get_transaction_count(args)
+            self.with_chain_id(self.client.eth_get_transaction_count(args))
                 .await
                 .reduce_with_equality(),
         )
-        .map(|count| into_nat(count.into_inner()))
+        .map(|count| into_nat(count.into_inner()));
+        if let (Some(key), MultiRpcResult::Consistent(Ok(count))) = (cache_key, &result) {
+            insert_cached_response(key, count);
+        }
+        result
     }
 
     pub async fn eth_fee_history(
@@ -206,38 +509,136 @@ impl CandidRpcClient {
     ) -> MultiRpcResult<Option<FeeHistory>> {
         process_result(
             RpcMethod::EthFeeHistory,
-            self.client.eth_fee_history(args.into()).await,
+            self.with_chain_id(self.client.eth_fee_history(args.into()))
+                .await,
         )
         .map(|history| history.into())
     }
 
+    pub async fn eth_blob_base_fee(&self) -> MultiRpcResult<U256> {
+        self.multi_json_rpc_call(
+            RpcMethod::EthBlobBaseFee,
+            json!([]),
+            ETH_BLOB_BASE_FEE_RESPONSE_SIZE_ESTIMATE,
+        )
+        .await
+    }
+
+    /// Returns whether the providers know a transaction, either pending in their mempool or
+    /// included in a block.
+    pub async fn eth_get_transaction_by_hash(&self, hash: String) -> MultiRpcResult<bool> {
+        self.multi_json_rpc_call(
+            RpcMethod::EthGetTransactionByHash,
+            json!([hash]),
+            ETH_GET_TRANSACTION_BY_HASH_RESPONSE_SIZE_ESTIMATE,
+        )
+        .await
+        .map(|transaction: Option<KnownTransaction>| transaction.is_some())
+    }
+
+    /// Executes a read-only call of a contract, returning the ABI-encoded output.
+    pub async fn eth_call(
+        &self,
+        to: &Address,
+        data: &[u8],
+        block: BlockTag,
+    ) -> MultiRpcResult<Bytes> {
+        self.multi_json_rpc_call(
+            RpcMethod::EthCall,
+            json!([
+                {
+                    "to": format!("{:#x}", to),
+                    "data": format!("0x{}", hex::encode(data)),
+                },
+                BlockSpec::from(block),
+            ]),
+            ETH_CALL_RESPONSE_SIZE_ESTIMATE,
+        )
+        .await
+    }
+
+    pub async fn eth_get_code(&self, args: candid_types::GetCodeArgs) -> MultiRpcResult<String> {
+        let address = match Address::from_str(&args.address) {
+            Ok(address) => address,
+            Err(_) => {
+                return MultiRpcResult::Consistent(Err(
+                    ValidationError::InvalidHex(args.address).into()
+                ))
+            }
+        };
+        let cache_key = match &args.block {
+            BlockTag::Number(n) if self.is_finalized(n) => self.cache_key(
+                RpcMethod::EthGetCode,
+                &candid_types::GetCodeArgs {
+                    address: format!("{:#x}", address),
+                    block: args.block.clone(),
+                },
+            ),
+            _ => None,
+        };
+        if let Some(result) = self.get_cached(RpcMethod::EthGetCode, &cache_key) {
+            return result;
+        }
+        let result = self
+            .multi_json_rpc_call::<_, Bytes>(
+                RpcMethod::EthGetCode,
+                json!([format!("{:#x}", address), BlockSpec::from(args.block)]),
+                ETH_GET_CODE_RESPONSE_SIZE_ESTIMATE,
+            )
+            .await
+            .map(|code| format!("0x{}", hex::encode(code)));
+        if let (Some(key), MultiRpcResult::Consistent(Ok(code))) = (cache_key, &result) {
+            insert_cached_response(key, code);
+        }
+        result
+    }
+
     pub async fn eth_send_raw_transaction(
         &self,
         raw_signed_transaction_hex: String,
     ) -> MultiRpcResult<candid_types::SendRawTransactionStatus> {
+        self.send_raw_transaction(raw_signed_transaction_hex)
+            .await
+            .0
+    }
+
+    /// Validates a signed transaction before sending it, returning its decoded fields (or the
+    /// reason why it was rejected) along with the result.
+    pub async fn send_raw_transaction(
+        &self,
+        raw_signed_transaction_hex: String,
+    ) -> (
+        MultiRpcResult<candid_types::SendRawTransactionStatus>,
+        Result<DecodedTransaction, TransactionError>,
+    ) {
         use candid_types::SendRawTransactionStatus::*;
-        let transaction_hash = get_transaction_hash(&raw_signed_transaction_hex);
-        process_result(
+        let transaction = match validate_raw_transaction(&raw_signed_transaction_hex, self.chain_id)
+        {
+            Ok(transaction) => transaction,
+            Err(err) => {
+                let result = Err(ValidationError::from(err.clone()).into());
+                return (MultiRpcResult::Consistent(result), Err(err));
+            }
+        };
+        let transaction_hash = Hash::from_str(&transaction.transaction_hash).ok();
+        let result = process_result(
             RpcMethod::EthSendRawTransaction,
-            self.client
-                .multi_eth_send_raw_transaction(raw_signed_transaction_hex)
-                .await,
+            self.with_chain_id(
+                self.client
+                    .multi_eth_send_raw_transaction(raw_signed_transaction_hex),
+            )
+            .await,
         )
         .map(|result| match result {
             SendRawTransactionResult::Ok => Ok(transaction_hash),
             SendRawTransactionResult::InsufficientFunds => InsufficientFunds,
             SendRawTransactionResult::NonceTooLow => NonceTooLow,
             SendRawTransactionResult::NonceTooHigh => NonceTooHigh,
-        })
+        });
+        (result, Ok(transaction))
     }
 }
 
-fn get_transaction_hash(raw_signed_transaction_hex: &str) -> Option<Hash> {
-    let bytes = hex_to_bytes(raw_signed_transaction_hex)?;
-    let transaction: Transaction = rlp::decode(&bytes).ok()?;
-    Some(Hash(transaction.hash.0))
-}
-
 #[test]
 fn test_process_result_mapping() {
     use cketh_common::eth_rpc_client::{providers::EthMainnetService, MultiCallResults};
@@ -308,3 +709,41 @@ fn test_process_result_mapping() {
         ])
     );
 }
+
+#[test]
+fn test_parse_json_rpc_response() {
+    let response = |body: &str| HttpResponse {
+        status: 200_u16.into(),
+        headers: vec![],
+        body: body.as_bytes().to_vec(),
+    };
+    assert_eq!(
+        parse_json_rpc_response::<Option<KnownTransaction>>(response(
+            r#"{"jsonrpc":"2.0","id":1,"result":{"hash":"0x12","nonce":"0x9"}}"#
+        )),
+        Ok(Some(KnownTransaction {
+            hash: "0x12".to_string()
+        }))
+    );
+    assert_eq!(
+        parse_json_rpc_response::<Option<KnownTransaction>>(response(
+            r#"{"jsonrpc":"2.0","id":1,"result":null}"#
+        )),
+        Ok(None)
+    );
+    assert!(matches!(
+        parse_json_rpc_response::<Option<KnownTransaction>>(response(
+            r#"{"jsonrpc":"2.0","id":1}"#
+        )),
+        Err(RpcError::HttpOutcallError(HttpOutcallError::InvalidHttpJsonRpcResponse {
+            parsing_error: Some(error),
+            ..
+        })) if error == "missing result"
+    ));
+    assert!(matches!(
+        parse_json_rpc_response::<String>(response(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"unavailable"}}"#
+        )),
+        Err(RpcError::JsonRpcError(_))
+    ));
+}
@@ -0,0 +1,179 @@
+use candid::{CandidType, Deserialize, Encode};
+use ic_certified_map::{AsHashTree, Hash, HashTree, RbTree};
+use serde::Serialize;
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+pub type CertifiedTree = RbTree<&'static str, Hash>;
+
+/// Query response which can be verified against the canister's certified data.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct CertifiedResponse {
+    /// Candid-encoded response; its SHA-256 hash is the leaf certified under the response's label.
+    pub data: ByteBuf,
+    /// System certificate for the canister's certified data (empty outside of query calls).
+    pub certificate: ByteBuf,
+    /// CBOR-encoded hash tree revealing the leaf for this response.
+    pub witness: ByteBuf,
+}
+
+pub fn get_provider_views() -> Vec<ProviderView> {
+    PROVIDERS.with(|p| {
+        p.borrow()
+            .iter()
+            .map(|(_, provider)| provider.into())
+            .collect()
+    })
+}
+
+pub fn get_service_provider_mappings() -> Vec<(RpcService, u64)> {
+    SERVICE_PROVIDER_MAP.with(|map| {
+        map.borrow()
+            .iter()
+            .filter_map(|(k, v)| Some((k.try_into().ok()?, v)))
+            .collect()
+    })
+}
+
+pub fn encode_provider_views() -> Vec<u8> {
+    Encode!(&get_provider_views()).expect("Unable to encode providers")
+}
+
+pub fn encode_service_provider_mappings() -> Vec<u8> {
+    Encode!(&get_service_provider_mappings()).expect("Unable to encode service provider map")
+}
+
+fn sha256(data: &[u8]) -> Hash {
+    Sha256::digest(data).into()
+}
+
+/// Recomputes the certified hashes of the provider registry and service map, returning the new root hash.
+pub fn update_certified_provider_registry() -> Hash {
+    UNSTABLE_CERTIFIED_TREE.with(|t| {
+        let mut tree = t.borrow_mut();
+        tree.insert(CERTIFIED_PROVIDERS_LABEL, sha256(&encode_provider_views()));
+        tree.insert(
+            CERTIFIED_SERVICE_PROVIDER_MAP_LABEL,
+            sha256(&encode_service_provider_mappings()),
+        );
+        tree.root_hash()
+    })
+}
+
+/// Must be called in update calls after changing providers or the service map.
+pub fn certify_provider_registry() {
+    ic_cdk::api::set_certified_data(&update_certified_provider_registry());
+}
+
+/// Takes a snapshot of the metrics and recomputes its certified hash, returning the new root hash.
+pub fn update_certified_metrics() -> Hash {
+    let snapshot = UNSTABLE_METRICS
+        .with(|metrics| Encode!(&*metrics.borrow()).expect("Unable to encode metrics"));
+    UNSTABLE_CERTIFIED_TREE.with(|t| {
+        let mut tree = t.borrow_mut();
+        tree.insert(CERTIFIED_METRICS_LABEL, sha256(&snapshot));
+        UNSTABLE_CERTIFIED_METRICS.with(|m| *m.borrow_mut() = snapshot);
+        tree.root_hash()
+    })
+}
+
+/// Certifies a snapshot of the metrics, which change with every request. Called periodically by
+/// a timer.
+pub fn certify_metrics() {
+    ic_cdk::api::set_certified_data(&update_certified_metrics());
+}
+
+/// Returns the last certified snapshot of the metrics (Candid-encoded).
+pub fn get_certified_metrics_snapshot() -> Vec<u8> {
+    UNSTABLE_CERTIFIED_METRICS.with(|m| m.borrow().clone())
+}
+
+pub fn get_certified_response(label: &'static str, data: Vec<u8>) -> CertifiedResponse {
+    let witness =
+        UNSTABLE_CERTIFIED_TREE.with(|t| encode_hash_tree(&t.borrow().witness(label.as_bytes())));
+    CertifiedResponse {
+        data: ByteBuf::from(data),
+        certificate: ByteBuf::from(ic_cdk::api::data_certificate().unwrap_or_default()),
+        witness: ByteBuf::from(witness),
+    }
+}
+
+fn encode_hash_tree(tree: &HashTree) -> Vec<u8> {
+    let mut serializer = serde_cbor::ser::Serializer::new(vec![]);
+    serializer.self_describe().unwrap();
+    tree.serialize(&mut serializer).unwrap();
+    serializer.into_inner()
+}
+
+#[test]
+fn test_certified_provider_registry() {
+    let root_hash = update_certified_provider_registry();
+    let provider_id = do_register_provider(
+        Principal::anonymous(),
+        RegisterProviderArgs {
+            chain_id: 1,
+            hostname: "rpc.example.com".to_string(),
+            credential_headers: None,
+            credential_path: "".to_string(),
+            cycles_per_call: 0,
+            cycles_per_message_byte: 0,
+        },
+    );
+    let updated_root_hash = update_certified_provider_registry();
+    assert_ne!(root_hash, updated_root_hash);
+    UNSTABLE_CERTIFIED_TREE.with(|t| {
+        let tree = t.borrow();
+        assert_eq!(
+            tree.get(CERTIFIED_PROVIDERS_LABEL.as_bytes()),
+            Some(&sha256(&encode_provider_views()))
+        );
+        for label in [
+            CERTIFIED_PROVIDERS_LABEL,
+            CERTIFIED_SERVICE_PROVIDER_MAP_LABEL,
+        ] {
+            assert_eq!(
+                tree.witness(label.as_bytes()).reconstruct(),
+                updated_root_hash
+            );
+        }
+    });
+    assert!(get_provider_views()
+        .iter()
+        .any(|provider| provider.provider_id == provider_id));
+}
+
+#[test]
+fn test_certified_metrics() {
+    use candid::Decode;
+
+    let root_hash = update_certified_metrics();
+    add_metric!(err_no_permission, 1);
+    assert_eq!(
+        Decode!(&get_certified_metrics_snapshot(), Metrics)
+            .unwrap()
+            .err_no_permission,
+        0
+    );
+    let updated_root_hash = update_certified_metrics();
+    assert_ne!(root_hash, updated_root_hash);
+    assert_eq!(
+        Decode!(&get_certified_metrics_snapshot(), Metrics)
+            .unwrap()
+            .err_no_permission,
+        1
+    );
+    UNSTABLE_CERTIFIED_TREE.with(|t| {
+        let tree = t.borrow();
+        assert_eq!(
+            tree.get(CERTIFIED_METRICS_LABEL.as_bytes()),
+            Some(&sha256(&get_certified_metrics_snapshot()))
+        );
+        assert_eq!(
+            tree.witness(CERTIFIED_METRICS_LABEL.as_bytes())
+                .reconstruct(),
+            updated_root_hash
+        );
+    });
+}
@@ -0,0 +1,213 @@
+use cketh_common::{eth_rpc::Block, logs::INFO, numeric::BlockNumber};
+use ic_canister_log::log;
+use ic_stable_structures::Storable;
+
+use crate::candid_types::BlockTag;
+use crate::*;
+
+pub fn get_chain_head(chain_id: u64) -> Option<ChainHead> {
+    CHAIN_HEADS.with(|h| h.borrow().get(&chain_id))
+}
+
+pub fn get_chain_head_services() -> Vec<(u64, RpcServices)> {
+    CHAIN_HEAD_SERVICES.with(|s| {
+        s.borrow()
+            .iter()
+            .map(|(chain_id, services)| (chain_id, services.0))
+            .collect()
+    })
+}
+
+/// Starts (or stops, if `services` is `None`) tracking the head of a chain.
+pub fn do_set_chain_head_services(chain_id: u64, services: Option<RpcServices>) {
+    match services {
+        Some(services) => {
+            if services.chain_id() != chain_id {
+                ic_cdk::trap("RPC services do not match the given chain ID");
+            }
+            let services = StorableRpcServices(services);
+            if services.to_bytes().len() > RPC_SERVICES_MAX_SIZE as usize {
+                ic_cdk::trap("RPC services exceed the maximum size");
+            }
+            CHAIN_HEAD_SERVICES.with(|s| s.borrow_mut().insert(chain_id, services));
+        }
+        None => {
+            CHAIN_HEAD_SERVICES.with(|s| s.borrow_mut().remove(&chain_id));
+            CHAIN_HEADS.with(|h| h.borrow_mut().remove(&chain_id));
+        }
+    }
+}
+
+/// Replaces `Latest` and `Finalized` with the block numbers of the tracked chain head, if available.
+pub fn pin_block_tag(chain_id: u64, tag: BlockTag) -> BlockTag {
+    let head = match get_chain_head(chain_id) {
+        Some(head) => head,
+        None => return tag,
+    };
+    let block = match tag {
+        BlockTag::Latest => head.latest,
+        BlockTag::Finalized => head.finalized,
+        _ => None,
+    };
+    match block {
+        Some(block) => BlockTag::Number(block.number),
+        None => tag,
+    }
+}
+
+/// Returns the block without its transaction hashes.
+pub fn block_header(block: Block) -> Block {
+    Block {
+        transactions: vec![],
+        ..block
+    }
+}
+
+/// Stores newly agreed blocks for a chain. The finalized head never moves backwards.
+pub fn update_chain_head(
+    chain_id: u64,
+    latest: Option<Block>,
+    finalized: Option<Block>,
+    timestamp: u64,
+) {
+    if latest.is_none() && finalized.is_none() {
+        return;
+    }
+    let mut head = get_chain_head(chain_id).unwrap_or_default();
+    if let Some(latest) = latest {
+        head.latest = Some(block_header(latest));
+    }
+    if let Some(finalized) = finalized {
+        if head
+            .finalized
+            .as_ref()
+            .map_or(true, |current| finalized.number >= current.number)
+        {
+            head.finalized = Some(block_header(finalized));
+        }
+    }
+    head.updated_at = timestamp;
+    CHAIN_HEADS.with(|h| h.borrow_mut().insert(chain_id, head));
+}
+
+/// Fetches the latest block on which the providers agree. If they report different latest blocks,
+/// the lowest of these is requested again by number.
+async fn fetch_agreed_latest_block(client: &CandidRpcClient) -> Option<Block> {
+    match client.eth_get_block_by_number(BlockTag::Latest).await {
+        MultiRpcResult::Consistent(result) => result.ok(),
+        MultiRpcResult::Inconsistent(results) => {
+            let number = results
+                .into_iter()
+                .filter_map(|(_, result)| result.ok())
+                .map(|block| block.number)
+                .min()?;
+            match client
+                .eth_get_block_by_number(BlockTag::Number(number))
+                .await
+            {
+                MultiRpcResult::Consistent(result) => result.ok(),
+                MultiRpcResult::Inconsistent(_) => None,
+            }
+        }
+    }
+}
+
+async fn fetch_agreed_finalized_block(client: &CandidRpcClient) -> Option<Block> {
+    match client.eth_get_block_by_number(BlockTag::Finalized).await {
+        MultiRpcResult::Consistent(result) => result.ok(),
+        MultiRpcResult::Inconsistent(_) => None,
+    }
+}
+
+/// Polls the latest and finalized blocks of all tracked chains, unless a poll is in progress.
+pub async fn poll_chain_heads() {
+    let _guard = match PollGuard::new(PollTask::ChainHeads) {
+        Some(guard) => guard,
+        None => return,
+    };
+    for (chain_id, services) in get_chain_head_services() {
+        let client = match CandidRpcClient::new(services, None) {
+            Ok(client) => client,
+            Err(err) => {
+                log!(
+                    INFO,
+                    "Unable to track head of chain {}: {:?}",
+                    chain_id,
+                    err
+                );
+                continue;
+            }
+        };
+        let latest = fetch_agreed_latest_block(&client).await;
+        let finalized = fetch_agreed_finalized_block(&client).await;
+        if latest.is_none() || finalized.is_none() {
+            log!(
+                INFO,
+                "Providers of chain {} did not agree on the latest or finalized block",
+                chain_id
+            );
+        }
+        update_chain_head(chain_id, latest, finalized, ic_cdk::api::time());
+    }
+}
+
+#[cfg(test)]
+fn test_block(number: u128) -> Block {
+    use cketh_common::{checked_amount::CheckedAmountOf, numeric::Wei};
+    Block {
+        base_fee_per_gas: Wei::new(1),
+        difficulty: CheckedAmountOf::new(0),
+        extra_data: "0x".to_string(),
+        gas_limit: CheckedAmountOf::new(30_000_000),
+        gas_used: CheckedAmountOf::new(0),
+        hash: format!("0x{:064x}", number),
+        logs_bloom: "0x".to_string(),
+        miner: "0x".to_string(),
+        mix_hash: "0x".to_string(),
+        nonce: CheckedAmountOf::new(0),
+        number: BlockNumber::new(number),
+        parent_hash: format!("0x{:064x}", number.saturating_sub(1)),
+        receipts_root: "0x".to_string(),
+        sha3_uncles: "0x".to_string(),
+        size: CheckedAmountOf::new(0),
+        state_root: "0x".to_string(),
+        timestamp: CheckedAmountOf::new(0),
+        total_difficulty: CheckedAmountOf::new(0),
+        transactions: vec![format!("0x{:064x}", number)],
+        transactions_root: None,
+        uncles: vec![],
+    }
+}
+
+#[test]
+fn test_update_chain_head() {
+    assert_eq!(get_chain_head(1), None);
+    update_chain_head(1, Some(test_block(110)), Some(test_block(100)), 1);
+    let head = get_chain_head(1).unwrap();
+    assert_eq!(head.latest, Some(block_header(test_block(110))));
+    assert!(head.latest.unwrap().transactions.is_empty());
+    assert_eq!(head.finalized.unwrap().number, BlockNumber::new(100));
+
+    // The finalized head does not move backwards
+    update_chain_head(1, None, Some(test_block(90)), 2);
+    let head = get_chain_head(1).unwrap();
+    assert_eq!(head.finalized.unwrap().number, BlockNumber::new(100));
+    assert_eq!(head.updated_at, 2);
+    assert_eq!(get_chain_head(2), None);
+}
+
+#[test]
+fn test_pin_block_tag() {
+    assert_eq!(pin_block_tag(1, BlockTag::Latest), BlockTag::Latest);
+    update_chain_head(1, Some(test_block(110)), Some(test_block(100)), 1);
+    assert_eq!(
+        pin_block_tag(1, BlockTag::Latest),
+        BlockTag::Number(BlockNumber::new(110))
+    );
+    assert_eq!(
+        pin_block_tag(1, BlockTag::Finalized),
+        BlockTag::Number(BlockNumber::new(100))
+    );
+    assert_eq!(pin_block_tag(1, BlockTag::Safe), BlockTag::Safe);
+    assert_eq!(pin_block_tag(2, BlockTag::Finalized), BlockTag::Finalized);
+}
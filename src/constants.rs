@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use cketh_common::eth_rpc_client::providers::{EthMainnetService, EthSepoliaService};
 
 // HTTP outcall cost calculation
@@ -22,14 +24,131 @@ pub const STRING_STORABLE_MAX_SIZE: u32 = 100;
 pub const PROVIDER_MAX_SIZE: u32 = 256;
 pub const RPC_SERVICE_MAX_SIZE: u32 = 256;
 pub const AUTH_SET_STORABLE_MAX_SIZE: u32 = 1000;
+pub const AUTH_GRANTS_STORABLE_MAX_SIZE: u32 = 4000;
+pub const QUOTA_TARGET_MAX_SIZE: u32 = 100;
+pub const QUOTA_MAX_SIZE: u32 = 100;
+pub const RPC_SERVICES_MAX_SIZE: u32 = 2048;
+
+pub const AUDIT_LOG_MAX_PAGE_SIZE: u64 = 100;
+
+// Maximum number of callers with usage statistics
+pub const MAX_TRACKED_CALLERS: usize = 1000;
+// Number of recent request IDs kept for each caller
+pub const RECENT_REQUESTS_PER_CALLER: usize = 10;
+
+// Version of the encoding used to save metrics in stable memory
+pub const METRICS_ENCODING_VERSION: u8 = 3;
+// Previous (Candid) encoding of metrics, which is still restored after an upgrade
+pub const METRICS_CANDID_ENCODING_VERSION: u8 = 2;
+
+// Histogram bucket bounds for HTTP outcall metrics
+pub const RESPONSE_LATENCY_BUCKETS_MS: &[u64] =
+    &[100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000];
+pub const RESPONSE_SIZE_BUCKETS_BYTES: &[u64] = &[
+    256, 1_024, 4_096, 16_384, 65_536, 262_144, 1_048_576, 2_097_152,
+];
+
 pub const WASM_PAGE_SIZE: u64 = 65536;
 
 pub const ETH_GET_LOGS_MAX_BLOCKS: u32 = 500;
 
+// Quota windows (in nanoseconds)
+pub const QUOTA_MINUTE_NANOS: u64 = 60_000_000_000;
+pub const QUOTA_DAY_NANOS: u64 = 24 * 60 * QUOTA_MINUTE_NANOS;
+
+// "Limit exceeded" error code from EIP-1474
+pub const RATE_LIMITED_ERROR_CODE: i64 = -32005;
+
+// Standard JSON-RPC 2.0 error codes
+pub const JSON_RPC_PARSE_ERROR_CODE: i64 = -32700;
+pub const JSON_RPC_INVALID_REQUEST_CODE: i64 = -32600;
+pub const JSON_RPC_METHOD_NOT_FOUND_CODE: i64 = -32601;
+pub const JSON_RPC_INVALID_PARAMS_CODE: i64 = -32602;
+pub const JSON_RPC_INTERNAL_ERROR_CODE: i64 = -32603;
+// Generic server error code used by Ethereum clients (e.g. "nonce too low")
+pub const JSON_RPC_SERVER_ERROR_CODE: i64 = -32000;
+
+// Number of most recent log entries shown on the dashboard
+pub const DASHBOARD_MAX_LOG_ENTRIES: usize = 100;
+
+// Labels of the certified provider registry and metrics hashes
+pub const CERTIFIED_PROVIDERS_LABEL: &str = "providers";
+pub const CERTIFIED_SERVICE_PROVIDER_MAP_LABEL: &str = "service_provider_map";
+pub const CERTIFIED_METRICS_LABEL: &str = "metrics";
+// Interval between certified snapshots of the metrics
+pub const CERTIFIED_METRICS_INTERVAL: Duration = Duration::from_secs(60);
+
+// Path prefix of the JSON-RPC endpoint served through `http_request_update`
+pub const HTTP_RPC_PATH_PREFIX: &str = "/rpc/";
+// Maximum number of requests in a JSON-RPC batch served over HTTP
+pub const HTTP_RPC_MAX_BATCH_SIZE: usize = 10;
+// Reserved principal (class 0x7f, which never makes calls) whose prepaid balance pays for JSON-RPC requests served over HTTP
+pub const HTTP_RPC_ACCOUNT: &[u8] = b"evm-rpc-http\x7f";
+
+// Response cache for immutable chain data
+pub const RESPONSE_CACHE_KEY_SIZE: u32 = 40;
+pub const RESPONSE_CACHE_ENTRY_MAX_SIZE: u32 = 32 * 1024;
+pub const RESPONSE_CACHE_MAX_ENTRIES_LIMIT: u64 = 10_000;
+// Flat fee for a response served from the cache instead of HTTP outcalls
+pub const RESPONSE_CACHE_HIT_CYCLES: u128 = 10_000_000;
+
+// Chain head tracker
+pub const CHAIN_HEAD_POLL_INTERVAL: Duration = Duration::from_secs(60);
+pub const CHAIN_HEAD_MAX_SIZE: u32 = 8192;
+
+// Subscriptions
+pub const SUBSCRIPTION_POLL_INTERVAL: Duration = Duration::from_secs(60);
+pub const SUBSCRIPTION_MAX_SIZE: u32 = 8192;
+pub const MAX_SUBSCRIPTIONS_PER_CALLER: usize = 10;
+pub const SUBSCRIPTION_CALLBACK_MAX_LENGTH: usize = 100;
+// Subscriptions whose owner cannot pay for polls for this long are removed
+pub const SUBSCRIPTION_UNPAID_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+pub const BLOCK_SUBSCRIPTION_MAX_BLOCKS_PER_POLL: u64 = 10;
+pub const BLOCK_SUBSCRIPTION_HISTORY_LENGTH: usize = 32;
+
+// Log cursors
+pub const MAX_LOG_CURSORS_PER_CALLER: usize = 10;
+pub const LOG_CURSOR_REORG_DEPTH: u64 = 64;
+pub const LOG_CURSOR_KEY_SIZE: u32 = 24;
+pub const LOG_CURSOR_ENTRY_MAX_SIZE: u32 = 4096;
+
+// Transaction tracker
+pub const TRANSACTION_POLL_INTERVAL: Duration = Duration::from_secs(60);
+pub const TRACKED_TRANSACTION_MAX_SIZE: u32 = 16 * 1024;
+pub const MAX_TRACKED_TRANSACTIONS_PER_CALLER: usize = 20;
+pub const TRANSACTION_REBROADCAST_AFTER_POLLS: u32 = 3;
+pub const TRANSACTION_MAX_REBROADCASTS: u32 = 5;
+pub const TRACKED_TRANSACTION_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+pub const ETH_GET_TRANSACTION_BY_HASH_RESPONSE_SIZE_ESTIMATE: u64 =
+    2 * TRACKED_TRANSACTION_MAX_SIZE as u64 + 2048;
+
+// Fee estimation
+pub const FEE_HISTORY_BLOCK_COUNT: u64 = 5;
+pub const FEE_HISTORY_MAX_BLOCK_COUNT: u64 = 1024;
+pub const FEE_HISTORY_REWARD_PERCENTILE: u8 = 50;
+pub const ETH_BLOB_BASE_FEE_RESPONSE_SIZE_ESTIMATE: u64 = 256;
+
+// Transaction signing
+pub const SIGN_WITH_ECDSA_CYCLES: u128 = 26_153_846_153;
+
+// Nonce manager
+pub const NONCE_KEY_SIZE: u32 = 28;
+pub const ADDRESS_NONCES_MAX_SIZE: u32 = 2048;
+pub const MAX_PENDING_NONCES_PER_ADDRESS: u64 = 16;
+pub const NONCE_RESERVATION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// Contract calls
+pub const ETH_CALL_RESPONSE_SIZE_ESTIMATE: u64 = 2048;
+// Hex-encoded contract code of at most 24KB (EIP-170)
+pub const ETH_GET_CODE_RESPONSE_SIZE_ESTIMATE: u64 = 2 * 24 * 1024 + 256;
+
 pub const NODES_IN_STANDARD_SUBNET: u32 = 13;
 pub const NODES_IN_FIDUCIARY_SUBNET: u32 = 28;
 pub const DEFAULT_OPEN_RPC_ACCESS: bool = true;
 
+// Interval for removing expired authorizations
+pub const AUTH_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 // Providers used by default (when passing `null` with `RpcServices`)
 pub const DEFAULT_ETH_MAINNET_SERVICES: &[EthMainnetService] = &[
     EthMainnetService::Ankr,
@@ -47,5 +166,3 @@ pub const CONTENT_TYPE_VALUE: &str = "application/json";
 
 pub const ETH_MAINNET_CHAIN_ID: u64 = 1;
 pub const ETH_SEPOLIA_CHAIN_ID: u64 = 11155111;
-
-pub const SERVICE_HOSTS_BLOCKLIST: &[&str] = &[];
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use cketh_common::logs::{Log, LogEntry, Priority, Sort};
+
+use crate::*;
+
+/// Aggregated HTTP outcall statistics for a single RPC host.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostStats {
+    pub requests: u64,
+    pub successful_responses: u64,
+    pub errors: u64,
+    pub cycles_charged: u128,
+}
+
+impl HostStats {
+    /// Percentage of requests which resulted in an HTTP, outcall or JSON-RPC error.
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            100.0 * self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+pub struct DashboardData {
+    pub providers: Vec<Provider>,
+    pub metrics: Metrics,
+    pub cycles_balance: u128,
+    pub log_entries: Vec<LogEntry>,
+}
+
+impl DashboardData {
+    pub fn collect() -> Self {
+        let mut log = Log::default();
+        log.push_logs(Priority::Info);
+        log.push_logs(Priority::Debug);
+        log.sort_logs(Sort::Descending);
+        log.entries.truncate(DASHBOARD_MAX_LOG_ENTRIES);
+        Self {
+            providers: PROVIDERS.with(|p| p.borrow().iter().map(|(_, p)| p).collect()),
+            metrics: UNSTABLE_METRICS.with(|m| m.borrow().clone()),
+            cycles_balance: ic_cdk::api::canister_balance128(),
+            log_entries: log.entries,
+        }
+    }
+}
+
+pub fn get_host_stats(metrics: &Metrics) -> BTreeMap<String, HostStats> {
+    let mut stats: BTreeMap<String, HostStats> = BTreeMap::new();
+    for ((_, host), count) in &metrics.requests {
+        stats.entry(host.0.clone()).or_default().requests += count;
+    }
+    for ((_, host, status), count) in &metrics.responses {
+        let host_stats = stats.entry(host.0.clone()).or_default();
+        if status.0.starts_with('2') {
+            host_stats.successful_responses += count;
+        } else {
+            host_stats.errors += count;
+        }
+    }
+    for ((_, host), count) in &metrics.err_http_outcall {
+        stats.entry(host.0.clone()).or_default().errors += count;
+    }
+    for ((_, host, _), count) in &metrics.err_json_rpc {
+        stats.entry(host.0.clone()).or_default().errors += count;
+    }
+    for ((_, host), cycles) in &metrics.cycles_charged {
+        stats.entry(host.0.clone()).or_default().cycles_charged += cycles;
+    }
+    stats
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_table(html: &mut String, headers: &[&str], rows: Vec<Vec<String>>) {
+    html.push_str("<table><thead><tr>");
+    for header in headers {
+        write!(html, "<th>{}</th>", escape_html(header)).unwrap();
+    }
+    html.push_str("</tr></thead><tbody>");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            write!(html, "<td>{}</td>", escape_html(&cell)).unwrap();
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+}
+
+pub fn render_dashboard(data: &DashboardData) -> String {
+    let mut html = String::new();
+    html.push_str(concat!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>EVM RPC Dashboard</title>",
+        "<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse;margin-bottom:2em}",
+        "th,td{border:1px solid #ccc;padding:0.3em 0.6em;text-align:left}th{background:#eee}</style>",
+        "</head><body><h1>EVM RPC Dashboard</h1>"
+    ));
+
+    html.push_str("<h2>Cycles</h2>");
+    let cycles_owed: u128 = data.providers.iter().map(|p| p.cycles_owed).sum();
+    write_table(
+        &mut html,
+        &["Balance", "Owed to providers", "Withdrawn by providers"],
+        vec![vec![
+            data.cycles_balance.to_string(),
+            cycles_owed.to_string(),
+            data.metrics.cycles_withdrawn.to_string(),
+        ]],
+    );
+
+    html.push_str("<h2>Providers</h2>");
+    write_table(
+        &mut html,
+        &[
+            "ID",
+            "Chain ID",
+            "Hostname",
+            "Owner",
+            "Cycles per call",
+            "Cycles per message byte",
+            "Cycles owed",
+            "Primary",
+        ],
+        data.providers
+            .iter()
+            .map(|p| {
+                vec![
+                    p.provider_id.to_string(),
+                    p.chain_id.to_string(),
+                    p.hostname.clone(),
+                    p.owner.to_string(),
+                    p.cycles_per_call.to_string(),
+                    p.cycles_per_message_byte.to_string(),
+                    p.cycles_owed.to_string(),
+                    p.primary.to_string(),
+                ]
+            })
+            .collect(),
+    );
+
+    html.push_str("<h2>Hosts</h2>");
+    write_table(
+        &mut html,
+        &[
+            "Host",
+            "Requests",
+            "Successful responses",
+            "Errors",
+            "Error rate",
+            "Cycles charged",
+        ],
+        get_host_stats(&data.metrics)
+            .into_iter()
+            .map(|(host, stats)| {
+                vec![
+                    host,
+                    stats.requests.to_string(),
+                    stats.successful_responses.to_string(),
+                    stats.errors.to_string(),
+                    format!("{:.1}%", stats.error_rate()),
+                    stats.cycles_charged.to_string(),
+                ]
+            })
+            .collect(),
+    );
+
+    html.push_str("<h2>Recent logs</h2>");
+    write_table(
+        &mut html,
+        &["Timestamp", "Priority", "Source", "Message"],
+        data.log_entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.timestamp.to_string(),
+                    format!("{:?}", entry.priority),
+                    format!("{}:{}", entry.file, entry.line),
+                    entry.message.clone(),
+                ]
+            })
+            .collect(),
+    );
+
+    html.push_str("</body></html>");
+    html
+}
+
+#[test]
+fn test_get_host_stats() {
+    let host = || MetricRpcHost("rpc.example.com".to_string());
+    let method = || MetricRpcMethod("eth_getLogs".to_string());
+    let metrics = Metrics {
+        requests: [((method(), host()), 4)].into_iter().collect(),
+        responses: [
+            ((method(), host(), MetricHttpStatusCode::from(200)), 2),
+            ((method(), host(), MetricHttpStatusCode::from(500)), 1),
+        ]
+        .into_iter()
+        .collect(),
+        err_http_outcall: [((method(), host()), 1)].into_iter().collect(),
+        cycles_charged: [((method(), host()), 100)].into_iter().collect(),
+        ..Default::default()
+    };
+    let stats = get_host_stats(&metrics);
+    assert_eq!(
+        stats.get("rpc.example.com"),
+        Some(&HostStats {
+            requests: 4,
+            successful_responses: 2,
+            errors: 2,
+            cycles_charged: 100,
+        })
+    );
+    assert_eq!(stats["rpc.example.com"].error_rate(), 50.0);
+}
+
+#[test]
+fn test_render_dashboard() {
+    let html = render_dashboard(&DashboardData {
+        providers: vec![],
+        metrics: Metrics::default(),
+        cycles_balance: 123,
+        log_entries: vec![],
+    });
+    assert!(html.contains("<td>123</td>"));
+    // The service provider map is restricted to managers
+    assert!(!html.contains("Service providers"));
+}
@@ -0,0 +1,241 @@
+use std::str::FromStr;
+
+use candid::Nat;
+use cketh_common::eth_rpc::{LogEntry, ValidationError};
+use ethers_core::{
+    abi::{self, ParamType, Token},
+    types::{Address, H256, U256},
+    utils::{id, keccak256},
+};
+
+use crate::*;
+
+const TRANSFER_EVENT: &str = "Transfer(address,address,uint256)";
+const APPROVAL_EVENT: &str = "Approval(address,address,uint256)";
+
+fn decoding_error(function: &str) -> RpcError {
+    ValidationError::Custom(format!("Unable to decode the output of {}", function)).into()
+}
+
+pub fn parse_address(address: &str) -> RpcResult<Address> {
+    Address::from_str(address)
+        .map_err(|_| ValidationError::Custom(format!("Invalid address: {}", address)).into())
+}
+
+pub fn u256_to_nat(value: U256) -> Nat {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    Nat(num::BigUint::from_bytes_be(&bytes))
+}
+
+/// Calls a function of an ERC-20 token contract, returning its ABI-encoded output.
+async fn erc20_call(
+    client: &CandidRpcClient,
+    token: &str,
+    function: &str,
+    args: &[Token],
+    block: Option<candid_types::BlockTag>,
+) -> MultiRpcResult<Vec<u8>> {
+    let token = match parse_address(token) {
+        Ok(token) => token,
+        Err(err) => return MultiRpcResult::Consistent(Err(err)),
+    };
+    let data = [&id(function)[..], &abi::encode(args)].concat();
+    client
+        .eth_call(&token, &data, block.unwrap_or_default())
+        .await
+        .map(|output| output.to_vec())
+}
+
+fn decode_uint(function: &str, output: &[u8]) -> RpcResult<U256> {
+    match abi::decode(&[ParamType::Uint(256)], output).as_deref() {
+        Ok([Token::Uint(value)]) => Ok(*value),
+        _ => Err(decoding_error(function)),
+    }
+}
+
+/// Decodes a token symbol, which is a `bytes32` instead of a `string` for some older tokens.
+fn decode_symbol(output: &[u8]) -> RpcResult<String> {
+    if let Ok([Token::String(symbol)]) = abi::decode(&[ParamType::String], output).as_deref() {
+        return Ok(symbol.clone());
+    }
+    if output.len() == 32 {
+        let symbol = output.split(|byte| *byte == 0).next().unwrap_or_default();
+        if let Ok(symbol) = String::from_utf8(symbol.to_vec()) {
+            return Ok(symbol);
+        }
+    }
+    Err(decoding_error("symbol()"))
+}
+
+pub async fn erc20_balance_of(
+    client: &CandidRpcClient,
+    args: Erc20BalanceOfArgs,
+) -> MultiRpcResult<Nat> {
+    let owner = match parse_address(&args.owner) {
+        Ok(owner) => owner,
+        Err(err) => return MultiRpcResult::Consistent(Err(err)),
+    };
+    let function = "balanceOf(address)";
+    erc20_call(
+        client,
+        &args.token,
+        function,
+        &[Token::Address(owner)],
+        args.block,
+    )
+    .await
+    .and_then(|output| decode_uint(function, &output).map(u256_to_nat))
+}
+
+pub async fn erc20_allowance(
+    client: &CandidRpcClient,
+    args: Erc20AllowanceArgs,
+) -> MultiRpcResult<Nat> {
+    let (owner, spender) = match (parse_address(&args.owner), parse_address(&args.spender)) {
+        (Ok(owner), Ok(spender)) => (owner, spender),
+        (Err(err), _) | (_, Err(err)) => return MultiRpcResult::Consistent(Err(err)),
+    };
+    let function = "allowance(address,address)";
+    erc20_call(
+        client,
+        &args.token,
+        function,
+        &[Token::Address(owner), Token::Address(spender)],
+        args.block,
+    )
+    .await
+    .and_then(|output| decode_uint(function, &output).map(u256_to_nat))
+}
+
+pub async fn erc20_total_supply(
+    client: &CandidRpcClient,
+    args: Erc20CallArgs,
+) -> MultiRpcResult<Nat> {
+    let function = "totalSupply()";
+    erc20_call(client, &args.token, function, &[], args.block)
+        .await
+        .and_then(|output| decode_uint(function, &output).map(u256_to_nat))
+}
+
+pub async fn erc20_decimals(client: &CandidRpcClient, args: Erc20CallArgs) -> MultiRpcResult<u8> {
+    let function = "decimals()";
+    erc20_call(client, &args.token, function, &[], args.block)
+        .await
+        .and_then(|output| {
+            let decimals = decode_uint(function, &output)?;
+            if decimals > U256::from(u8::MAX) {
+                return Err(decoding_error(function));
+            }
+            Ok(decimals.as_u32() as u8)
+        })
+}
+
+pub async fn erc20_symbol(client: &CandidRpcClient, args: Erc20CallArgs) -> MultiRpcResult<String> {
+    erc20_call(client, &args.token, "symbol()", &[], args.block)
+        .await
+        .and_then(|output| decode_symbol(&output))
+}
+
+/// Decodes a `Transfer` or `Approval` event of an ERC-20 token. Other logs, including ERC-721
+/// transfers (whose token ID is indexed), are ignored.
+pub fn decode_erc20_event(log: &LogEntry) -> Option<Erc20Event> {
+    let (signature, first, second) = match log.topics.as_slice() {
+        [signature, first, second] => (signature, first, second),
+        _ => return None,
+    };
+    let topic_address = |topic: &[u8; 32]| format!("{:#x}", Address::from(H256(*topic)));
+    let value = match abi::decode(&[ParamType::Uint(256)], &log.data.0).as_deref() {
+        Ok([Token::Uint(value)]) => u256_to_nat(*value),
+        _ => return None,
+    };
+    let event = if signature.0 == keccak256(TRANSFER_EVENT) {
+        Erc20EventKind::Transfer {
+            from: topic_address(&first.0),
+            to: topic_address(&second.0),
+            value,
+        }
+    } else if signature.0 == keccak256(APPROVAL_EVENT) {
+        Erc20EventKind::Approval {
+            owner: topic_address(&first.0),
+            spender: topic_address(&second.0),
+            value,
+        }
+    } else {
+        return None;
+    };
+    Some(Erc20Event {
+        token: format!("{:#x}", Address::from_slice(log.address.as_ref())),
+        event,
+        block_number: log.block_number.map(Nat::from),
+        transaction_hash: log.transaction_hash.as_ref().map(|hash| hash.to_string()),
+        log_index: log.log_index.map(Nat::from),
+        removed: log.removed,
+    })
+}
+
+pub fn decode_erc20_events(logs: &[LogEntry]) -> Vec<Erc20Event> {
+    logs.iter().filter_map(decode_erc20_event).collect()
+}
+
+#[test]
+fn test_decode_symbol() {
+    let output = abi::encode(&[Token::String("USDT".to_string())]);
+    assert_eq!(decode_symbol(&output), Ok("USDT".to_string()));
+    let mut output = [0; 32];
+    output[..3].copy_from_slice(b"MKR");
+    assert_eq!(decode_symbol(&output), Ok("MKR".to_string()));
+    assert!(decode_symbol(&[1, 2, 3]).is_err());
+}
+
+#[test]
+fn test_decode_erc20_event() {
+    use cketh_common::eth_rpc::{Data, FixedSizeData};
+
+    let topic = |hex: &str| FixedSizeData::from_str(hex).unwrap();
+    let mut log = LogEntry {
+        address: cketh_common::address::Address::from_str(
+            "0xdac17f958d2ee523a2206206994597c13d831ec7",
+        )
+        .unwrap(),
+        topics: vec![
+            topic("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"),
+            topic("0x000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43"),
+            topic("0x00000000000000000000000078cccfb3d517cd4ed6d045e263e134712288ace2"),
+        ],
+        data: Data(
+            hex::decode("000000000000000000000000000000000000000000000000000000003b9c6433")
+                .unwrap(),
+        ),
+        block_number: None,
+        transaction_hash: None,
+        transaction_index: None,
+        block_hash: None,
+        log_index: None,
+        removed: false,
+    };
+    assert_eq!(
+        decode_erc20_event(&log),
+        Some(Erc20Event {
+            token: "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+            event: Erc20EventKind::Transfer {
+                from: "0xa9d1e08c7793af67e9d92fe308d5697fb81d3e43".to_string(),
+                to: "0x78cccfb3d517cd4ed6d045e263e134712288ace2".to_string(),
+                value: Nat::from(0x3b9c6433_u64),
+            },
+            block_number: None,
+            transaction_hash: None,
+            log_index: None,
+            removed: false,
+        })
+    );
+
+    log.topics[0] = topic("0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925");
+    assert!(matches!(
+        decode_erc20_event(&log).map(|event| event.event),
+        Some(Erc20EventKind::Approval { .. })
+    ));
+
+    log.topics.push(topic(&format!("0x{:064x}", 1)));
+    assert_eq!(decode_erc20_event(&log), None);
+}
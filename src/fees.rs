@@ -0,0 +1,188 @@
+use candid::Nat;
+use cketh_common::eth_rpc::{FeeHistory, RpcError, ValidationError};
+use ethers_core::types::U256;
+use num_traits::ToPrimitive;
+
+use crate::candid_types::{BlockTag, FeeHistoryArgs};
+use crate::*;
+
+fn fee_too_large() -> RpcError {
+    ValidationError::Custom("Fee is too large".to_string()).into()
+}
+
+fn to_u128(value: Nat) -> RpcResult<u128> {
+    value.0.to_u128().ok_or_else(fee_too_large)
+}
+
+/// Returns the maximum fee which covers the given base fee after it rises according to the
+/// urgency (rounded up).
+fn max_base_fee(base_fee: u128, urgency: FeeUrgency) -> RpcResult<u128> {
+    let (numerator, denominator) = match urgency {
+        FeeUrgency::Low => (9, 8),
+        FeeUrgency::Medium => (2, 1),
+        FeeUrgency::High => (4, 1),
+    };
+    base_fee
+        .checked_mul(numerator)
+        .map(|fee| (fee + denominator - 1) / denominator)
+        .ok_or_else(fee_too_large)
+}
+
+/// Suggests fees for the next block from a fee history whose rewards were requested for a single
+/// percentile:
+/// * `maxPriorityFeePerGas` is the median of the rewards of the recent blocks;
+/// * `maxFeePerGas` is the base fee of the next block, increased according to the urgency, plus
+///   `maxPriorityFeePerGas`.
+pub fn compute_fee_estimate(history: &FeeHistory, urgency: FeeUrgency) -> RpcResult<FeeEstimate> {
+    let base_fee = match history.base_fee_per_gas.last() {
+        Some(base_fee) => to_u128(Nat::from(*base_fee))?,
+        None => {
+            return Err(
+                ValidationError::Custom("Unable to estimate the base fee".to_string()).into(),
+            )
+        }
+    };
+    let mut rewards = history
+        .reward
+        .iter()
+        .filter_map(|rewards| rewards.first())
+        .map(|reward| to_u128(Nat::from(*reward)))
+        .collect::<RpcResult<Vec<_>>>()?;
+    rewards.sort_unstable();
+    let priority_fee = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+    let max_fee = max_base_fee(base_fee, urgency)?
+        .checked_add(priority_fee)
+        .ok_or_else(fee_too_large)?;
+    Ok(FeeEstimate {
+        base_fee_per_gas: base_fee.into(),
+        max_fee_per_gas: max_fee.into(),
+        max_priority_fee_per_gas: priority_fee.into(),
+        base_fee_per_blob_gas: None,
+        max_fee_per_blob_gas: None,
+    })
+}
+
+/// Adds blob fees to an estimate: the blob base fee rises like the base fee, and blobs do not pay
+/// a priority fee.
+pub fn add_blob_fee_estimate(
+    estimate: &mut FeeEstimate,
+    blob_base_fee: U256,
+    urgency: FeeUrgency,
+) -> RpcResult<()> {
+    if blob_base_fee > U256::from(u128::MAX) {
+        return Err(fee_too_large());
+    }
+    let blob_base_fee = blob_base_fee.as_u128();
+    estimate.max_fee_per_blob_gas = Some(max_base_fee(blob_base_fee, urgency)?.into());
+    estimate.base_fee_per_blob_gas = Some(blob_base_fee.into());
+    Ok(())
+}
+
+/// Estimates fees from the consensus fee history, without blob fees.
+pub async fn estimate_eip1559_fees(
+    client: &CandidRpcClient,
+    args: &EstimateFeesArgs,
+) -> MultiRpcResult<FeeEstimate> {
+    let percentile = args.percentile.unwrap_or(FEE_HISTORY_REWARD_PERCENTILE);
+    let block_count = args.block_count.unwrap_or(FEE_HISTORY_BLOCK_COUNT);
+    if percentile > 100 {
+        return MultiRpcResult::Consistent(Err(ValidationError::Custom(
+            "Percentile must be at most 100".to_string(),
+        )
+        .into()));
+    }
+    if block_count == 0 || block_count > FEE_HISTORY_MAX_BLOCK_COUNT {
+        return MultiRpcResult::Consistent(Err(ValidationError::Custom(format!(
+            "Block count must be between 1 and {}",
+            FEE_HISTORY_MAX_BLOCK_COUNT
+        ))
+        .into()));
+    }
+    let urgency = args.urgency.unwrap_or_default();
+    client
+        .eth_fee_history(FeeHistoryArgs {
+            block_count: block_count as u128,
+            newest_block: BlockTag::Latest,
+            reward_percentiles: Some(vec![percentile]),
+        })
+        .await
+        .and_then(|history| match history {
+            Some(history) => compute_fee_estimate(&history, urgency),
+            None => Err(ValidationError::Custom("Missing fee history".to_string()).into()),
+        })
+}
+
+/// Estimates fees, including blob fees unless they were explicitly disabled. Unless blob fees were
+/// explicitly requested, they are left out when `eth_blobBaseFee` fails (e.g. on chains without
+/// EIP-4844 blobs) or providers disagree on it.
+pub async fn estimate_fees(
+    client: &CandidRpcClient,
+    args: EstimateFeesArgs,
+) -> MultiRpcResult<FeeEstimate> {
+    let estimate = match estimate_eip1559_fees(client, &args).await {
+        MultiRpcResult::Consistent(Ok(estimate)) => estimate,
+        result => return result,
+    };
+    if args.blob_fees == Some(false) {
+        return MultiRpcResult::Consistent(Ok(estimate));
+    }
+    let urgency = args.urgency.unwrap_or_default();
+    let result = client.eth_blob_base_fee().await.and_then(|blob_base_fee| {
+        let mut estimate = estimate.clone();
+        add_blob_fee_estimate(&mut estimate, blob_base_fee, urgency)?;
+        Ok(estimate)
+    });
+    match result {
+        MultiRpcResult::Consistent(Ok(_)) => result,
+        _ if args.blob_fees == Some(true) => result,
+        _ => MultiRpcResult::Consistent(Ok(estimate)),
+    }
+}
+
+#[test]
+fn test_compute_fee_estimate() {
+    use cketh_common::checked_amount::CheckedAmountOf;
+
+    let history = FeeHistory {
+        oldest_block: CheckedAmountOf::new(100),
+        base_fee_per_gas: vec![CheckedAmountOf::new(90), CheckedAmountOf::new(100)],
+        gas_used_ratio: vec![],
+        reward: vec![
+            vec![CheckedAmountOf::new(3)],
+            vec![CheckedAmountOf::new(1)],
+            vec![CheckedAmountOf::new(2)],
+        ],
+    };
+    let estimate = |urgency| compute_fee_estimate(&history, urgency).unwrap();
+    assert_eq!(
+        estimate(FeeUrgency::Medium),
+        FeeEstimate {
+            base_fee_per_gas: 100_u128.into(),
+            max_fee_per_gas: 202_u128.into(),
+            max_priority_fee_per_gas: 2_u128.into(),
+            base_fee_per_blob_gas: None,
+            max_fee_per_blob_gas: None,
+        }
+    );
+    assert_eq!(
+        estimate(FeeUrgency::Low).max_fee_per_gas,
+        Nat::from(115_u128)
+    );
+    assert_eq!(
+        estimate(FeeUrgency::High).max_fee_per_gas,
+        Nat::from(402_u128)
+    );
+    assert!(compute_fee_estimate(
+        &FeeHistory {
+            base_fee_per_gas: vec![],
+            ..history.clone()
+        },
+        FeeUrgency::Medium
+    )
+    .is_err());
+
+    let mut estimate = estimate(FeeUrgency::Low);
+    add_blob_fee_estimate(&mut estimate, U256::from(1), FeeUrgency::Low).unwrap();
+    assert_eq!(estimate.base_fee_per_blob_gas, Some(1_u128.into()));
+    assert_eq!(estimate.max_fee_per_blob_gas, Some(2_u128.into()));
+}
@@ -0,0 +1,159 @@
+use cketh_common::eth_rpc::ValidationError;
+
+use crate::*;
+
+fn normalize_host(host: &str) -> String {
+    host.trim().to_lowercase()
+}
+
+/// Traps if a host or pattern does not fit into the blocklist or allowlist.
+fn validate_host_length(host: &str) {
+    if host.len() > STRING_STORABLE_MAX_SIZE as usize {
+        ic_cdk::trap(&format!(
+            "Host must not be longer than {} bytes",
+            STRING_STORABLE_MAX_SIZE
+        ));
+    }
+}
+
+pub fn get_blocked_hosts() -> Vec<String> {
+    HOST_BLOCKLIST.with(|b| b.borrow().iter().map(|(host, _)| host.0).collect())
+}
+
+pub fn is_host_blocked(host: &str) -> bool {
+    HOST_BLOCKLIST.with(|b| {
+        b.borrow()
+            .contains_key(&StringStorable(normalize_host(host)))
+    })
+}
+
+/// Adds a host to the blocklist. Returns `true` if the host was not blocked before.
+pub fn do_block_host(host: &str) -> bool {
+    let host = normalize_host(host);
+    if host.is_empty() {
+        ic_cdk::trap("Host must not be empty");
+    }
+    validate_host_length(&host);
+    HOST_BLOCKLIST.with(|b| b.borrow_mut().insert(StringStorable(host), ()).is_none())
+}
+
+/// Removes a host from the blocklist. Returns `true` if the host was blocked before.
+pub fn do_unblock_host(host: &str) -> bool {
+    HOST_BLOCKLIST.with(|b| {
+        b.borrow_mut()
+            .remove(&StringStorable(normalize_host(host)))
+            .is_some()
+    })
+}
+
+pub fn get_allowed_host_patterns() -> Vec<String> {
+    HOST_ALLOWLIST.with(|a| a.borrow().iter().map(|(pattern, _)| pattern.0).collect())
+}
+
+/// Adds a host pattern to the allowlist. A pattern is either an exact hostname
+/// (`example.com`) or a wildcard matching any subdomain (`*.example.com`).
+/// Returns `true` if the pattern was not allowed before.
+pub fn do_allow_host_pattern(pattern: &str) -> bool {
+    let pattern = normalize_host(pattern);
+    let host = pattern.strip_prefix("*.").unwrap_or(&pattern);
+    if host.is_empty() || host.contains('*') {
+        ic_cdk::trap("Invalid host pattern");
+    }
+    validate_host_length(&pattern);
+    HOST_ALLOWLIST.with(|a| a.borrow_mut().insert(StringStorable(pattern), ()).is_none())
+}
+
+/// Removes a host pattern from the allowlist. Returns `true` if the pattern was allowed before.
+pub fn do_disallow_host_pattern(pattern: &str) -> bool {
+    HOST_ALLOWLIST.with(|a| {
+        a.borrow_mut()
+            .remove(&StringStorable(normalize_host(pattern)))
+            .is_some()
+    })
+}
+
+pub fn is_host_allowlist_enabled() -> bool {
+    METADATA.with(|m| m.borrow().get().host_allowlist_enabled.unwrap_or_default())
+}
+
+pub fn set_host_allowlist_enabled(enabled: bool) {
+    METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        metadata.host_allowlist_enabled = Some(enabled);
+        m.borrow_mut().set(metadata).unwrap();
+    });
+}
+
+pub fn host_matches_pattern(host: &str, pattern: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .map(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.'))
+            .unwrap_or(false),
+        None => host == pattern,
+    }
+}
+
+pub fn is_host_allowlisted(host: &str) -> bool {
+    let host = normalize_host(host);
+    HOST_ALLOWLIST.with(|a| {
+        a.borrow()
+            .iter()
+            .any(|(pattern, _)| host_matches_pattern(&host, &pattern.0))
+    })
+}
+
+/// Checks that requests may be sent to the given host of a `Custom` RPC service.
+pub fn validate_custom_hostname(hostname: &str) -> Result<(), ValidationError> {
+    validate_hostname(hostname)?;
+    if is_host_allowlist_enabled() && !is_host_allowlisted(hostname) {
+        Err(ValidationError::HostNotAllowed(hostname.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_host_matches_pattern() {
+    assert!(host_matches_pattern("example.com", "example.com"));
+    assert!(!host_matches_pattern("rpc.example.com", "example.com"));
+    assert!(host_matches_pattern("rpc.example.com", "*.example.com"));
+    assert!(host_matches_pattern("a.rpc.example.com", "*.example.com"));
+    assert!(!host_matches_pattern("example.com", "*.example.com"));
+    assert!(!host_matches_pattern("badexample.com", "*.example.com"));
+}
+
+#[test]
+fn test_host_blocklist() {
+    assert_eq!(validate_hostname("bad.host"), Ok(()));
+    assert!(do_block_host("Bad.Host"));
+    assert!(!do_block_host("bad.host"));
+    assert_eq!(get_blocked_hosts(), vec!["bad.host".to_string()]);
+    assert_eq!(
+        validate_hostname("bad.host"),
+        Err(ValidationError::HostNotAllowed("bad.host".to_string()))
+    );
+    assert!(do_unblock_host("bad.host"));
+    assert!(!do_unblock_host("bad.host"));
+    assert_eq!(validate_hostname("bad.host"), Ok(()));
+}
+
+#[test]
+fn test_host_allowlist() {
+    assert_eq!(validate_custom_hostname("rpc.good.host"), Ok(()));
+    set_host_allowlist_enabled(true);
+    assert_eq!(
+        validate_custom_hostname("rpc.good.host"),
+        Err(ValidationError::HostNotAllowed("rpc.good.host".to_string()))
+    );
+    assert!(do_allow_host_pattern("*.good.host"));
+    assert_eq!(validate_custom_hostname("rpc.good.host"), Ok(()));
+    assert!(validate_custom_hostname("good.host").is_err());
+
+    do_block_host("rpc.good.host");
+    assert!(validate_custom_hostname("rpc.good.host").is_err());
+
+    assert!(do_disallow_host_pattern("*.good.host"));
+    set_host_allowlist_enabled(false);
+    assert_eq!(validate_custom_hostname("good.host"), Ok(()));
+}
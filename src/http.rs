@@ -62,20 +62,38 @@ pub async fn do_http_request(
         None => return Err(ValidationError::UrlParseError(api.url).into()),
     };
     let rpc_host = MetricRpcHost(host.to_string());
-    if SERVICE_HOSTS_BLOCKLIST.contains(&rpc_host.0.as_str()) {
+    let provider_id = provider.as_ref().map(|provider| provider.provider_id);
+    let auth_context = AuthContext {
+        // Custom services are attributed to the chain ID given by the caller
+        chain_id: provider
+            .as_ref()
+            .map(|provider| provider.chain_id)
+            .or_else(rpc_chain_id),
+        method: &rpc_method.0,
+        provider_id,
+    };
+    if !is_rpc_allowed_for(&caller, &auth_context) {
+        add_metric!(err_no_permission, 1);
+        return Err(ProviderError::NoPermission.into());
+    }
+    let host_validation = match provider {
+        Some(_) => validate_hostname(&rpc_host.0),
+        None => validate_custom_hostname(&rpc_host.0),
+    };
+    if let Err(err) = host_validation {
         add_metric_entry!(err_host_not_allowed, rpc_host.clone(), 1);
-        return Err(ValidationError::HostNotAllowed(rpc_host.0).into());
+        return Err(err.into());
     }
-    if !is_authorized(&caller, Auth::FreeRpc) {
-        let cycles_available = ic_cdk::api::call::msg_cycles_available128();
-        if cycles_available < cycles_cost {
-            return Err(ProviderError::TooFewCycles {
-                expected: cycles_cost,
-                received: cycles_available,
-            }
-            .into());
+    check_caller_cycles_quota(&caller, cycles_cost)?;
+    if !is_authorized_for(&caller, Auth::FreeRpc, &auth_context) {
+        let is_http_rpc = caller == http_rpc_account();
+        if is_http_rpc {
+            check_http_rpc_spending(cycles_cost)?;
+        }
+        charge_cycles(&caller, cycles_cost)?;
+        if is_http_rpc {
+            record_http_rpc_spending(cycles_cost);
         }
-        ic_cdk::api::call::msg_cycles_accept128(cycles_cost);
         if let Some(mut provider) = provider {
             provider.cycles_owed += get_provider_cost(
                 &provider,
@@ -97,21 +115,111 @@ pub async fn do_http_request(
             (rpc_method.clone(), rpc_host.clone()),
             cycles_cost
         );
+        add_caller_usage(caller, |usage| usage.cycles_charged += cycles_cost);
     }
+    record_quota_usage(
+        &caller,
+        cycles_cost,
+        current_request_id(),
+        ic_cdk::api::time(),
+    );
     add_metric_entry!(requests, (rpc_method.clone(), rpc_host.clone()), 1);
-    match ic_cdk::api::management_canister::http_request::http_request(request, cycles_cost).await {
+    add_caller_usage(caller, |usage| usage.requests += 1);
+    let start_time = ic_cdk::api::time();
+    let result =
+        ic_cdk::api::management_canister::http_request::http_request(request, cycles_cost).await;
+    observe_metric_entry!(
+        response_latencies,
+        (rpc_method.clone(), rpc_host.clone()),
+        ic_cdk::api::time().saturating_sub(start_time) / 1_000_000,
+        RESPONSE_LATENCY_BUCKETS_MS
+    );
+    let mut trace = OutcallTrace {
+        request_id: current_request_id(),
+        provider_id,
+        host: rpc_host.0.clone(),
+        method: rpc_method.0.clone(),
+        status: None,
+        response_size: None,
+        cycles: cycles_cost,
+        outcome: "Ok".to_string(),
+    };
+    match result {
         Ok((response,)) => {
             let status: u32 = response.status.0.clone().try_into().unwrap_or(0);
+            let json_rpc_error_code = get_json_rpc_error_code(&response.body);
+            trace.status = Some(status);
+            trace.response_size = Some(response.body.len() as u64);
+            if let Some(code) = json_rpc_error_code {
+                trace.outcome = format!("JsonRpcError({})", code);
+            } else if !(200..300).contains(&status) {
+                trace.outcome = "HttpError".to_string();
+            }
+            trace_outcall(&trace);
+            observe_metric_entry!(
+                response_sizes,
+                (rpc_method.clone(), rpc_host.clone()),
+                response.body.len() as u64,
+                RESPONSE_SIZE_BUCKETS_BYTES
+            );
+            if let Some(code) = json_rpc_error_code {
+                add_metric_entry!(
+                    err_json_rpc,
+                    (rpc_method.clone(), rpc_host.clone(), code.into()),
+                    1
+                );
+            }
             add_metric_entry!(responses, (rpc_method, rpc_host, status.into()), 1);
             Ok(response)
         }
         Err((code, message)) => {
+            trace.outcome = format!("OutcallError({:?})", code);
+            trace_outcall(&trace);
             add_metric_entry!(err_http_outcall, (rpc_method, rpc_host), 1);
             Err(HttpOutcallError::IcError { code, message }.into())
         }
     }
 }
 
+/// Applies the permission checks, charges and quota accounting of outcalls to the given providers
+/// to a response served from the response cache. The cached response costs a flat fee, which is not
+/// owed to the providers.
+pub fn charge_cached_response(
+    caller: Principal,
+    rpc_method: MetricRpcMethod,
+    provider_ids: &[u64],
+) -> RpcResult<()> {
+    let mut is_free = true;
+    for provider_id in provider_ids {
+        let provider = PROVIDERS
+            .with(|p| p.borrow().get(provider_id))
+            .ok_or(ProviderError::ProviderNotFound)?;
+        let auth_context = AuthContext {
+            chain_id: Some(provider.chain_id),
+            method: &rpc_method.0,
+            provider_id: Some(provider.provider_id),
+        };
+        if !is_rpc_allowed_for(&caller, &auth_context) {
+            add_metric!(err_no_permission, 1);
+            return Err(ProviderError::NoPermission.into());
+        }
+        is_free &= is_authorized_for(&caller, Auth::FreeRpc, &auth_context);
+    }
+    let cycles_cost = RESPONSE_CACHE_HIT_CYCLES;
+    check_caller_cycles_quota(&caller, cycles_cost)?;
+    if !is_free {
+        charge_cycles(&caller, cycles_cost)?;
+        add_caller_usage(caller, |usage| usage.cycles_charged += cycles_cost);
+    }
+    record_quota_usage(
+        &caller,
+        cycles_cost,
+        current_request_id(),
+        ic_cdk::api::time(),
+    );
+    Ok(())
+}
+
 pub fn do_transform_http_request(args: TransformArgs) -> HttpResponse {
     HttpResponse {
         status: args.response.status,
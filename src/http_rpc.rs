@@ -0,0 +1,642 @@
+use candid::{CandidType, Deserialize, Principal};
+use cketh_common::eth_rpc::{JsonRpcError, RpcError, ValidationError};
+use cketh_common::numeric::BlockNumber;
+use ic_canisters_http_types::{HttpRequest, HttpResponse};
+use ic_stable_structures::Storable;
+use serde::Serialize;
+use serde_bytes::ByteBuf;
+use serde_json::{json, Value};
+
+use crate::candid_types::{
+    BlockTag, FeeHistoryArgs, GetLogsArgs, GetTransactionCountArgs, SendRawTransactionStatus,
+    TransactionReceipt,
+};
+use crate::*;
+
+/// HTTP response which may ask the HTTP gateway to retry a query as an update call.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct HttpGatewayResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+    pub upgrade: Option<bool>,
+}
+
+impl HttpGatewayResponse {
+    pub fn upgrade() -> Self {
+        Self {
+            status_code: 200,
+            headers: vec![],
+            body: ByteBuf::new(),
+            upgrade: Some(true),
+        }
+    }
+
+    fn text(status_code: u16, text: &str) -> Self {
+        Self {
+            status_code,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "text/plain; charset=utf-8".to_string(),
+            )],
+            body: ByteBuf::from(text.as_bytes().to_vec()),
+            upgrade: None,
+        }
+    }
+
+    fn json(value: &Value) -> Self {
+        Self {
+            status_code: 200,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/json; charset=utf-8".to_string(),
+            )],
+            body: ByteBuf::from(serde_json::to_vec(value).unwrap_or_default()),
+            upgrade: None,
+        }
+    }
+}
+
+impl From<HttpResponse> for HttpGatewayResponse {
+    fn from(response: HttpResponse) -> Self {
+        Self {
+            status_code: response.status_code,
+            headers: response.headers,
+            body: response.body,
+            upgrade: None,
+        }
+    }
+}
+
+/// Returns `true` if the request targets the JSON-RPC endpoint, which must be handled in an update call.
+pub fn is_http_rpc_request(request: &HttpRequest) -> bool {
+    request.path().starts_with(HTTP_RPC_PATH_PREFIX)
+}
+
+pub fn get_http_rpc_services() -> Vec<(u64, RpcServices)> {
+    HTTP_RPC_SERVICES.with(|s| {
+        s.borrow()
+            .iter()
+            .map(|(chain_id, services)| (chain_id, services.0))
+            .collect()
+    })
+}
+
+pub fn do_set_http_rpc_services(chain_id: u64, services: Option<RpcServices>) {
+    match services {
+        Some(services) => {
+            if services.chain_id() != chain_id {
+                ic_cdk::trap("RPC services do not match the given chain ID");
+            }
+            let services = StorableRpcServices(services);
+            if services.to_bytes().len() > RPC_SERVICES_MAX_SIZE as usize {
+                ic_cdk::trap("RPC services exceed the maximum size");
+            }
+            HTTP_RPC_SERVICES.with(|s| s.borrow_mut().insert(chain_id, services));
+        }
+        None => {
+            HTTP_RPC_SERVICES.with(|s| s.borrow_mut().remove(&chain_id));
+        }
+    }
+}
+
+/// Returns the principal whose prepaid balance pays for JSON-RPC requests served over HTTP.
+pub fn http_rpc_account() -> Principal {
+    Principal::from_slice(HTTP_RPC_ACCOUNT)
+}
+
+pub fn get_http_rpc_account() -> HttpRpcAccount {
+    let principal = http_rpc_account();
+    HttpRpcAccount {
+        principal,
+        balance: get_balance(&principal),
+        max_cycles_per_request: get_http_rpc_max_cycles_per_request(),
+    }
+}
+
+pub fn get_http_rpc_max_cycles_per_request() -> Option<u128> {
+    METADATA.with(|m| m.borrow().get().http_rpc_max_cycles_per_request)
+}
+
+pub fn set_http_rpc_max_cycles_per_request(max_cycles: Option<u128>) {
+    METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        metadata.http_rpc_max_cycles_per_request = max_cycles;
+        m.borrow_mut().set(metadata).unwrap();
+    });
+}
+
+/// Checks that charging the HTTP JSON-RPC account keeps the current HTTP request within its spending limit.
+pub fn check_http_rpc_spending(cycles_cost: u128) -> RpcResult<()> {
+    let max_cycles = match get_http_rpc_max_cycles_per_request() {
+        Some(max_cycles) => max_cycles,
+        None => return Ok(()),
+    };
+    let spent = current_request_id()
+        .and_then(|id| UNSTABLE_HTTP_RPC_SPENDING.with(|s| s.borrow().get(&id).copied()))
+        .unwrap_or_default();
+    if spent.saturating_add(cycles_cost) > max_cycles {
+        return Err(ValidationError::Custom(format!(
+            "HTTP JSON-RPC request exceeds the spending limit of {} cycles",
+            max_cycles
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Adds cycles charged to the HTTP JSON-RPC account to the spending of the current HTTP request.
+pub fn record_http_rpc_spending(cycles: u128) {
+    if let Some(id) = current_request_id() {
+        UNSTABLE_HTTP_RPC_SPENDING.with(|s| {
+            let mut spending = s.borrow_mut();
+            let spent = spending.entry(id).or_default();
+            *spent = spent.saturating_add(cycles);
+        });
+    }
+}
+
+/// Forgets the spending of an HTTP request once it is served.
+struct SpendingGuard(Option<u64>);
+
+impl Drop for SpendingGuard {
+    fn drop(&mut self) {
+        if let Some(id) = self.0 {
+            UNSTABLE_HTTP_RPC_SPENDING.with(|s| s.borrow_mut().remove(&id));
+        }
+    }
+}
+
+/// Describes RPC services for the audit log without including API keys or custom URLs.
+pub fn describe_rpc_services(services: &RpcServices) -> String {
+    match services {
+        RpcServices::EthMainnet(services) => format!("EthMainnet({:?})", services),
+        RpcServices::EthSepolia(services) => format!("EthSepolia({:?})", services),
+        RpcServices::Custom { chain_id, services } => {
+            format!(
+                "Custom {{ chainId: {}, services: {} }}",
+                chain_id,
+                services.len()
+            )
+        }
+    }
+}
+
+/// Serves a `POST /rpc/<chainId>` request containing a single JSON-RPC request or a batch.
+pub async fn serve_http_rpc_request(request: HttpRequest) -> HttpGatewayResponse {
+    let chain_id = match request
+        .path()
+        .strip_prefix(HTTP_RPC_PATH_PREFIX)
+        .and_then(|chain_id| chain_id.parse::<u64>().ok())
+    {
+        Some(chain_id) => chain_id,
+        None => return HttpGatewayResponse::text(404, "Not found"),
+    };
+    if !request.method.eq_ignore_ascii_case("POST") {
+        return HttpGatewayResponse::text(405, "Method not allowed");
+    }
+    let services = match HTTP_RPC_SERVICES.with(|s| s.borrow().get(&chain_id)) {
+        Some(services) => services.0,
+        None => return HttpGatewayResponse::text(404, "Chain not configured"),
+    };
+    let _guard = SpendingGuard(current_request_id());
+    let response = match serde_json::from_slice::<Value>(&request.body) {
+        Ok(Value::Array(batch)) => {
+            if batch.is_empty() || batch.len() > HTTP_RPC_MAX_BATCH_SIZE {
+                json_rpc_response(
+                    Value::Null,
+                    Err(JsonRpcError {
+                        code: JSON_RPC_INVALID_REQUEST_CODE,
+                        message: format!(
+                            "Batch must contain between 1 and {} requests",
+                            HTTP_RPC_MAX_BATCH_SIZE
+                        ),
+                    }),
+                )
+            } else {
+                let mut responses = Vec::with_capacity(batch.len());
+                for request in batch {
+                    responses.push(handle_json_rpc_request(chain_id, &services, request).await);
+                }
+                Value::Array(responses)
+            }
+        }
+        Ok(request) => handle_json_rpc_request(chain_id, &services, request).await,
+        Err(err) => json_rpc_response(
+            Value::Null,
+            Err(JsonRpcError {
+                code: JSON_RPC_PARSE_ERROR_CODE,
+                message: format!("Parse error: {}", err),
+            }),
+        ),
+    };
+    HttpGatewayResponse::json(&response)
+}
+
+async fn handle_json_rpc_request(chain_id: u64, services: &RpcServices, request: Value) -> Value {
+    let (id, method, params) = match parse_json_rpc_request(request) {
+        Ok(request) => request,
+        Err((id, err)) => return json_rpc_response(id, Err(err)),
+    };
+    let result = with_rpc_caller(
+        http_rpc_account(),
+        call_json_rpc_method(chain_id, services.clone(), &method, params),
+    )
+    .await;
+    json_rpc_response(id, result)
+}
+
+/// Splits a JSON-RPC request into its ID, method and positional parameters.
+fn parse_json_rpc_request(
+    request: Value,
+) -> Result<(Value, String, Vec<Value>), (Value, JsonRpcError)> {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let invalid_request = |id: Value, message: &str| {
+        Err((
+            id,
+            JsonRpcError {
+                code: JSON_RPC_INVALID_REQUEST_CODE,
+                message: message.to_string(),
+            },
+        ))
+    };
+    if !request.is_object() {
+        return invalid_request(Value::Null, "Request must be an object");
+    }
+    if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return invalid_request(id, "Unsupported JSON-RPC version");
+    }
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) => method.to_string(),
+        None => return invalid_request(id, "Missing method"),
+    };
+    let params = match request.get("params") {
+        None | Some(Value::Null) => vec![],
+        Some(Value::Array(params)) => params.clone(),
+        Some(_) => {
+            return Err((
+                id,
+                invalid_params("Expected positional parameters".to_string()),
+            ))
+        }
+    };
+    Ok((id, method, params))
+}
+
+async fn call_json_rpc_method(
+    chain_id: u64,
+    services: RpcServices,
+    method: &str,
+    params: Vec<Value>,
+) -> Result<Value, JsonRpcError> {
+    let param = |index: usize| params.get(index).cloned().unwrap_or(Value::Null);
+    match method {
+        "eth_chainId" => Ok(json!(format!("{:#x}", chain_id))),
+        "net_version" => Ok(json!(chain_id.to_string())),
+        "eth_getLogs" => {
+            let args = parse_get_logs_args(&param(0))?;
+            let result = match CandidRpcClient::new(services, None) {
+                Ok(client) => client.eth_get_logs(args).await,
+                Err(err) => Err(err).into(),
+            };
+            add_rpc_error_metrics(rpc_caller(), RpcMethod::EthGetLogs, &result);
+            into_json_result(result, |logs| to_json(&logs))
+        }
+        "eth_getBlockByNumber" => {
+            let block = parse_block_tag(&param(0))?;
+            let result = match CandidRpcClient::new(services, None) {
+                Ok(client) => client.eth_get_block_by_number(block).await,
+                Err(err) => Err(err).into(),
+            };
+            add_rpc_error_metrics(rpc_caller(), RpcMethod::EthGetBlockByNumber, &result);
+            into_json_result(result, |block| to_json(&block))
+        }
+        "eth_getTransactionReceipt" => {
+            let hash = parse_string(&param(0), "transaction hash")?;
+            let result = match CandidRpcClient::new(services, None) {
+                Ok(client) => client.eth_get_transaction_receipt(hash).await,
+                Err(err) => Err(err).into(),
+            };
+            add_rpc_error_metrics(rpc_caller(), RpcMethod::EthGetTransactionReceipt, &result);
+            into_json_result(result, |receipt| match receipt {
+                Some(receipt) => transaction_receipt_to_json(receipt),
+                None => Ok(Value::Null),
+            })
+        }
+        "eth_getTransactionCount" => {
+            let args = GetTransactionCountArgs {
+                address: parse_string(&param(0), "address")?,
+                block: parse_block_tag(&param(1))?,
+            };
+            let result = match CandidRpcClient::new(services, None) {
+                Ok(client) => client.eth_get_transaction_count(args).await,
+                Err(err) => Err(err).into(),
+            };
+            add_rpc_error_metrics(rpc_caller(), RpcMethod::EthGetTransactionCount, &result);
+            into_json_result(result, |count| Ok(json!(nat_to_hex(&count))))
+        }
+        "eth_feeHistory" => {
+            let args = FeeHistoryArgs {
+                block_count: parse_quantity(&param(0), "block count")?,
+                newest_block: parse_block_tag(&param(1))?,
+                reward_percentiles: parse_reward_percentiles(&param(2))?,
+            };
+            let result = match CandidRpcClient::new(services, None) {
+                Ok(client) => client.eth_fee_history(args).await,
+                Err(err) => Err(err).into(),
+            };
+            add_rpc_error_metrics(rpc_caller(), RpcMethod::EthFeeHistory, &result);
+            into_json_result(result, |history| to_json(&history))
+        }
+        "eth_sendRawTransaction" => {
+            let transaction = parse_string(&param(0), "raw transaction")?;
+            let result = match CandidRpcClient::new(services, None) {
+                Ok(client) => client.eth_send_raw_transaction(transaction).await,
+                Err(err) => Err(err).into(),
+            };
+            add_rpc_error_metrics(rpc_caller(), RpcMethod::EthSendRawTransaction, &result);
+            into_json_result(result, |status| match status {
+                SendRawTransactionStatus::Ok(hash) => {
+                    Ok(hash.map_or(Value::Null, |hash| json!(format!("{:#x}", hash))))
+                }
+                SendRawTransactionStatus::InsufficientFunds => {
+                    Err(server_error("insufficient funds for gas * price + value"))
+                }
+                SendRawTransactionStatus::NonceTooLow => Err(server_error("nonce too low")),
+                SendRawTransactionStatus::NonceTooHigh => Err(server_error("nonce too high")),
+            })
+        }
+        _ => Err(JsonRpcError {
+            code: JSON_RPC_METHOD_NOT_FOUND_CODE,
+            message: format!("Method not supported: {}", method),
+        }),
+    }
+}
+
+fn json_rpc_response(id: Value, result: Result<Value, JsonRpcError>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(err) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": err.code, "message": err.message}
+        }),
+    }
+}
+
+fn into_json_result<T>(
+    result: MultiRpcResult<T>,
+    f: impl FnOnce(T) -> Result<Value, JsonRpcError>,
+) -> Result<Value, JsonRpcError> {
+    match result {
+        MultiRpcResult::Consistent(Ok(value)) => f(value),
+        MultiRpcResult::Consistent(Err(err)) => Err(rpc_error_to_json_rpc_error(err)),
+        MultiRpcResult::Inconsistent(_) => {
+            Err(server_error("Inconsistent results from RPC providers"))
+        }
+    }
+}
+
+fn rpc_error_to_json_rpc_error(err: RpcError) -> JsonRpcError {
+    match err {
+        RpcError::JsonRpcError(err) => err,
+        RpcError::ValidationError(err) => invalid_params(format!("{:?}", err)),
+        err => JsonRpcError {
+            code: JSON_RPC_INTERNAL_ERROR_CODE,
+            message: format!("{:?}", err),
+        },
+    }
+}
+
+fn invalid_params(message: String) -> JsonRpcError {
+    JsonRpcError {
+        code: JSON_RPC_INVALID_PARAMS_CODE,
+        message,
+    }
+}
+
+fn server_error(message: &str) -> JsonRpcError {
+    JsonRpcError {
+        code: JSON_RPC_SERVER_ERROR_CODE,
+        message: message.to_string(),
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value, JsonRpcError> {
+    serde_json::to_value(value).map_err(|err| JsonRpcError {
+        code: JSON_RPC_INTERNAL_ERROR_CODE,
+        message: format!("Unable to serialize result: {}", err),
+    })
+}
+
+fn nat_to_hex(value: &candid::Nat) -> String {
+    format!("{:#x}", value.0)
+}
+
+fn transaction_receipt_to_json(receipt: TransactionReceipt) -> Result<Value, JsonRpcError> {
+    Ok(json!({
+        "blockHash": receipt.block_hash,
+        "blockNumber": to_json(&receipt.block_number)?,
+        "effectiveGasPrice": nat_to_hex(&receipt.effective_gas_price),
+        "gasUsed": nat_to_hex(&receipt.gas_used),
+        "status": nat_to_hex(&receipt.status),
+        "transactionHash": receipt.transaction_hash,
+        "contractAddress": receipt.contract_address,
+        "from": receipt.from,
+        "logs": to_json(&receipt.logs)?,
+        "logsBloom": receipt.logs_bloom,
+        "to": receipt.to,
+        "transactionIndex": nat_to_hex(&receipt.transaction_index),
+        "type": receipt.r#type,
+    }))
+}
+
+fn parse_string(value: &Value, name: &str) -> Result<String, JsonRpcError> {
+    value
+        .as_str()
+        .map(|value| value.to_string())
+        .ok_or_else(|| invalid_params(format!("Expected {} as a string", name)))
+}
+
+fn parse_block_tag(value: &Value) -> Result<BlockTag, JsonRpcError> {
+    match value {
+        Value::Null => Ok(BlockTag::Latest),
+        Value::String(tag) => match tag.as_str() {
+            "latest" => Ok(BlockTag::Latest),
+            "finalized" => Ok(BlockTag::Finalized),
+            "safe" => Ok(BlockTag::Safe),
+            "earliest" => Ok(BlockTag::Earliest),
+            "pending" => Ok(BlockTag::Pending),
+            number => BlockNumber::from_str_hex(number)
+                .map(BlockTag::Number)
+                .map_err(|_| invalid_params(format!("Invalid block tag: {}", number))),
+        },
+        _ => Err(invalid_params("Expected block tag as a string".to_string())),
+    }
+}
+
+fn parse_quantity(value: &Value, name: &str) -> Result<u128, JsonRpcError> {
+    let invalid = || invalid_params(format!("Invalid {}", name));
+    match value {
+        Value::Number(number) => number.as_u64().map(u128::from).ok_or_else(invalid),
+        Value::String(hex) => hex
+            .strip_prefix("0x")
+            .and_then(|hex| u128::from_str_radix(hex, 16).ok())
+            .ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_reward_percentiles(value: &Value) -> Result<Option<Vec<u8>>, JsonRpcError> {
+    let percentiles = match value {
+        Value::Null => return Ok(None),
+        Value::Array(percentiles) => percentiles,
+        _ => {
+            return Err(invalid_params(
+                "Expected reward percentiles as an array".to_string(),
+            ))
+        }
+    };
+    percentiles
+        .iter()
+        .map(|percentile| match percentile.as_f64() {
+            Some(percentile) if (0.0..=100.0).contains(&percentile) => Ok(percentile as u8),
+            _ => Err(invalid_params(format!(
+                "Invalid reward percentile: {}",
+                percentile
+            ))),
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+fn parse_hex_strings(value: &Value, name: &str) -> Result<Vec<String>, JsonRpcError> {
+    match value {
+        Value::Null => Ok(vec![]),
+        Value::String(value) => Ok(vec![value.clone()]),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| parse_string(value, name))
+            .collect(),
+        _ => Err(invalid_params(format!("Invalid {}", name))),
+    }
+}
+
+fn parse_get_logs_args(filter: &Value) -> Result<GetLogsArgs, JsonRpcError> {
+    if !filter.is_object() {
+        return Err(invalid_params("Expected filter object".to_string()));
+    }
+    if filter.get("blockHash").is_some() {
+        return Err(invalid_params(
+            "blockHash filter is not supported".to_string(),
+        ));
+    }
+    let block_tag = |name: &str| match filter.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => parse_block_tag(value).map(Some),
+    };
+    let topics = match filter.get("topics") {
+        None | Some(Value::Null) => None,
+        Some(Value::Array(topics)) => Some(
+            topics
+                .iter()
+                .map(|topic| parse_hex_strings(topic, "topic"))
+                .collect::<Result<_, _>>()?,
+        ),
+        Some(_) => return Err(invalid_params("Expected topics as an array".to_string())),
+    };
+    Ok(GetLogsArgs {
+        from_block: block_tag("fromBlock")?,
+        to_block: block_tag("toBlock")?,
+        addresses: parse_hex_strings(filter.get("address").unwrap_or(&Value::Null), "address")?,
+        topics,
+    })
+}
+
+#[test]
+fn test_parse_json_rpc_request() {
+    let (id, method, params) = parse_json_rpc_request(
+        json!({"jsonrpc": "2.0", "id": 7, "method": "eth_chainId", "params": []}),
+    )
+    .unwrap();
+    assert_eq!(id, json!(7));
+    assert_eq!(method, "eth_chainId");
+    assert_eq!(params, Vec::<Value>::new());
+
+    let (id, err) =
+        parse_json_rpc_request(json!({"jsonrpc": "2.0", "id": "a", "params": []})).unwrap_err();
+    assert_eq!(id, json!("a"));
+    assert_eq!(err.code, JSON_RPC_INVALID_REQUEST_CODE);
+
+    let (_, err) = parse_json_rpc_request(
+        json!({"jsonrpc": "2.0", "id": 1, "method": "eth_getLogs", "params": {}}),
+    )
+    .unwrap_err();
+    assert_eq!(err.code, JSON_RPC_INVALID_PARAMS_CODE);
+
+    let (id, err) = parse_json_rpc_request(json!(5)).unwrap_err();
+    assert_eq!(id, Value::Null);
+    assert_eq!(err.code, JSON_RPC_INVALID_REQUEST_CODE);
+}
+
+#[test]
+fn test_parse_block_tag() {
+    assert_eq!(parse_block_tag(&Value::Null).unwrap(), BlockTag::Latest);
+    assert_eq!(
+        parse_block_tag(&json!("finalized")).unwrap(),
+        BlockTag::Finalized
+    );
+    assert_eq!(
+        parse_block_tag(&json!("0x10")).unwrap(),
+        BlockTag::Number(BlockNumber::new(16))
+    );
+    assert_eq!(
+        parse_block_tag(&json!("latest!")).unwrap_err().code,
+        JSON_RPC_INVALID_PARAMS_CODE
+    );
+    assert_eq!(
+        parse_block_tag(&json!(16)).unwrap_err().code,
+        JSON_RPC_INVALID_PARAMS_CODE
+    );
+}
+
+#[test]
+fn test_parse_get_logs_args() {
+    let args = parse_get_logs_args(&json!({
+        "fromBlock": "0x1",
+        "address": "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+        "topics": [null, "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef", ["0x01", "0x02"]]
+    }))
+    .unwrap();
+    assert_eq!(
+        args,
+        GetLogsArgs {
+            from_block: Some(BlockTag::Number(BlockNumber::new(1))),
+            to_block: None,
+            addresses: vec!["0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()],
+            topics: Some(vec![
+                vec![],
+                vec![
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                        .to_string()
+                ],
+                vec!["0x01".to_string(), "0x02".to_string()],
+            ]),
+        }
+    );
+    assert!(parse_get_logs_args(&json!({"blockHash": "0x01"})).is_err());
+    assert!(parse_get_logs_args(&json!([])).is_err());
+}
+
+#[test]
+fn test_parse_fee_history_params() {
+    assert_eq!(parse_quantity(&json!("0x5"), "block count").unwrap(), 5);
+    assert_eq!(parse_quantity(&json!(5), "block count").unwrap(), 5);
+    assert!(parse_quantity(&json!("5"), "block count").is_err());
+    assert_eq!(
+        parse_reward_percentiles(&json!([25, 75.5])).unwrap(),
+        Some(vec![25, 75])
+    );
+    assert_eq!(parse_reward_percentiles(&Value::Null).unwrap(), None);
+    assert!(parse_reward_percentiles(&json!([101])).is_err());
+}
@@ -1,25 +1,67 @@
 pub use candid::Principal;
 
+mod abi_codec;
 mod accounting;
+mod audit;
 mod auth;
+mod balances;
+mod cache;
 mod candid_rpc;
+mod certification;
+mod chain_head;
 mod constants;
+mod dashboard;
+mod erc20;
+mod fees;
+mod hosts;
 mod http;
+mod http_rpc;
+mod log_cursor;
 mod memory;
 mod metrics;
+mod nonce_manager;
+mod poll_guard;
 mod providers;
+mod quotas;
+mod signer;
+mod subscriptions;
+mod trace;
+mod transaction;
+mod tx_tracker;
 mod types;
+mod usage;
 mod util;
 mod validate;
 
+pub use crate::abi_codec::*;
 pub use crate::accounting::*;
+pub use crate::audit::*;
 pub use crate::auth::*;
+pub use crate::balances::*;
+pub use crate::cache::*;
 pub use crate::candid_rpc::*;
+pub use crate::certification::*;
+pub use crate::chain_head::*;
 pub use crate::constants::*;
+pub use crate::dashboard::*;
+pub use crate::erc20::*;
+pub use crate::fees::*;
+pub use crate::hosts::*;
 pub use crate::http::*;
+pub use crate::http_rpc::*;
+pub use crate::log_cursor::*;
 pub use crate::memory::*;
 pub use crate::metrics::*;
+pub use crate::nonce_manager::*;
+pub use crate::poll_guard::*;
 pub use crate::providers::*;
+pub use crate::quotas::*;
+pub use crate::signer::*;
+pub use crate::subscriptions::*;
+pub use crate::trace::*;
+pub use crate::transaction::*;
+pub use crate::tx_tracker::*;
 pub use crate::types::*;
+pub use crate::usage::*;
 pub use crate::util::*;
 pub use crate::validate::*;
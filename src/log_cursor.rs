@@ -0,0 +1,346 @@
+use cketh_common::{
+    eth_rpc::{GetLogsParam, LogEntry, ProviderError, RpcError},
+    eth_rpc_client::RpcConfig,
+    numeric::BlockNumber,
+};
+use ic_stable_structures::Storable;
+use num_traits::ToPrimitive;
+
+use crate::candid_types::{BlockTag, GetLogsArgs};
+use crate::*;
+
+fn next_log_cursor_id() -> u64 {
+    METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        let id = metadata.next_log_cursor_id.unwrap_or_default();
+        metadata.next_log_cursor_id = Some(id + 1);
+        m.borrow_mut().set(metadata).unwrap();
+        id
+    })
+}
+
+pub fn get_log_cursor(id: u64) -> Option<LogCursor> {
+    LOG_CURSORS.with(|c| c.borrow().get(&id))
+}
+
+pub fn get_log_cursors(owner: Principal) -> Vec<LogCursor> {
+    LOG_CURSORS.with(|c| {
+        c.borrow()
+            .iter()
+            .map(|(_, cursor)| cursor)
+            .filter(|cursor| cursor.owner == owner)
+            .collect()
+    })
+}
+
+pub fn do_create_log_cursor(owner: Principal, services: RpcServices, filter: GetLogsArgs) -> u64 {
+    if owner == Principal::anonymous() {
+        ic_cdk::trap("Anonymous principals cannot create log cursors");
+    }
+    let confirmation = filter.to_block.clone().unwrap_or(BlockTag::Latest);
+    if !matches!(
+        confirmation,
+        BlockTag::Latest | BlockTag::Safe | BlockTag::Finalized
+    ) {
+        ic_cdk::trap("Log cursors only support the Latest, Safe and Finalized tags for toBlock");
+    }
+    let start_block = match &filter.from_block {
+        Some(BlockTag::Number(n)) => {
+            Some(block_number_to_u64(*n).unwrap_or_else(|err| ic_cdk::trap(&err)))
+        }
+        Some(BlockTag::Earliest) => Some(0),
+        _ => None,
+    };
+    if let Err(err) = GetLogsParam::try_from(filter.clone()) {
+        ic_cdk::trap(&format!("Invalid log filter: {:?}", err));
+    }
+    if get_log_cursors(owner).len() >= MAX_LOG_CURSORS_PER_CALLER {
+        ic_cdk::trap("Too many log cursors");
+    }
+    let id = next_log_cursor_id();
+    let cursor = LogCursor {
+        id,
+        owner,
+        services,
+        addresses: filter.addresses,
+        topics: filter.topics,
+        confirmation,
+        start_block,
+        next_block: start_block,
+    };
+    if cursor.to_bytes().len() > SUBSCRIPTION_MAX_SIZE as usize {
+        ic_cdk::trap("Log cursor exceeds the maximum size");
+    }
+    LOG_CURSORS.with(|c| c.borrow_mut().insert(id, cursor));
+    id
+}
+
+/// Removes a log cursor owned by the caller together with its stored logs.
+pub fn do_delete_log_cursor(caller: Principal, id: u64) -> bool {
+    match get_log_cursor(id) {
+        Some(cursor) if cursor.owner == caller => {
+            LOG_CURSORS.with(|c| c.borrow_mut().remove(&id));
+            remove_cursor_logs(id, 0, u64::MAX);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn cursor_log_keys(id: u64, from: u64, to: u64) -> Vec<LogCursorEntryKey> {
+    LOG_CURSOR_ENTRIES.with(|e| {
+        e.borrow()
+            .range(LogCursorEntryKey::new(id, from, 0)..=LogCursorEntryKey::new(id, to, u64::MAX))
+            .map(|(key, _)| key)
+            .collect()
+    })
+}
+
+/// Returns the stored logs of a cursor between the given blocks (inclusive).
+pub fn get_cursor_logs(id: u64, from: u64, to: u64) -> Vec<LogEntry> {
+    LOG_CURSOR_ENTRIES.with(|e| {
+        e.borrow()
+            .range(LogCursorEntryKey::new(id, from, 0)..=LogCursorEntryKey::new(id, to, u64::MAX))
+            .map(|(_, entry)| entry.0)
+            .collect()
+    })
+}
+
+fn remove_cursor_logs(id: u64, from: u64, to: u64) {
+    for key in cursor_log_keys(id, from, to) {
+        LOG_CURSOR_ENTRIES.with(|e| e.borrow_mut().remove(&key));
+    }
+}
+
+fn cursor_log_key(id: u64, log: &LogEntry) -> Option<LogCursorEntryKey> {
+    let block_number = block_number_to_u64(log.block_number?).ok()?;
+    let log_index = candid::Nat::from(log.log_index?).0.to_u64()?;
+    Some(LogCursorEntryKey::new(id, block_number, log_index))
+}
+
+/// Stores a returned log, unless it is pending or too large (in which case its removal cannot be reported).
+fn insert_cursor_log(id: u64, log: &LogEntry) {
+    if log.block_hash.is_none() {
+        return;
+    }
+    let key = match cursor_log_key(id, log) {
+        Some(key) => key,
+        None => return,
+    };
+    let entry = StorableLogEntry(log.clone());
+    if entry.to_bytes().len() <= LOG_CURSOR_ENTRY_MAX_SIZE as usize {
+        LOG_CURSOR_ENTRIES.with(|e| e.borrow_mut().insert(key, entry));
+    }
+}
+
+/// Compares previously returned logs with the current logs of the same blocks. Returns the logs of
+/// blocks which are no longer part of the chain (marked as removed) and the logs which were not
+/// returned before.
+pub fn diff_cursor_logs(
+    previous: Vec<LogEntry>,
+    current: Vec<LogEntry>,
+) -> (Vec<LogEntry>, Vec<LogEntry>) {
+    let is_known_block = |logs: &[LogEntry], log: &LogEntry| {
+        log.block_hash.is_some() && logs.iter().any(|l| l.block_hash == log.block_hash)
+    };
+    let removed = previous
+        .iter()
+        .filter(|log| !is_known_block(&current, log))
+        .map(|log| LogEntry {
+            removed: true,
+            ..log.clone()
+        })
+        .collect();
+    let added = current
+        .iter()
+        .filter(|log| !is_known_block(&previous, log))
+        .cloned()
+        .collect();
+    (removed, added)
+}
+
+/// Resolves the block up to which logs are returned. If the providers disagree, the lowest
+/// reported block is used.
+async fn get_cursor_head(
+    client: &CandidRpcClient,
+    chain_id: u64,
+    tag: BlockTag,
+) -> Result<u64, RpcError> {
+    let block_number = match pin_block_tag(chain_id, tag) {
+        BlockTag::Number(n) => n,
+        tag => match client.eth_get_block_by_number(tag).await {
+            MultiRpcResult::Consistent(result) => result?.number,
+            MultiRpcResult::Inconsistent(results) => {
+                let mut min = None;
+                let mut error = None;
+                for (_, result) in results {
+                    match result {
+                        Ok(block) => {
+                            min = Some(
+                                min.map_or(block.number, |min: BlockNumber| min.min(block.number)),
+                            )
+                        }
+                        Err(err) => error = Some(err),
+                    }
+                }
+                match (min, error) {
+                    (Some(number), _) => number,
+                    (None, Some(err)) => return Err(err),
+                    (None, None) => return Err(ProviderError::ProviderNotFound.into()),
+                }
+            }
+        },
+    };
+    Ok(block_number_to_u64(block_number).unwrap_or_else(|err| ic_cdk::trap(&err)))
+}
+
+/// Returns the logs since the previous poll of a log cursor. Blocks up to `LOG_CURSOR_REORG_DEPTH`
+/// behind the cursor are checked again, and the logs of reorged blocks are returned first with
+/// `removed: true`.
+pub async fn do_poll_log_cursor(
+    caller: Principal,
+    id: u64,
+    config: Option<RpcConfig>,
+) -> MultiRpcResult<Vec<LogEntry>> {
+    let cursor = match get_log_cursor(id) {
+        Some(cursor) if cursor.owner == caller => cursor,
+        _ => ic_cdk::trap("Log cursor not found"),
+    };
+    let _guard = PollGuard::new(PollTask::LogCursor(id))
+        .unwrap_or_else(|| ic_cdk::trap("Log cursor is already being polled"));
+    let client = match CandidRpcClient::new(cursor.services.clone(), config) {
+        Ok(client) => client,
+        Err(err) => return Err(err).into(),
+    };
+    let chain_id = cursor.services.chain_id();
+    let head = match get_cursor_head(&client, chain_id, cursor.confirmation.clone()).await {
+        Ok(head) => head,
+        Err(err) => return Err(err).into(),
+    };
+    let next_block = cursor.next_block.unwrap_or(head);
+    let start_block = cursor.start_block.unwrap_or(next_block);
+    let from = next_block
+        .saturating_sub(LOG_CURSOR_REORG_DEPTH)
+        .max(start_block);
+    let to = head.min(from + ETH_GET_LOGS_MAX_BLOCKS as u64 - 1);
+    if to < from {
+        return MultiRpcResult::Consistent(Ok(vec![]));
+    }
+    let args = GetLogsArgs {
+        from_block: Some(BlockTag::Number(BlockNumber::new(from as u128))),
+        to_block: Some(BlockTag::Number(BlockNumber::new(to as u128))),
+        addresses: cursor.addresses.clone(),
+        topics: cursor.topics.clone(),
+    };
+    let logs = match client.eth_get_logs(args).await {
+        MultiRpcResult::Consistent(Ok(logs)) => logs,
+        result => return result,
+    };
+    // The cursor may have been deleted in the meantime
+    if get_log_cursor(id).is_none() {
+        return MultiRpcResult::Consistent(Ok(logs));
+    }
+    let (removed, added) = diff_cursor_logs(get_cursor_logs(id, from, to), logs);
+    for log in &removed {
+        if let Some(key) = cursor_log_key(id, log) {
+            LOG_CURSOR_ENTRIES.with(|e| e.borrow_mut().remove(&key));
+        }
+    }
+    for log in &added {
+        insert_cursor_log(id, log);
+    }
+    let next_block = next_block.max(to + 1);
+    if let Some(prune_to) = next_block
+        .saturating_sub(LOG_CURSOR_REORG_DEPTH)
+        .checked_sub(1)
+    {
+        remove_cursor_logs(id, 0, prune_to);
+    }
+    LOG_CURSORS.with(|c| {
+        c.borrow_mut().insert(
+            id,
+            LogCursor {
+                start_block: Some(start_block),
+                next_block: Some(next_block),
+                ..cursor
+            },
+        )
+    });
+    MultiRpcResult::Consistent(Ok(removed.into_iter().chain(added).collect()))
+}
+
+#[cfg(test)]
+fn test_log(block_number: u128, block_hash: &str, log_index: u128) -> LogEntry {
+    use cketh_common::{address::Address, checked_amount::CheckedAmountOf, eth_rpc::Hash};
+    use std::str::FromStr;
+    LogEntry {
+        address: Address::from_str("0xdac17f958d2ee523a2206206994597c13d831ec7").unwrap(),
+        topics: vec![],
+        data: cketh_common::eth_rpc::Data(vec![]),
+        block_number: Some(BlockNumber::new(block_number)),
+        transaction_hash: None,
+        transaction_index: None,
+        block_hash: Some(Hash::from_str(block_hash).unwrap()),
+        log_index: Some(CheckedAmountOf::new(log_index)),
+        removed: false,
+    }
+}
+
+#[test]
+fn test_diff_cursor_logs() {
+    let hash_a = format!("0x{:064x}", 0xa);
+    let hash_b = format!("0x{:064x}", 0xb);
+    let hash_c = format!("0x{:064x}", 0xc);
+    let previous = vec![test_log(100, &hash_a, 0), test_log(101, &hash_b, 1)];
+
+    // Unchanged blocks
+    let (removed, added) = diff_cursor_logs(previous.clone(), previous.clone());
+    assert_eq!((removed, added), (vec![], vec![]));
+
+    // Block 101 was replaced and block 102 is new
+    let current = vec![
+        test_log(100, &hash_a, 0),
+        test_log(101, &hash_c, 3),
+        test_log(102, &hash_a, 0),
+    ];
+    let (removed, added) = diff_cursor_logs(previous, current);
+    assert_eq!(
+        removed,
+        vec![LogEntry {
+            removed: true,
+            ..test_log(101, &hash_b, 1)
+        }]
+    );
+    assert_eq!(
+        added,
+        vec![test_log(101, &hash_c, 3), test_log(102, &hash_a, 0)]
+    );
+}
+
+#[test]
+fn test_log_cursor_storage() {
+    let owner = Principal::from_slice(&[1]);
+    let id = do_create_log_cursor(
+        owner,
+        RpcServices::EthMainnet(None),
+        GetLogsArgs {
+            from_block: Some(BlockTag::Number(BlockNumber::new(100))),
+            to_block: None,
+            addresses: vec![],
+            topics: None,
+        },
+    );
+    let cursor = get_log_cursor(id).unwrap();
+    assert_eq!(cursor.confirmation, BlockTag::Latest);
+    assert_eq!(cursor.next_block, Some(100));
+
+    let hash = format!("0x{:064x}", 0xa);
+    insert_cursor_log(id, &test_log(100, &hash, 0));
+    insert_cursor_log(id, &test_log(105, &hash, 2));
+    assert_eq!(get_cursor_logs(id, 101, 200), vec![test_log(105, &hash, 2)]);
+    assert_eq!(get_cursor_logs(id + 1, 0, 200), vec![]);
+
+    assert!(!do_delete_log_cursor(Principal::from_slice(&[2]), id));
+    assert!(do_delete_log_cursor(owner, id));
+    assert_eq!(get_cursor_logs(id, 0, u64::MAX), vec![]);
+}
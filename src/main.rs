@@ -1,5 +1,5 @@
 use candid::candid_method;
-use cketh_common::eth_rpc::{Block, FeeHistory, LogEntry, RpcError};
+use cketh_common::eth_rpc::{Block, FeeHistory, LogEntry, RpcError, ValidationError};
 
 use cketh_common::eth_rpc_client::providers::RpcService;
 use cketh_common::eth_rpc_client::RpcConfig;
@@ -22,10 +22,15 @@ pub async fn eth_get_logs(
     config: Option<RpcConfig>,
     args: candid_types::GetLogsArgs,
 ) -> MultiRpcResult<Vec<LogEntry>> {
-    match CandidRpcClient::new(source, config) {
-        Ok(source) => source.eth_get_logs(args).await,
-        Err(err) => Err(err).into(),
-    }
+    trace_request("eth_getLogs", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => source.eth_get_logs(args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthGetLogs, &result);
+        result
+    })
+    .await
 }
 
 #[update(name = "eth_getBlockByNumber")]
@@ -35,10 +40,15 @@ pub async fn eth_get_block_by_number(
     config: Option<RpcConfig>,
     block: candid_types::BlockTag,
 ) -> MultiRpcResult<Block> {
-    match CandidRpcClient::new(source, config) {
-        Ok(source) => source.eth_get_block_by_number(block).await,
-        Err(err) => Err(err).into(),
-    }
+    trace_request("eth_getBlockByNumber", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => source.eth_get_block_by_number(block).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthGetBlockByNumber, &result);
+        result
+    })
+    .await
 }
 
 #[update(name = "eth_getTransactionReceipt")]
@@ -48,10 +58,19 @@ pub async fn eth_get_transaction_receipt(
     config: Option<RpcConfig>,
     hash: String,
 ) -> MultiRpcResult<Option<candid_types::TransactionReceipt>> {
-    match CandidRpcClient::new(source, config) {
-        Ok(source) => source.eth_get_transaction_receipt(hash).await,
-        Err(err) => Err(err).into(),
-    }
+    trace_request("eth_getTransactionReceipt", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => source.eth_get_transaction_receipt(hash).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(
+            ic_cdk::caller(),
+            RpcMethod::EthGetTransactionReceipt,
+            &result,
+        );
+        result
+    })
+    .await
 }
 
 #[update(name = "eth_getTransactionCount")]
@@ -61,10 +80,33 @@ pub async fn eth_get_transaction_count(
     config: Option<RpcConfig>,
     args: candid_types::GetTransactionCountArgs,
 ) -> MultiRpcResult<candid::Nat> {
-    match CandidRpcClient::new(source, config) {
-        Ok(source) => source.eth_get_transaction_count(args).await,
-        Err(err) => Err(err).into(),
-    }
+    trace_request("eth_getTransactionCount", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => source.eth_get_transaction_count(args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthGetTransactionCount, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "eth_getCode")]
+#[candid_method(rename = "eth_getCode")]
+pub async fn eth_get_code(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: candid_types::GetCodeArgs,
+) -> MultiRpcResult<String> {
+    trace_request("eth_getCode", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => source.eth_get_code(args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthGetCode, &result);
+        result
+    })
+    .await
 }
 
 #[update(name = "eth_feeHistory")]
@@ -74,10 +116,156 @@ pub async fn eth_fee_history(
     config: Option<RpcConfig>,
     args: candid_types::FeeHistoryArgs,
 ) -> MultiRpcResult<Option<FeeHistory>> {
-    match CandidRpcClient::new(source, config) {
-        Ok(source) => source.eth_fee_history(args).await,
-        Err(err) => Err(err).into(),
-    }
+    trace_request("eth_feeHistory", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => source.eth_fee_history(args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthFeeHistory, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "estimateFees")]
+#[candid_method(rename = "estimateFees")]
+pub async fn estimate_fees(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: EstimateFeesArgs,
+) -> MultiRpcResult<FeeEstimate> {
+    trace_request("estimateFees", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => evm_rpc::estimate_fees(&source, args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthFeeHistory, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "erc20BalanceOf")]
+#[candid_method(rename = "erc20BalanceOf")]
+pub async fn erc20_balance_of(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: Erc20BalanceOfArgs,
+) -> MultiRpcResult<candid::Nat> {
+    trace_request("erc20BalanceOf", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => evm_rpc::erc20_balance_of(&source, args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthCall, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "erc20Allowance")]
+#[candid_method(rename = "erc20Allowance")]
+pub async fn erc20_allowance(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: Erc20AllowanceArgs,
+) -> MultiRpcResult<candid::Nat> {
+    trace_request("erc20Allowance", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => evm_rpc::erc20_allowance(&source, args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthCall, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "erc20TotalSupply")]
+#[candid_method(rename = "erc20TotalSupply")]
+pub async fn erc20_total_supply(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: Erc20CallArgs,
+) -> MultiRpcResult<candid::Nat> {
+    trace_request("erc20TotalSupply", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => evm_rpc::erc20_total_supply(&source, args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthCall, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "erc20Decimals")]
+#[candid_method(rename = "erc20Decimals")]
+pub async fn erc20_decimals(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: Erc20CallArgs,
+) -> MultiRpcResult<u8> {
+    trace_request("erc20Decimals", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => evm_rpc::erc20_decimals(&source, args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthCall, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "erc20Symbol")]
+#[candid_method(rename = "erc20Symbol")]
+pub async fn erc20_symbol(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: Erc20CallArgs,
+) -> MultiRpcResult<String> {
+    trace_request("erc20Symbol", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => evm_rpc::erc20_symbol(&source, args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthCall, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "eth_callAbi")]
+#[candid_method(rename = "eth_callAbi")]
+pub async fn eth_call_abi(
+    source: RpcServices,
+    config: Option<RpcConfig>,
+    args: CallAbiArgs,
+) -> MultiRpcResult<Vec<AbiValue>> {
+    trace_request("eth_callAbi", async move {
+        let result = match CandidRpcClient::new(source, config) {
+            Ok(source) => evm_rpc::eth_call_abi(&source, args).await,
+            Err(err) => Err(err).into(),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthCall, &result);
+        result
+    })
+    .await
+}
+
+#[query(name = "decodeLogs")]
+#[candid_method(query, rename = "decodeLogs")]
+fn decode_logs(
+    event_signature: String,
+    logs: Vec<LogEntry>,
+) -> Result<Vec<DecodedLog>, ValidationError> {
+    do_decode_logs(&event_signature, &logs)
+}
+
+#[query(name = "decodeErc20Events")]
+#[candid_method(query, rename = "decodeErc20Events")]
+fn decode_erc20_events(logs: Vec<LogEntry>) -> Vec<Erc20Event> {
+    evm_rpc::decode_erc20_events(&logs)
 }
 
 #[update(name = "eth_sendRawTransaction")]
@@ -86,15 +274,32 @@ pub async fn eth_send_raw_transaction(
     source: RpcServices,
     config: Option<RpcConfig>,
     raw_signed_transaction_hex: String,
-) -> MultiRpcResult<candid_types::SendRawTransactionStatus> {
-    match CandidRpcClient::new(source, config) {
-        Ok(source) => {
-            source
-                .eth_send_raw_transaction(raw_signed_transaction_hex)
-                .await
-        }
-        Err(err) => Err(err).into(),
-    }
+) -> (
+    MultiRpcResult<candid_types::SendRawTransactionStatus>,
+    Option<Result<DecodedTransaction, TransactionError>>,
+) {
+    trace_request("eth_sendRawTransaction", async move {
+        let (result, transaction) = match CandidRpcClient::new(source, config) {
+            Ok(source) => {
+                let (result, transaction) = source
+                    .send_raw_transaction(raw_signed_transaction_hex)
+                    .await;
+                (result, Some(transaction))
+            }
+            Err(err) => (Err(err).into(), None),
+        };
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthSendRawTransaction, &result);
+        (result, transaction)
+    })
+    .await
+}
+
+#[query(name = "decodeRawTransaction")]
+#[candid_method(query, rename = "decodeRawTransaction")]
+fn decode_raw_transaction(
+    raw_signed_transaction_hex: String,
+) -> Result<DecodedTransaction, TransactionError> {
+    evm_rpc::decode_raw_transaction(&raw_signed_transaction_hex)
 }
 
 #[update]
@@ -104,15 +309,34 @@ async fn request(
     json_rpc_payload: String,
     max_response_bytes: u64,
 ) -> Result<String, RpcError> {
-    let response = do_json_rpc_request(
-        ic_cdk::caller(),
-        resolve_rpc_service(service)?,
-        MetricRpcMethod("request".to_string()),
-        &json_rpc_payload,
-        max_response_bytes,
-    )
-    .await?;
-    get_http_response_body(response)
+    trace_request("request", async move {
+        let rpc_method = MetricRpcMethod("request".to_string());
+        let result = match check_caller_quota(&ic_cdk::caller())
+            .and_then(|_| resolve_rpc_service(service).map_err(RpcError::from))
+        {
+            Ok(service) => do_json_rpc_request(
+                ic_cdk::caller(),
+                service,
+                rpc_method.clone(),
+                &json_rpc_payload,
+                max_response_bytes,
+            )
+            .await
+            .and_then(get_http_response_body),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = &result {
+            add_rpc_error_metric(ic_cdk::caller(), rpc_method, err);
+        }
+        result
+    })
+    .await
+}
+
+#[query(name = "getRecentRequests")]
+#[candid_method(query, rename = "getRecentRequests")]
+fn get_recent_requests() -> Vec<RecentRequest> {
+    evm_rpc::get_recent_requests(&ic_cdk::caller())
 }
 
 #[query(name = "requestCost")]
@@ -132,32 +356,59 @@ fn request_cost(
 #[query(name = "getProviders")]
 #[candid_method(query, rename = "getProviders")]
 fn get_providers() -> Vec<ProviderView> {
-    PROVIDERS.with(|p| {
-        p.borrow()
-            .iter()
-            .map(|(_, provider)| provider.into())
-            .collect::<Vec<ProviderView>>()
-    })
+    get_provider_views()
+}
+
+#[query(name = "getCertifiedProviders")]
+#[candid_method(query, rename = "getCertifiedProviders")]
+fn get_certified_providers() -> CertifiedResponse {
+    get_certified_response(CERTIFIED_PROVIDERS_LABEL, encode_provider_views())
 }
 
 #[update(name = "registerProvider", guard = "require_register_provider")]
 #[candid_method(rename = "registerProvider")]
 fn register_provider(provider: RegisterProviderArgs) -> u64 {
-    do_register_provider(ic_cdk::caller(), provider)
+    let caller = ic_cdk::caller();
+    let provider_id = do_register_provider(caller, provider);
+    certify_provider_registry();
+    record_audit_entry(
+        caller,
+        "registerProvider",
+        provider_id,
+        None,
+        describe_provider(provider_id),
+    );
+    provider_id
 }
 
 #[update(name = "unregisterProvider")]
 #[candid_method(rename = "unregisterProvider")]
 fn unregister_provider(provider_id: u64) -> bool {
     let caller = ic_cdk::caller();
-    do_unregister_provider(caller, is_controller(&caller), provider_id)
+    let before = describe_provider(provider_id);
+    let removed = do_unregister_provider(caller, is_controller(&caller), provider_id);
+    certify_provider_registry();
+    if removed {
+        record_audit_entry(caller, "unregisterProvider", provider_id, before, None);
+    }
+    removed
 }
 
 #[update(name = "updateProvider")]
 #[candid_method(rename = "updateProvider")]
 fn update_provider(provider: UpdateProviderArgs) {
     let caller = ic_cdk::caller();
-    do_update_provider(caller, is_controller(&caller), provider)
+    let provider_id = provider.provider_id;
+    let before = describe_provider(provider_id);
+    do_update_provider(caller, is_controller(&caller), provider);
+    certify_provider_registry();
+    record_audit_entry(
+        caller,
+        "updateProvider",
+        provider_id,
+        before,
+        describe_provider(provider_id),
+    );
 }
 
 #[update(name = "manageProvider", guard = "require_manage_or_controller")]
@@ -169,18 +420,35 @@ fn manage_provider(args: ManageProviderArgs) {
         ic_cdk::caller(),
         args.provider_id
     );
-    do_manage_provider(args)
+    let provider_id = args.provider_id;
+    let before = describe_provider(provider_id);
+    do_manage_provider(args);
+    certify_provider_registry();
+    record_audit_entry(
+        ic_cdk::caller(),
+        "manageProvider",
+        provider_id,
+        before,
+        describe_provider(provider_id),
+    );
 }
 
 #[query(name = "getServiceProviderMap", guard = "require_manage_or_controller")]
 #[candid_method(query, rename = "getServiceProviderMap")]
 fn get_service_provider_map() -> Vec<(RpcService, u64)> {
-    SERVICE_PROVIDER_MAP.with(|map| {
-        map.borrow()
-            .iter()
-            .filter_map(|(k, v)| Some((k.try_into().ok()?, v)))
-            .collect()
-    })
+    get_service_provider_mappings()
+}
+
+#[query(
+    name = "getCertifiedServiceProviderMap",
+    guard = "require_rpc_access_or_manage"
+)]
+#[candid_method(query, rename = "getCertifiedServiceProviderMap")]
+fn get_certified_service_provider_map() -> CertifiedResponse {
+    get_certified_response(
+        CERTIFIED_SERVICE_PROVIDER_MAP_LABEL,
+        encode_service_provider_mappings(),
+    )
 }
 
 #[query(name = "getNodesInSubnet")]
@@ -200,7 +468,62 @@ fn get_accumulated_cycle_count(provider_id: u64) -> u128 {
 #[candid_method(rename = "withdrawAccumulatedCycles")]
 async fn withdraw_accumulated_cycles(provider_id: u64, canister_id: Principal) {
     let caller = ic_cdk::caller();
-    do_withdraw_accumulated_cycles(caller, is_controller(&caller), provider_id, canister_id).await
+    let cycles_owed = || {
+        PROVIDERS
+            .with(|p| p.borrow().get(&provider_id))
+            .map(|p| p.cycles_owed)
+    };
+    let before = cycles_owed();
+    do_withdraw_accumulated_cycles(caller, is_controller(&caller), provider_id, canister_id).await;
+    record_audit_entry(
+        caller,
+        "withdrawAccumulatedCycles",
+        format!("{} (recipient: {})", provider_id, canister_id),
+        before.map(|cycles| cycles.to_string()),
+        cycles_owed().map(|cycles| cycles.to_string()),
+    );
+}
+
+#[update(name = "depositCycles")]
+#[candid_method(rename = "depositCycles")]
+fn deposit_cycles(principal: Option<Principal>) -> u128 {
+    let principal = principal.unwrap_or_else(ic_cdk::caller);
+    let amount =
+        ic_cdk::api::call::msg_cycles_accept128(ic_cdk::api::call::msg_cycles_available128());
+    log!(
+        INFO,
+        "[{}] Depositing {} cycles for principal: {}",
+        ic_cdk::caller(),
+        amount,
+        principal
+    );
+    do_deposit(principal, amount)
+}
+
+#[query(name = "getBalance")]
+#[candid_method(query, rename = "getBalance")]
+fn get_balance(principal: Option<Principal>) -> u128 {
+    let caller = ic_cdk::caller();
+    let principal = principal.unwrap_or(caller);
+    if principal != caller {
+        require_manage_or_controller().unwrap_or_else(|err| ic_cdk::trap(&err));
+    }
+    evm_rpc::get_balance(&principal)
+}
+
+#[query(name = "getUsage")]
+#[candid_method(query, rename = "getUsage")]
+fn get_usage(principal: Principal) -> Option<CallerUsage> {
+    if principal != ic_cdk::caller() {
+        require_manage_or_controller().unwrap_or_else(|err| ic_cdk::trap(&err));
+    }
+    get_caller_usage(&principal)
+}
+
+#[update(name = "withdrawBalance")]
+#[candid_method(rename = "withdrawBalance")]
+async fn withdraw_balance(amount: u128, canister_id: Principal) {
+    do_withdraw_balance(ic_cdk::caller(), amount, canister_id).await
 }
 
 #[query(name = "__transform_json_rpc")]
@@ -227,17 +550,63 @@ fn init(args: InitArgs) {
         });
         set_service_provider(&service, &provider);
     }
+    certify_provider_registry();
+}
+
+#[ic_cdk::pre_upgrade]
+fn pre_upgrade() {
+    save_metrics();
+    save_next_request_id();
 }
 
 #[ic_cdk::post_upgrade]
 fn post_upgrade(args: InitArgs) {
     UNSTABLE_SUBNET_SIZE.with(|m| *m.borrow_mut() = args.nodes_in_subnet);
+    restore_metrics();
+    restore_next_request_id();
+    update_certified_metrics();
+    certify_provider_registry();
+
+    ic_cdk_timers::set_timer_interval(AUTH_PRUNE_INTERVAL, || {
+        prune_expired_authorizations(ic_cdk::id(), ic_cdk::api::time());
+    });
+    ic_cdk_timers::set_timer_interval(CHAIN_HEAD_POLL_INTERVAL, || {
+        ic_cdk::spawn(poll_chain_heads())
+    });
+    ic_cdk_timers::set_timer_interval(SUBSCRIPTION_POLL_INTERVAL, || {
+        ic_cdk::spawn(poll_log_subscriptions());
+        ic_cdk::spawn(poll_block_subscriptions());
+    });
+    ic_cdk_timers::set_timer_interval(TRANSACTION_POLL_INTERVAL, || {
+        ic_cdk::spawn(poll_tracked_transactions())
+    });
+    ic_cdk_timers::set_timer_interval(CERTIFIED_METRICS_INTERVAL, certify_metrics);
 }
 
 #[query]
-fn http_request(request: AssetHttpRequest) -> AssetHttpResponse {
+fn http_request(request: AssetHttpRequest) -> HttpGatewayResponse {
+    if is_http_rpc_request(&request) {
+        return HttpGatewayResponse::upgrade();
+    }
+    serve_http_request(request).into()
+}
+
+#[update]
+async fn http_request_update(request: AssetHttpRequest) -> HttpGatewayResponse {
+    let (mut response, request_id) = with_request_id(serve_http_rpc_request(request)).await;
+    response
+        .headers
+        .push(("X-Request-Id".to_string(), request_id.to_string()));
+    response
+}
+
+fn serve_http_request(request: AssetHttpRequest) -> AssetHttpResponse {
     match request.path() {
         "/metrics" => serve_metrics(encode_metrics),
+        "/dashboard" => HttpResponseBuilder::ok()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .with_body_and_content_length(render_dashboard(&DashboardData::collect()))
+            .build(),
         "/logs" => {
             use cketh_common::logs::{Log, Priority, Sort};
             use std::str::FromStr;
@@ -254,6 +623,28 @@ fn http_request(request: AssetHttpRequest) -> AssetHttpResponse {
                 None => 0,
             };
 
+            let request_id = match request.raw_query_param("requestId") {
+                Some(arg) => match u64::from_str(arg) {
+                    Ok(value) => Some(value),
+                    Err(_) => {
+                        return HttpResponseBuilder::bad_request()
+                            .with_body_and_content_length(
+                                "failed to parse the 'requestId' parameter",
+                            )
+                            .build()
+                    }
+                },
+                None => None,
+            };
+            if request_id.is_some() || request.raw_query_param("priority") == Some("TraceRpc") {
+                let mut entries = get_trace_log_entries(request_id);
+                entries.retain(|entry| entry.timestamp >= max_skip_timestamp);
+                return HttpResponseBuilder::ok()
+                    .header("Content-Type", "application/json; charset=utf-8")
+                    .with_body_and_content_length(serialize_trace_log_entries(entries))
+                    .build();
+            }
+
             let mut log: Log = Default::default();
 
             match request.raw_query_param("priority").map(Priority::from_str) {
@@ -314,6 +705,12 @@ fn get_metrics() -> Metrics {
     UNSTABLE_METRICS.with(|metrics| (*metrics.borrow()).clone())
 }
 
+#[query(name = "getCertifiedMetrics")]
+#[candid_method(query, rename = "getCertifiedMetrics")]
+fn get_certified_metrics() -> CertifiedResponse {
+    get_certified_response(CERTIFIED_METRICS_LABEL, get_certified_metrics_snapshot())
+}
+
 #[query(name = "stableSize", guard = "require_manage_or_controller")]
 fn stable_size() -> u64 {
     ic_cdk::api::stable::stable64_size() * WASM_PAGE_SIZE
@@ -328,15 +725,36 @@ fn stable_read(offset: u64, length: u64) -> Vec<u8> {
 
 #[update(guard = "require_manage_or_controller")]
 #[candid_method]
-fn authorize(principal: Principal, auth: Auth) -> bool {
+fn authorize(
+    principal: Principal,
+    auth: Auth,
+    expires_at: Option<u64>,
+    scope: Option<AuthScope>,
+) -> bool {
+    if let Some(expires_at) = expires_at {
+        if expires_at <= ic_cdk::api::time() {
+            ic_cdk::trap("Expiration time must be in the future");
+        }
+    }
     log!(
         INFO,
-        "[{}] Authorizing `{:?}` for principal: {}",
+        "[{}] Authorizing `{:?}` for principal: {} (expires at: {:?}, scope: {:?})",
         ic_cdk::caller(),
         auth,
-        principal
+        principal,
+        expires_at,
+        scope
+    );
+    let before = describe_authorizations(&principal);
+    let changed = do_authorize_with(principal, auth, expires_at, scope);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "authorize",
+        principal,
+        before,
+        describe_authorizations(&principal),
     );
-    do_authorize(principal, auth)
+    changed
 }
 
 #[query(name = "getAuthorized", guard = "require_manage_or_controller")]
@@ -345,7 +763,8 @@ fn get_authorized(auth: Auth) -> Vec<Principal> {
     AUTH.with(|a| {
         let mut result = Vec::new();
         for (k, v) in a.borrow().iter() {
-            if v.is_authorized(auth) {
+            // Skip expired authorizations which have not been pruned yet
+            if v.is_authorized(auth) && is_authorized(&k.0, auth) {
                 result.push(k.0);
             }
         }
@@ -363,7 +782,451 @@ fn deauthorize(principal: Principal, auth: Auth) -> bool {
         auth,
         principal
     );
-    do_deauthorize(principal, auth)
+    let before = describe_authorizations(&principal);
+    let changed = do_deauthorize(principal, auth);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "deauthorize",
+        principal,
+        before,
+        describe_authorizations(&principal),
+    );
+    changed
+}
+
+#[query(name = "getBlockedHosts", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getBlockedHosts")]
+fn get_blocked_hosts() -> Vec<String> {
+    evm_rpc::get_blocked_hosts()
+}
+
+#[update(name = "blockHost", guard = "require_manage_or_controller")]
+#[candid_method(rename = "blockHost")]
+fn block_host(host: String) -> bool {
+    log!(INFO, "[{}] Blocking host: {}", ic_cdk::caller(), host);
+    let changed = do_block_host(&host);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "blockHost",
+        host,
+        Some((!changed).to_string()),
+        Some(true.to_string()),
+    );
+    changed
+}
+
+#[update(name = "unblockHost", guard = "require_manage_or_controller")]
+#[candid_method(rename = "unblockHost")]
+fn unblock_host(host: String) -> bool {
+    log!(INFO, "[{}] Unblocking host: {}", ic_cdk::caller(), host);
+    let changed = do_unblock_host(&host);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "unblockHost",
+        host,
+        Some(changed.to_string()),
+        Some(false.to_string()),
+    );
+    changed
+}
+
+#[query(name = "getAllowedHosts", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getAllowedHosts")]
+fn get_allowed_hosts() -> Vec<String> {
+    get_allowed_host_patterns()
+}
+
+#[update(name = "allowHost", guard = "require_manage_or_controller")]
+#[candid_method(rename = "allowHost")]
+fn allow_host(pattern: String) -> bool {
+    log!(
+        INFO,
+        "[{}] Allowing host pattern: {}",
+        ic_cdk::caller(),
+        pattern
+    );
+    let changed = do_allow_host_pattern(&pattern);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "allowHost",
+        pattern,
+        Some((!changed).to_string()),
+        Some(true.to_string()),
+    );
+    changed
+}
+
+#[update(name = "disallowHost", guard = "require_manage_or_controller")]
+#[candid_method(rename = "disallowHost")]
+fn disallow_host(pattern: String) -> bool {
+    log!(
+        INFO,
+        "[{}] Disallowing host pattern: {}",
+        ic_cdk::caller(),
+        pattern
+    );
+    let changed = do_disallow_host_pattern(&pattern);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "disallowHost",
+        pattern,
+        Some(changed.to_string()),
+        Some(false.to_string()),
+    );
+    changed
+}
+
+#[query(
+    name = "getHostAllowlistEnabled",
+    guard = "require_manage_or_controller"
+)]
+#[candid_method(query, rename = "getHostAllowlistEnabled")]
+fn get_host_allowlist_enabled() -> bool {
+    is_host_allowlist_enabled()
+}
+
+#[update(
+    name = "setHostAllowlistEnabled",
+    guard = "require_manage_or_controller"
+)]
+#[candid_method(rename = "setHostAllowlistEnabled")]
+fn set_host_allowlist_enabled(enabled: bool) {
+    log!(
+        INFO,
+        "[{}] Setting host allowlist enabled to `{}`",
+        ic_cdk::caller(),
+        enabled
+    );
+    let before = is_host_allowlist_enabled();
+    evm_rpc::set_host_allowlist_enabled(enabled);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setHostAllowlistEnabled",
+        "hostAllowlistEnabled",
+        Some(before.to_string()),
+        Some(enabled.to_string()),
+    );
+}
+
+#[query(name = "getHttpRpcServices", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getHttpRpcServices")]
+fn get_http_rpc_services() -> Vec<(u64, RpcServices)> {
+    evm_rpc::get_http_rpc_services()
+}
+
+#[update(name = "setHttpRpcServices", guard = "require_manage_or_controller")]
+#[candid_method(rename = "setHttpRpcServices")]
+fn set_http_rpc_services(chain_id: u64, services: Option<RpcServices>) {
+    log!(
+        INFO,
+        "[{}] Setting HTTP JSON-RPC services for chain ID {}",
+        ic_cdk::caller(),
+        chain_id
+    );
+    let describe = || {
+        HTTP_RPC_SERVICES
+            .with(|s| s.borrow().get(&chain_id))
+            .map(|services| describe_rpc_services(&services.0))
+    };
+    let before = describe();
+    do_set_http_rpc_services(chain_id, services);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setHttpRpcServices",
+        chain_id,
+        before,
+        describe(),
+    );
+}
+
+#[query(name = "getHttpRpcAccount")]
+#[candid_method(query, rename = "getHttpRpcAccount")]
+fn get_http_rpc_account() -> HttpRpcAccount {
+    evm_rpc::get_http_rpc_account()
+}
+
+#[update(
+    name = "setHttpRpcSpendingLimit",
+    guard = "require_manage_or_controller"
+)]
+#[candid_method(rename = "setHttpRpcSpendingLimit")]
+fn set_http_rpc_spending_limit(max_cycles_per_request: Option<u128>) {
+    log!(
+        INFO,
+        "[{}] Setting HTTP JSON-RPC spending limit to {:?} cycles per request",
+        ic_cdk::caller(),
+        max_cycles_per_request
+    );
+    let before = get_http_rpc_max_cycles_per_request();
+    set_http_rpc_max_cycles_per_request(max_cycles_per_request);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setHttpRpcSpendingLimit",
+        "httpRpcMaxCyclesPerRequest",
+        before.map(|cycles| cycles.to_string()),
+        max_cycles_per_request.map(|cycles| cycles.to_string()),
+    );
+}
+
+#[query(name = "getResponseCacheStats", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getResponseCacheStats")]
+fn get_response_cache_stats() -> ResponseCacheStats {
+    evm_rpc::get_response_cache_stats()
+}
+
+#[update(name = "setResponseCacheSize", guard = "require_manage_or_controller")]
+#[candid_method(rename = "setResponseCacheSize")]
+fn set_response_cache_size(max_entries: u64) {
+    log!(
+        INFO,
+        "[{}] Setting response cache size to {} entries",
+        ic_cdk::caller(),
+        max_entries
+    );
+    let before = get_response_cache_max_entries();
+    set_response_cache_max_entries(max_entries);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setResponseCacheSize",
+        "responseCacheMaxEntries",
+        Some(before.to_string()),
+        Some(max_entries.to_string()),
+    );
+}
+
+#[update(name = "clearResponseCache", guard = "require_manage_or_controller")]
+#[candid_method(rename = "clearResponseCache")]
+fn clear_response_cache(chain_id: Option<u64>) -> u64 {
+    log!(
+        INFO,
+        "[{}] Clearing response cache (chain ID: {:?})",
+        ic_cdk::caller(),
+        chain_id
+    );
+    let removed = evm_rpc::clear_response_cache(chain_id);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "clearResponseCache",
+        chain_id.map_or_else(|| "all".to_string(), |id| id.to_string()),
+        None,
+        Some(format!("{} entries removed", removed)),
+    );
+    removed
+}
+
+#[query(name = "getChainHead")]
+#[candid_method(query, rename = "getChainHead")]
+fn get_chain_head(chain_id: u64) -> Option<ChainHead> {
+    evm_rpc::get_chain_head(chain_id)
+}
+
+#[query(name = "getChainHeadServices", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getChainHeadServices")]
+fn get_chain_head_services() -> Vec<(u64, RpcServices)> {
+    evm_rpc::get_chain_head_services()
+}
+
+#[update(name = "setChainHeadServices", guard = "require_manage_or_controller")]
+#[candid_method(rename = "setChainHeadServices")]
+fn set_chain_head_services(chain_id: u64, services: Option<RpcServices>) {
+    log!(
+        INFO,
+        "[{}] Setting chain head tracker services for chain ID {}",
+        ic_cdk::caller(),
+        chain_id
+    );
+    let describe = || {
+        CHAIN_HEAD_SERVICES
+            .with(|s| s.borrow().get(&chain_id))
+            .map(|services| describe_rpc_services(&services.0))
+    };
+    let before = describe();
+    do_set_chain_head_services(chain_id, services);
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setChainHeadServices",
+        chain_id,
+        before,
+        describe(),
+    );
+}
+
+#[query(name = "getEcdsaKeyName", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getEcdsaKeyName")]
+fn get_ecdsa_key_name() -> Option<String> {
+    evm_rpc::get_ecdsa_key_name()
+}
+
+#[update(name = "setEcdsaKeyName", guard = "require_controller")]
+#[candid_method(rename = "setEcdsaKeyName")]
+fn set_ecdsa_key_name(key_name: Option<String>) {
+    log!(
+        INFO,
+        "[{}] Setting ECDSA key name to {:?}",
+        ic_cdk::caller(),
+        key_name
+    );
+    let before = evm_rpc::get_ecdsa_key_name();
+    evm_rpc::set_ecdsa_key_name(key_name.clone());
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setEcdsaKeyName",
+        "ecdsaKeyName",
+        before,
+        key_name,
+    );
+}
+
+#[update(name = "getEthAddress")]
+#[candid_method(rename = "getEthAddress")]
+async fn get_eth_address(principal: Principal) -> String {
+    do_get_eth_address(principal).await
+}
+
+#[query(name = "getAddressNonces")]
+#[candid_method(query, rename = "getAddressNonces")]
+fn get_address_nonces(chain_id: u64, address: String) -> Option<AddressNonces> {
+    do_get_address_nonces(chain_id, &address)
+}
+
+#[update(name = "sendTransaction")]
+#[candid_method(rename = "sendTransaction")]
+async fn send_transaction(
+    args: SendTransactionArgs,
+    config: Option<RpcConfig>,
+) -> (
+    MultiRpcResult<candid_types::SendRawTransactionStatus>,
+    Option<DecodedTransaction>,
+) {
+    let caller = ic_cdk::caller();
+    if !is_rpc_allowed(&caller) {
+        ic_cdk::trap("You are not authorized");
+    }
+    trace_request("sendTransaction", async move {
+        let (result, transaction) = do_send_transaction(caller, args, config).await;
+        add_rpc_error_metrics(caller, RpcMethod::EthSendRawTransaction, &result);
+        (result, transaction)
+    })
+    .await
+}
+
+#[update(name = "subscribeLogs")]
+#[candid_method(rename = "subscribeLogs")]
+fn subscribe_logs(args: SubscribeLogsArgs) -> u64 {
+    let caller = ic_cdk::caller();
+    let id = do_subscribe_logs(caller, args);
+    log!(INFO, "[{}] Created log subscription {}", caller, id);
+    id
+}
+
+#[update(name = "unsubscribeLogs")]
+#[candid_method(rename = "unsubscribeLogs")]
+fn unsubscribe_logs(subscription_id: u64) -> bool {
+    let caller = ic_cdk::caller();
+    do_unsubscribe_logs(
+        caller,
+        subscription_id,
+        require_manage_or_controller().is_ok(),
+    )
+}
+
+#[update(name = "acknowledgeLogs")]
+#[candid_method(rename = "acknowledgeLogs")]
+fn acknowledge_logs(subscription_id: u64, cursor: u64) {
+    do_acknowledge_logs(ic_cdk::caller(), subscription_id, cursor)
+}
+
+#[query(name = "getLogSubscriptions")]
+#[candid_method(query, rename = "getLogSubscriptions")]
+fn get_log_subscriptions() -> Vec<LogSubscription> {
+    evm_rpc::get_log_subscriptions(ic_cdk::caller())
+}
+
+#[update(name = "subscribeBlocks")]
+#[candid_method(rename = "subscribeBlocks")]
+fn subscribe_blocks(args: SubscribeBlocksArgs) -> u64 {
+    let caller = ic_cdk::caller();
+    let id = do_subscribe_blocks(caller, args);
+    log!(INFO, "[{}] Created block subscription {}", caller, id);
+    id
+}
+
+#[update(name = "unsubscribeBlocks")]
+#[candid_method(rename = "unsubscribeBlocks")]
+fn unsubscribe_blocks(subscription_id: u64) -> bool {
+    let caller = ic_cdk::caller();
+    do_unsubscribe_blocks(
+        caller,
+        subscription_id,
+        require_manage_or_controller().is_ok(),
+    )
+}
+
+#[query(name = "getBlockSubscriptions")]
+#[candid_method(query, rename = "getBlockSubscriptions")]
+fn get_block_subscriptions() -> Vec<BlockSubscription> {
+    evm_rpc::get_block_subscriptions(ic_cdk::caller())
+}
+
+#[update(name = "createLogCursor")]
+#[candid_method(rename = "createLogCursor")]
+fn create_log_cursor(source: RpcServices, args: candid_types::GetLogsArgs) -> u64 {
+    let caller = ic_cdk::caller();
+    if !is_rpc_allowed(&caller) {
+        ic_cdk::trap("You are not authorized");
+    }
+    do_create_log_cursor(caller, source, args)
+}
+
+#[update(name = "pollLogCursor")]
+#[candid_method(rename = "pollLogCursor")]
+pub async fn poll_log_cursor(
+    cursor_id: u64,
+    config: Option<RpcConfig>,
+) -> MultiRpcResult<Vec<LogEntry>> {
+    trace_request("pollLogCursor", async move {
+        let result = do_poll_log_cursor(ic_cdk::caller(), cursor_id, config).await;
+        add_rpc_error_metrics(ic_cdk::caller(), RpcMethod::EthGetLogs, &result);
+        result
+    })
+    .await
+}
+
+#[update(name = "deleteLogCursor")]
+#[candid_method(rename = "deleteLogCursor")]
+fn delete_log_cursor(cursor_id: u64) -> bool {
+    do_delete_log_cursor(ic_cdk::caller(), cursor_id)
+}
+
+#[query(name = "getLogCursors")]
+#[candid_method(query, rename = "getLogCursors")]
+fn get_log_cursors() -> Vec<LogCursor> {
+    evm_rpc::get_log_cursors(ic_cdk::caller())
+}
+
+#[update(name = "trackTransaction")]
+#[candid_method(rename = "trackTransaction")]
+fn track_transaction(args: TrackTransactionArgs) -> String {
+    let caller = ic_cdk::caller();
+    if !is_rpc_allowed(&caller) {
+        ic_cdk::trap("You are not authorized");
+    }
+    let hash = do_track_transaction(caller, args, ic_cdk::api::time());
+    log!(INFO, "[{}] Tracking transaction {}", caller, hash);
+    hash
+}
+
+#[update(name = "untrackTransaction")]
+#[candid_method(rename = "untrackTransaction")]
+fn untrack_transaction(hash: String) -> bool {
+    let caller = ic_cdk::caller();
+    do_untrack_transaction(caller, &hash, require_manage_or_controller().is_ok())
+}
+
+#[query(name = "getTransactionStatus")]
+#[candid_method(query, rename = "getTransactionStatus")]
+fn get_transaction_status(hash: String) -> Option<TransactionStatus> {
+    evm_rpc::get_transaction_status(&hash)
 }
 
 #[query(name = "getOpenRpcAccess", guard = "require_manage_or_controller")]
@@ -381,11 +1244,77 @@ fn set_open_rpc_access(open_rpc_access: bool) {
         ic_cdk::caller(),
         open_rpc_access
     );
-    METADATA.with(|m| {
+    let before = METADATA.with(|m| {
         let mut metadata = m.borrow().get().clone();
+        let before = metadata.open_rpc_access;
         metadata.open_rpc_access = open_rpc_access;
         m.borrow_mut().set(metadata).unwrap();
+        before
     });
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setOpenRpcAccess",
+        "openRpcAccess",
+        Some(before.to_string()),
+        Some(open_rpc_access.to_string()),
+    );
+}
+
+#[query(name = "getQuotas", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getQuotas")]
+fn get_quotas() -> Vec<(QuotaTarget, Quota)> {
+    QUOTAS.with(|q| {
+        q.borrow()
+            .iter()
+            .filter_map(|(k, v)| Some((k.try_into().ok()?, v)))
+            .collect()
+    })
+}
+
+#[query(name = "getQuotaStatus")]
+#[candid_method(query, rename = "getQuotaStatus")]
+fn get_quota_status(principal: Option<Principal>) -> Option<QuotaStatus> {
+    let caller = ic_cdk::caller();
+    let principal = principal.unwrap_or(caller);
+    if principal != caller {
+        require_manage_or_controller().unwrap_or_else(|err| ic_cdk::trap(&err));
+    }
+    evm_rpc::get_quota_status(&principal, ic_cdk::api::time())
+}
+
+#[update(name = "setQuota", guard = "require_manage_or_controller")]
+#[candid_method(rename = "setQuota")]
+fn set_quota(target: QuotaTarget, quota: Option<Quota>) {
+    log!(
+        INFO,
+        "[{}] Setting quota for {:?} to `{:?}`",
+        ic_cdk::caller(),
+        target,
+        quota
+    );
+    let before = get_quota(&target);
+    do_set_quota(target.clone(), quota.clone());
+    record_audit_entry(
+        ic_cdk::caller(),
+        "setQuota",
+        format!("{:?}", target),
+        before.map(|quota| format!("{:?}", quota)),
+        quota.map(|quota| format!("{:?}", quota)),
+    );
+}
+
+#[update(name = "resetMetrics", guard = "require_controller")]
+#[candid_method(rename = "resetMetrics")]
+fn reset_metrics() {
+    log!(INFO, "[{}] Resetting metrics", ic_cdk::caller());
+    evm_rpc::reset_metrics();
+    record_audit_entry(ic_cdk::caller(), "resetMetrics", "metrics", None, None);
+}
+
+#[query(name = "getAuditLog", guard = "require_manage_or_controller")]
+#[candid_method(query, rename = "getAuditLog")]
+fn get_audit_log(offset: u64, limit: u64) -> Vec<AuditLogEntry> {
+    get_audit_entries(offset, limit)
 }
 
 #[cfg(not(any(target_arch = "wasm32", test)))]
@@ -1,12 +1,16 @@
+use candid::Principal;
+use cketh_common::numeric::BlockNumber;
+use ethers_core::types::Address;
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
 #[cfg(target_arch = "wasm32")]
 use ic_stable_structures::DefaultMemoryImpl;
 #[cfg(not(target_arch = "wasm32"))]
 use ic_stable_structures::VectorMemory;
-use ic_stable_structures::{Cell, StableBTreeMap};
-use std::cell::RefCell;
+use ic_stable_structures::{Cell, StableBTreeMap, StableLog};
+use std::cell::{Cell as StdCell, RefCell};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 
-use crate::{types::*, NODES_IN_FIDUCIARY_SUBNET};
+use crate::{types::*, CallerUsageMap, CertifiedTree, PollTask, NODES_IN_FIDUCIARY_SUBNET};
 
 #[cfg(not(target_arch = "wasm32"))]
 type Memory = VirtualMemory<VectorMemory>;
@@ -17,6 +21,19 @@ thread_local! {
     // Unstable static data: this is reset when the canister is upgraded.
     pub static UNSTABLE_METRICS: RefCell<Metrics> = RefCell::new(Metrics::default());
     pub static UNSTABLE_SUBNET_SIZE: RefCell<u32> = RefCell::new(NODES_IN_FIDUCIARY_SUBNET);
+    pub static UNSTABLE_CALLER_USAGE: RefCell<CallerUsageMap> = RefCell::new(CallerUsageMap::default());
+    pub static UNSTABLE_QUOTA_USAGE: RefCell<HashMap<Principal, QuotaUsage>> = RefCell::new(HashMap::new());
+    pub static UNSTABLE_NEXT_REQUEST_ID: StdCell<u64> = StdCell::new(0);
+    pub static UNSTABLE_CURRENT_REQUEST_ID: StdCell<Option<u64>> = StdCell::new(None);
+    pub static UNSTABLE_RECENT_REQUESTS: RefCell<HashMap<Principal, VecDeque<RecentRequest>>> = RefCell::new(HashMap::new());
+    pub static UNSTABLE_RPC_CALLER: StdCell<Option<Principal>> = StdCell::new(None);
+    pub static UNSTABLE_RPC_CHAIN_ID: StdCell<Option<u64>> = StdCell::new(None);
+    pub static UNSTABLE_HTTP_RPC_SPENDING: RefCell<HashMap<u64, u128>> = RefCell::new(HashMap::new());
+    pub static UNSTABLE_CERTIFIED_TREE: RefCell<CertifiedTree> = RefCell::new(CertifiedTree::new());
+    pub static UNSTABLE_CERTIFIED_METRICS: RefCell<Vec<u8>> = RefCell::new(vec![]);
+    pub static UNSTABLE_POLL_TASKS: RefCell<BTreeSet<PollTask>> = RefCell::new(BTreeSet::new());
+    pub static UNSTABLE_FINALIZED_BLOCKS: RefCell<HashMap<u64, BlockNumber>> = RefCell::new(HashMap::new());
+    pub static UNSTABLE_ETH_ADDRESSES: RefCell<HashMap<Principal, Address>> = RefCell::new(HashMap::new());
 
     // Stable static data: this is preserved when the canister is upgraded.
     #[cfg(not(target_arch = "wasm32"))]
@@ -34,4 +51,44 @@ thread_local! {
         StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2)))));
     pub static SERVICE_PROVIDER_MAP: RefCell<StableBTreeMap<StorableRpcService, u64, Memory>> = RefCell::new(
         StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(3)))));
+    pub static BALANCES: RefCell<StableBTreeMap<PrincipalStorable, CyclesStorable, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4)))));
+    pub static QUOTAS: RefCell<StableBTreeMap<StorableQuotaTarget, Quota, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5)))));
+    pub static AUTH_GRANTS: RefCell<StableBTreeMap<PrincipalStorable, AuthGrants, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6)))));
+    pub static HOST_BLOCKLIST: RefCell<StableBTreeMap<StringStorable, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7)))));
+    pub static HOST_ALLOWLIST: RefCell<StableBTreeMap<StringStorable, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8)))));
+    pub static AUDIT_LOG: RefCell<StableLog<AuditLogEntry, Memory, Memory>> = RefCell::new(
+        StableLog::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(9))),
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(10))),
+        ).expect("Unable to initialize audit log"));
+    pub static STABLE_METRICS: RefCell<Cell<StorableMetrics, Memory>> = RefCell::new(Cell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(11))),
+            StorableMetrics::default()).unwrap());
+    pub static HTTP_RPC_SERVICES: RefCell<StableBTreeMap<u64, StorableRpcServices, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(12)))));
+    pub static RESPONSE_CACHE: RefCell<StableBTreeMap<ResponseCacheKey, ResponseCacheEntry, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(13)))));
+    pub static RESPONSE_CACHE_ORDER: RefCell<StableBTreeMap<u64, ResponseCacheKey, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(14)))));
+    pub static CHAIN_HEAD_SERVICES: RefCell<StableBTreeMap<u64, StorableRpcServices, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(15)))));
+    pub static CHAIN_HEADS: RefCell<StableBTreeMap<u64, ChainHead, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(16)))));
+    pub static LOG_SUBSCRIPTIONS: RefCell<StableBTreeMap<u64, LogSubscription, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(17)))));
+    pub static BLOCK_SUBSCRIPTIONS: RefCell<StableBTreeMap<u64, BlockSubscription, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(18)))));
+    pub static LOG_CURSORS: RefCell<StableBTreeMap<u64, LogCursor, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(19)))));
+    pub static LOG_CURSOR_ENTRIES: RefCell<StableBTreeMap<LogCursorEntryKey, StorableLogEntry, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(20)))));
+    pub static TRACKED_TRANSACTIONS: RefCell<StableBTreeMap<StringStorable, TrackedTransaction, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(21)))));
+    pub static NONCES: RefCell<StableBTreeMap<NonceKey, AddressNonces, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(22)))));
 }
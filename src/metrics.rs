@@ -25,6 +25,19 @@ macro_rules! add_metric_entry {
     }};
 }
 
+#[macro_export]
+macro_rules! observe_metric_entry {
+    ($metric:ident, $key:expr, $value:expr, $bounds:expr) => {{
+        $crate::UNSTABLE_METRICS.with(|m| {
+            m.borrow_mut()
+                .$metric
+                .entry($key)
+                .or_insert_with(|| $crate::MetricHistogram::new($bounds))
+                .observe($value);
+        });
+    }};
+}
+
 trait EncoderExtensions {
     fn counter_entries<K: MetricLabels, V: MetricValue>(
         &mut self,
@@ -32,6 +45,13 @@ trait EncoderExtensions {
         map: &HashMap<K, V>,
         help: &str,
     );
+
+    fn histogram_entries<K: MetricLabels>(
+        &mut self,
+        name: &str,
+        map: &HashMap<K, MetricHistogram>,
+        help: &str,
+    ) -> std::io::Result<()>;
 }
 
 impl EncoderExtensions for ic_metrics_encoder::MetricsEncoder<Vec<u8>> {
@@ -50,6 +70,58 @@ impl EncoderExtensions for ic_metrics_encoder::MetricsEncoder<Vec<u8>> {
                 .unwrap_or(());
         })
     }
+
+    fn histogram_entries<K: MetricLabels>(
+        &mut self,
+        name: &str,
+        map: &HashMap<K, MetricHistogram>,
+        help: &str,
+    ) -> std::io::Result<()> {
+        if map.is_empty() {
+            return Ok(());
+        }
+        let mut builder = self.histogram_vec(name, help)?;
+        for (k, histogram) in map {
+            builder = builder.histogram(
+                &k.metric_labels(),
+                histogram.buckets.iter().map(|(bound, count)| {
+                    let bound = if *bound == u64::MAX {
+                        f64::INFINITY
+                    } else {
+                        bound.metric_value()
+                    };
+                    (bound, count.metric_value())
+                }),
+                histogram.sum.metric_value(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Saves the metrics to stable memory (e.g. before an upgrade).
+pub fn save_metrics() {
+    let metrics = UNSTABLE_METRICS.with(|m| m.borrow().clone());
+    STABLE_METRICS.with(|m| {
+        m.borrow_mut()
+            .set(StorableMetrics(Some(metrics)))
+            .expect("Unable to save metrics")
+    });
+}
+
+/// Restores the metrics saved in stable memory (e.g. after an upgrade).
+pub fn restore_metrics() {
+    let metrics = STABLE_METRICS.with(|m| m.borrow().get().0.clone());
+    UNSTABLE_METRICS.with(|m| *m.borrow_mut() = metrics.unwrap_or_default());
+}
+
+pub fn reset_metrics() {
+    UNSTABLE_METRICS.with(|m| *m.borrow_mut() = Metrics::default());
+    STABLE_METRICS.with(|m| {
+        m.borrow_mut()
+            .set(StorableMetrics(None))
+            .expect("Unable to reset metrics")
+    });
 }
 
 pub fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
@@ -111,7 +183,88 @@ pub fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> st
             m.err_no_permission.metric_value(),
             "Number of NoPermission errors",
         )?;
+        w.encode_counter(
+            "evmrpc_err_rate_limited",
+            m.err_rate_limited.metric_value(),
+            "Number of requests rejected by rate limits or quotas",
+        )?;
+        w.counter_entries(
+            "evmrpc_err_json_rpc",
+            &m.err_json_rpc,
+            "Number of JSON-RPC error codes in provider responses",
+        );
+        w.counter_entries(
+            "evmrpc_err_rpc",
+            &m.err_rpc,
+            "Number of errors returned to callers",
+        );
+        w.counter_entries(
+            "evmrpc_cache_hits",
+            &m.cache_hits,
+            "Number of RPC calls served from the response cache",
+        );
+        w.counter_entries(
+            "evmrpc_cache_misses",
+            &m.cache_misses,
+            "Number of cacheable RPC calls not found in the response cache",
+        );
+        w.encode_gauge(
+            "evmrpc_cache_entries",
+            crate::RESPONSE_CACHE
+                .with(|c| c.borrow().len())
+                .metric_value(),
+            "Number of responses in the response cache",
+        )?;
+        UNSTABLE_CALLER_USAGE.with(|u| {
+            let usage = u.borrow();
+            let usage_entries = |f: fn(&CallerUsage) -> u128| {
+                usage
+                    .iter()
+                    .map(|(caller, usage)| (MetricCaller(caller.to_text()), f(usage)))
+                    .collect::<HashMap<_, _>>()
+            };
+            w.counter_entries(
+                "evmrpc_caller_requests",
+                &usage_entries(|usage| usage.requests as u128),
+                "Number of JSON-RPC requests per caller",
+            );
+            w.counter_entries(
+                "evmrpc_caller_cycles_charged",
+                &usage_entries(|usage| usage.cycles_charged),
+                "Number of cycles charged per caller",
+            );
+            w.counter_entries(
+                "evmrpc_caller_errors",
+                &usage_entries(|usage| usage.errors as u128),
+                "Number of errors returned per caller",
+            );
+        });
+        w.histogram_entries(
+            "evmrpc_response_latency_ms",
+            &m.response_latencies,
+            "Duration of HTTP outcalls in milliseconds",
+        )?;
+        w.histogram_entries(
+            "evmrpc_response_size_bytes",
+            &m.response_sizes,
+            "Size of HTTP outcall response bodies in bytes",
+        )?;
 
         Ok(())
     })
 }
+
+#[test]
+fn test_save_and_restore_metrics() {
+    add_metric!(cycles_withdrawn, 100);
+    save_metrics();
+    reset_metrics();
+    restore_metrics();
+    assert_eq!(UNSTABLE_METRICS.with(|m| m.borrow().cycles_withdrawn), 0);
+
+    add_metric!(cycles_withdrawn, 100);
+    save_metrics();
+    UNSTABLE_METRICS.with(|m| *m.borrow_mut() = Metrics::default());
+    restore_metrics();
+    assert_eq!(UNSTABLE_METRICS.with(|m| m.borrow().cycles_withdrawn), 100);
+}
@@ -0,0 +1,194 @@
+use std::str::FromStr;
+
+use cketh_common::logs::INFO;
+use ethers_core::types::Address;
+use ic_canister_log::log;
+
+use crate::*;
+
+pub fn get_address_nonces(chain_id: u64, address: &[u8; 20]) -> Option<AddressNonces> {
+    NONCES.with(|n| n.borrow().get(&NonceKey::new(chain_id, address)))
+}
+
+pub fn do_get_address_nonces(chain_id: u64, address: &str) -> Option<AddressNonces> {
+    match Address::from_str(address) {
+        Ok(address) => get_address_nonces(chain_id, &address.0),
+        Err(_) => ic_cdk::trap("Invalid address"),
+    }
+}
+
+fn update_address_nonces<R>(
+    chain_id: u64,
+    address: &[u8; 20],
+    f: impl FnOnce(&mut AddressNonces) -> R,
+) -> R {
+    let key = NonceKey::new(chain_id, address);
+    NONCES.with(|n| {
+        let mut map = n.borrow_mut();
+        let mut nonces = map.get(&key).unwrap_or_default();
+        let result = f(&mut nonces);
+        map.insert(key, nonces);
+        result
+    })
+}
+
+/// Reconciles the nonces of an address with its transaction count including pending transactions.
+/// Nonces which are neither in flight nor known to the providers are gaps, which are returned if
+/// they were not known yet.
+pub fn reconcile_nonces(nonces: &mut AddressNonces, pending_count: u64, now: u64) -> Vec<u64> {
+    let timeout = NONCE_RESERVATION_TIMEOUT.as_nanos() as u64;
+    nonces.gaps.retain(|nonce| *nonce >= pending_count);
+    nonces.in_flight.retain(|reservation| {
+        reservation.nonce >= pending_count && now.saturating_sub(reservation.updated_at) <= timeout
+    });
+    if pending_count >= nonces.next_nonce {
+        nonces.next_nonce = pending_count;
+        return vec![];
+    }
+    let detected: Vec<u64> = (pending_count..nonces.next_nonce)
+        .filter(|nonce| {
+            !nonces.gaps.contains(nonce)
+                && !nonces
+                    .in_flight
+                    .iter()
+                    .any(|reservation| reservation.nonce == *nonce)
+        })
+        .collect();
+    nonces.gaps.extend(&detected);
+    nonces.gaps.sort_unstable();
+    detected
+}
+
+/// Reserves the lowest nonce of an address which is not in flight, filling gaps first.
+pub fn reserve_nonce(
+    chain_id: u64,
+    address: &[u8; 20],
+    pending_count: u64,
+    now: u64,
+) -> Result<u64, String> {
+    update_address_nonces(chain_id, address, |nonces| {
+        let detected = reconcile_nonces(nonces, pending_count, now);
+        if !detected.is_empty() {
+            log!(
+                INFO,
+                "Detected nonce gaps {:?} of 0x{} on chain {}",
+                detected,
+                hex::encode(address),
+                chain_id
+            );
+        }
+        let nonce = if nonces.gaps.is_empty() {
+            if nonces.next_nonce - pending_count >= MAX_PENDING_NONCES_PER_ADDRESS {
+                return Err(format!(
+                    "Too many pending transactions (limited to {})",
+                    MAX_PENDING_NONCES_PER_ADDRESS
+                ));
+            }
+            nonces.next_nonce += 1;
+            nonces.next_nonce - 1
+        } else {
+            nonces.gaps.remove(0)
+        };
+        nonces.in_flight.push(NonceReservation {
+            nonce,
+            sent: false,
+            updated_at: now,
+        });
+        Ok(nonce)
+    })
+}
+
+/// Records that a transaction using a reserved nonce was sent. The nonce stays in flight until
+/// the providers include it in the transaction count, or becomes a gap after a timeout.
+pub fn mark_nonce_sent(chain_id: u64, address: &[u8; 20], nonce: u64, now: u64) {
+    update_address_nonces(chain_id, address, |nonces| {
+        for reservation in nonces.in_flight.iter_mut() {
+            if reservation.nonce == nonce {
+                reservation.sent = true;
+                reservation.updated_at = now;
+            }
+        }
+    })
+}
+
+/// Releases a reserved nonce after a failed send, leaving a gap which is filled by the next
+/// reservation.
+pub fn release_nonce(chain_id: u64, address: &[u8; 20], nonce: u64) {
+    update_address_nonces(chain_id, address, |nonces| {
+        nonces
+            .in_flight
+            .retain(|reservation| reservation.nonce != nonce);
+        if nonce < nonces.next_nonce && !nonces.gaps.contains(&nonce) {
+            nonces.gaps.push(nonce);
+            nonces.gaps.sort_unstable();
+        }
+    })
+}
+
+#[test]
+fn test_reserve_nonces() {
+    let address = [1; 20];
+    assert_eq!(reserve_nonce(1, &address, 5, 0), Ok(5));
+    assert_eq!(reserve_nonce(1, &address, 5, 0), Ok(6));
+    assert_eq!(reserve_nonce(11155111, &address, 0, 0), Ok(0));
+
+    // Failed sends leave gaps, which are filled first
+    release_nonce(1, &address, 5);
+    assert_eq!(get_address_nonces(1, &address).unwrap().gaps, vec![5]);
+    assert_eq!(reserve_nonce(1, &address, 5, 0), Ok(5));
+    assert_eq!(reserve_nonce(1, &address, 5, 0), Ok(7));
+
+    // Transactions known to the providers are no longer tracked
+    mark_nonce_sent(1, &address, 5, 0);
+    assert_eq!(reserve_nonce(1, &address, 8, 0), Ok(8));
+    assert_eq!(
+        get_address_nonces(1, &address).unwrap(),
+        AddressNonces {
+            next_nonce: 9,
+            gaps: vec![],
+            in_flight: vec![NonceReservation {
+                nonce: 8,
+                sent: false,
+                updated_at: 0
+            }],
+        }
+    );
+    assert_eq!(reserve_nonce(1, &address, 20, 0), Ok(20));
+
+    for nonce in 21..20 + MAX_PENDING_NONCES_PER_ADDRESS {
+        assert_eq!(reserve_nonce(1, &address, 20, 0), Ok(nonce));
+    }
+    assert!(reserve_nonce(1, &address, 20, 0).is_err());
+}
+
+#[test]
+fn test_reconcile_nonces() {
+    let timeout = NONCE_RESERVATION_TIMEOUT.as_nanos() as u64;
+    let reservation = |nonce, updated_at| NonceReservation {
+        nonce,
+        sent: true,
+        updated_at,
+    };
+    let mut nonces = AddressNonces {
+        next_nonce: 5,
+        gaps: vec![4],
+        in_flight: vec![reservation(2, 0), reservation(3, timeout)],
+    };
+
+    // Dropped transactions are detected once their reservation expires
+    assert_eq!(reconcile_nonces(&mut nonces, 1, timeout), vec![1]);
+    assert_eq!(nonces.gaps, vec![1, 4]);
+    assert_eq!(reconcile_nonces(&mut nonces, 1, timeout + 1), vec![2]);
+    assert_eq!(nonces.gaps, vec![1, 2, 4]);
+    assert_eq!(nonces.in_flight, vec![reservation(3, timeout)]);
+
+    assert_eq!(reconcile_nonces(&mut nonces, 7, timeout + 1), vec![]);
+    assert_eq!(
+        nonces,
+        AddressNonces {
+            next_nonce: 7,
+            gaps: vec![],
+            in_flight: vec![],
+        }
+    );
+}
@@ -0,0 +1,47 @@
+use crate::*;
+
+/// Timer tasks which must not overlap with themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PollTask {
+    ChainHeads,
+    LogSubscriptions,
+    BlockSubscriptions,
+    TrackedTransactions,
+    LogCursor(u64),
+}
+
+/// Marks a task as running until dropped, so that a slow poll does not overlap with the next one.
+pub struct PollGuard(PollTask);
+
+impl PollGuard {
+    /// Returns `None` if the task is already running.
+    pub fn new(task: PollTask) -> Option<Self> {
+        UNSTABLE_POLL_TASKS
+            .with(|p| p.borrow_mut().insert(task))
+            .then_some(Self(task))
+    }
+}
+
+impl Drop for PollGuard {
+    fn drop(&mut self) {
+        UNSTABLE_POLL_TASKS.with(|p| p.borrow_mut().remove(&self.0));
+    }
+}
+
+#[test]
+fn test_poll_guard() {
+    let guard = PollGuard::new(PollTask::LogSubscriptions);
+    assert!(guard.is_some());
+    assert!(PollGuard::new(PollTask::LogSubscriptions).is_none());
+    // Other tasks are independent
+    let other = PollGuard::new(PollTask::BlockSubscriptions);
+    assert!(other.is_some());
+    let cursor = PollGuard::new(PollTask::LogCursor(1));
+    assert!(cursor.is_some());
+    assert!(PollGuard::new(PollTask::LogCursor(1)).is_none());
+    assert!(PollGuard::new(PollTask::LogCursor(2)).is_some());
+    drop(guard);
+    assert!(PollGuard::new(PollTask::LogSubscriptions).is_some());
+    drop(cursor);
+    assert!(PollGuard::new(PollTask::LogCursor(1)).is_some());
+}
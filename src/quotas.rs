@@ -0,0 +1,290 @@
+use candid::Principal;
+use cketh_common::eth_rpc::{JsonRpcError, RpcError};
+
+use crate::*;
+
+const ROLES: [Auth; 4] = [
+    Auth::Manage,
+    Auth::RegisterProvider,
+    Auth::PriorityRpc,
+    Auth::FreeRpc,
+];
+
+pub fn get_quota(target: &QuotaTarget) -> Option<Quota> {
+    QUOTAS.with(|q| q.borrow().get(&StorableQuotaTarget::new(target)))
+}
+
+pub fn do_set_quota(target: QuotaTarget, quota: Option<Quota>) {
+    if target == QuotaTarget::Principal(Principal::anonymous()) {
+        ic_cdk::trap("Cannot set a quota for the anonymous principal");
+    }
+    QUOTAS.with(|q| {
+        let mut quotas = q.borrow_mut();
+        let key = StorableQuotaTarget::new(&target);
+        match quota {
+            Some(quota) => quotas.insert(key, quota),
+            None => quotas.remove(&key),
+        }
+    });
+}
+
+/// Returns the quota which applies to a principal. A principal-specific quota takes
+/// precedence; otherwise, the most restrictive limits of the principal's roles apply.
+pub fn get_applicable_quota(principal: &Principal) -> Option<Quota> {
+    // Background tasks are not limited by the quotas of the roles
+    if is_canister_self(principal) {
+        return None;
+    }
+    if let Some(quota) = get_quota(&QuotaTarget::Principal(*principal)) {
+        return Some(quota);
+    }
+    ROLES
+        .into_iter()
+        .filter(|auth| is_authorized(principal, *auth))
+        .filter_map(|auth| get_quota(&QuotaTarget::Role(auth)))
+        .reduce(|a, b| Quota {
+            requests_per_minute: min_limit(a.requests_per_minute, b.requests_per_minute),
+            cycles_per_day: min_limit(a.cycles_per_day, b.cycles_per_day),
+        })
+}
+
+fn min_limit<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Returns the usage of a principal within the current quota windows.
+fn current_usage(usage: &QuotaUsage, now: u64) -> QuotaUsage {
+    let minute_start = now - now % QUOTA_MINUTE_NANOS;
+    let day_start = now - now % QUOTA_DAY_NANOS;
+    QuotaUsage {
+        minute_start,
+        requests: if usage.minute_start == minute_start {
+            usage.requests
+        } else {
+            0
+        },
+        day_start,
+        cycles: if usage.day_start == day_start {
+            usage.cycles
+        } else {
+            0
+        },
+        last_request_id: usage.last_request_id,
+    }
+}
+
+/// Returns the time (in nanoseconds since the epoch) after which the principal may spend the
+/// given cycles, or `None` if they fit within the daily limit.
+fn get_cycles_retry_after(quota: &Quota, usage: &QuotaUsage, cycles: u128) -> Option<u64> {
+    match quota.cycles_per_day {
+        Some(limit) if usage.cycles >= limit || usage.cycles.saturating_add(cycles) > limit => {
+            Some(usage.day_start + QUOTA_DAY_NANOS)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the time (in nanoseconds since the epoch) after which the principal may make
+/// requests again, or `None` if the quota is not exceeded.
+fn get_retry_after(quota: &Quota, usage: &QuotaUsage) -> Option<u64> {
+    if matches!(quota.requests_per_minute, Some(limit) if usage.requests >= limit) {
+        return Some(usage.minute_start + QUOTA_MINUTE_NANOS);
+    }
+    get_cycles_retry_after(quota, usage, 0)
+}
+
+fn get_current_usage(principal: &Principal, now: u64) -> QuotaUsage {
+    UNSTABLE_QUOTA_USAGE
+        .with(|u| current_usage(&u.borrow().get(principal).cloned().unwrap_or_default(), now))
+}
+
+/// Checks whether a principal may start a new request, without recording any usage.
+/// Returns the time (in nanoseconds since the epoch) after which to retry if the quota is exceeded.
+pub fn check_quota(principal: &Principal, now: u64) -> Result<(), u64> {
+    let quota = match get_applicable_quota(principal) {
+        Some(quota) => quota,
+        None => return Ok(()),
+    };
+    match get_retry_after(&quota, &get_current_usage(principal, now)) {
+        Some(retry_after) => Err(retry_after),
+        None => Ok(()),
+    }
+}
+
+/// Checks whether a principal may spend the given cycles on an outcall of the current request,
+/// without exceeding its daily limit.
+pub fn check_cycles_quota(principal: &Principal, cycles: u128, now: u64) -> Result<(), u64> {
+    let quota = match get_applicable_quota(principal) {
+        Some(quota) => quota,
+        None => return Ok(()),
+    };
+    match get_cycles_retry_after(&quota, &get_current_usage(principal, now), cycles) {
+        Some(retry_after) => Err(retry_after),
+        None => Ok(()),
+    }
+}
+
+/// Checks the quota of the principal making a request, at the start of the request.
+pub fn check_caller_quota(principal: &Principal) -> RpcResult<()> {
+    check_quota(principal, ic_cdk::api::time()).map_err(|retry_after| {
+        add_metric!(err_rate_limited, 1);
+        rate_limited_error(retry_after)
+    })
+}
+
+/// Checks that the cycles of an outcall fit within the daily limit of the principal.
+pub fn check_caller_cycles_quota(principal: &Principal, cycles: u128) -> RpcResult<()> {
+    check_cycles_quota(principal, cycles, ic_cdk::api::time()).map_err(|retry_after| {
+        add_metric!(err_rate_limited, 1);
+        rate_limited_error(retry_after)
+    })
+}
+
+/// Records the cycles paid for an HTTP outcall against the principal's quota. All outcalls
+/// made for the same request count as a single request.
+pub fn record_quota_usage(principal: &Principal, cycles: u128, request_id: Option<u64>, now: u64) {
+    if get_applicable_quota(principal).is_none() {
+        return;
+    }
+    UNSTABLE_QUOTA_USAGE.with(|u| {
+        let mut usage_map = u.borrow_mut();
+        let usage = usage_map.entry(*principal).or_default();
+        *usage = current_usage(usage, now);
+        if request_id.is_none() || usage.last_request_id != request_id {
+            usage.requests += 1;
+            usage.last_request_id = request_id;
+        }
+        usage.cycles = usage.cycles.saturating_add(cycles);
+    })
+}
+
+/// Returns the quota which applies to a principal together with its current usage.
+pub fn get_quota_status(principal: &Principal, now: u64) -> Option<QuotaStatus> {
+    let quota = get_applicable_quota(principal)?;
+    let usage = get_current_usage(principal, now);
+    Some(QuotaStatus {
+        retry_after: get_retry_after(&quota, &usage),
+        quota,
+        requests_this_minute: usage.requests,
+        cycles_today: usage.cycles,
+    })
+}
+
+/// Error for a request which exceeds the caller's quota, with the time (in nanoseconds since the
+/// epoch) after which to retry.
+pub fn rate_limited_error(retry_after: u64) -> RpcError {
+    RpcError::JsonRpcError(JsonRpcError {
+        code: RATE_LIMITED_ERROR_CODE,
+        message: format!("Rate limit exceeded, retry after {}", retry_after),
+    })
+}
+
+#[test]
+fn test_quota() {
+    let principal =
+        Principal::from_text("k5dlc-ijshq-lsyre-qvvpq-2bnxr-pb26c-ag3sc-t6zo5-rdavy-recje-zqe")
+            .unwrap();
+    let start = 10 * QUOTA_DAY_NANOS;
+    assert_eq!(check_quota(&principal, start), Ok(()));
+    record_quota_usage(&principal, 1_000_000, Some(0), start);
+    assert_eq!(check_quota(&principal, start), Ok(()));
+
+    do_set_quota(
+        QuotaTarget::Principal(principal),
+        Some(Quota {
+            requests_per_minute: Some(2),
+            cycles_per_day: Some(1000),
+        }),
+    );
+    assert_eq!(check_quota(&principal, start), Ok(()));
+    record_quota_usage(&principal, 100, Some(1), start);
+    // Outcalls of the same request count once
+    record_quota_usage(&principal, 100, Some(1), start);
+    assert_eq!(check_quota(&principal, start + 1), Ok(()));
+    record_quota_usage(&principal, 100, Some(2), start + 1);
+    assert_eq!(
+        check_quota(&principal, start + 2),
+        Err(start + QUOTA_MINUTE_NANOS)
+    );
+    assert_eq!(
+        get_quota_status(&principal, start + 2).map(|status| status.retry_after),
+        Some(Some(start + QUOTA_MINUTE_NANOS))
+    );
+    assert_eq!(check_quota(&principal, start + QUOTA_MINUTE_NANOS), Ok(()));
+    // An outcall may not overshoot the daily limit
+    assert_eq!(
+        check_cycles_quota(&principal, 701, start + QUOTA_MINUTE_NANOS),
+        Err(start + QUOTA_DAY_NANOS)
+    );
+    assert_eq!(
+        check_cycles_quota(&principal, 700, start + QUOTA_MINUTE_NANOS),
+        Ok(())
+    );
+    record_quota_usage(&principal, 700, Some(3), start + QUOTA_MINUTE_NANOS);
+    assert_eq!(
+        check_quota(&principal, start + 2 * QUOTA_MINUTE_NANOS),
+        Err(start + QUOTA_DAY_NANOS)
+    );
+    assert_eq!(
+        get_quota_status(&principal, start + 2 * QUOTA_MINUTE_NANOS),
+        Some(QuotaStatus {
+            quota: Quota {
+                requests_per_minute: Some(2),
+                cycles_per_day: Some(1000),
+            },
+            requests_this_minute: 0,
+            cycles_today: 1000,
+            retry_after: Some(start + QUOTA_DAY_NANOS),
+        })
+    );
+    assert_eq!(check_quota(&principal, start + QUOTA_DAY_NANOS), Ok(()));
+
+    do_set_quota(QuotaTarget::Principal(principal), None);
+    assert_eq!(get_applicable_quota(&principal), None);
+    assert_eq!(get_quota_status(&principal, start), None);
+}
+
+#[test]
+fn test_role_quota() {
+    let principal =
+        Principal::from_text("yxhtl-jlpgx-wqnzc-ysego-h6yqe-3zwfo-o3grn-gvuhm-nz3kv-ainub-6ae")
+            .unwrap();
+    do_set_quota(
+        QuotaTarget::Role(Auth::PriorityRpc),
+        Some(Quota {
+            requests_per_minute: Some(10),
+            cycles_per_day: None,
+        }),
+    );
+    do_set_quota(
+        QuotaTarget::Role(Auth::FreeRpc),
+        Some(Quota {
+            requests_per_minute: Some(20),
+            cycles_per_day: Some(1000),
+        }),
+    );
+    assert_eq!(get_applicable_quota(&principal), None);
+
+    do_authorize(principal, Auth::PriorityRpc);
+    do_authorize(principal, Auth::FreeRpc);
+    assert_eq!(
+        get_applicable_quota(&principal),
+        Some(Quota {
+            requests_per_minute: Some(10),
+            cycles_per_day: Some(1000),
+        })
+    );
+
+    let principal_quota = Quota {
+        requests_per_minute: None,
+        cycles_per_day: Some(5000),
+    };
+    do_set_quota(
+        QuotaTarget::Principal(principal),
+        Some(principal_quota.clone()),
+    );
+    assert_eq!(get_applicable_quota(&principal), Some(principal_quota));
+}
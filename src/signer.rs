@@ -0,0 +1,356 @@
+use std::str::FromStr;
+
+use candid::Nat;
+use cketh_common::eth_rpc::{HttpOutcallError, RpcError, ValidationError};
+use cketh_common::logs::INFO;
+use ethers_core::{
+    k256::ecdsa::VerifyingKey,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Eip1559TransactionRequest, Signature,
+        H256, U256,
+    },
+    utils::public_key_to_address,
+};
+use ic_canister_log::log;
+use ic_cdk::api::management_canister::ecdsa::{
+    ecdsa_public_key, sign_with_ecdsa, EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyArgument,
+    SignWithEcdsaArgument,
+};
+use num_traits::ToPrimitive;
+
+use crate::candid_types::{BlockTag, GetTransactionCountArgs, SendRawTransactionStatus};
+use crate::*;
+
+/// Order of the secp256k1 curve, used to normalize signatures to a low `s` value (EIP-2).
+const SECP256K1_ORDER: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+/// Returns the name of the threshold ECDSA key used to sign transactions (`None` if disabled).
+pub fn get_ecdsa_key_name() -> Option<String> {
+    METADATA.with(|m| m.borrow().get().ecdsa_key_name.clone())
+}
+
+pub fn set_ecdsa_key_name(key_name: Option<String>) {
+    METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        metadata.ecdsa_key_name = key_name;
+        m.borrow_mut().set(metadata).unwrap();
+    });
+    // Addresses depend on the key
+    UNSTABLE_ETH_ADDRESSES.with(|a| a.borrow_mut().clear());
+}
+
+fn ecdsa_key_id() -> Result<EcdsaKeyId, RpcError> {
+    match get_ecdsa_key_name() {
+        Some(name) => Ok(EcdsaKeyId {
+            curve: EcdsaCurve::Secp256k1,
+            name,
+        }),
+        None => Err(ValidationError::Custom("Transaction signing is disabled".to_string()).into()),
+    }
+}
+
+/// Each principal signs with its own key, derived from the principal.
+fn derivation_path(owner: &Principal) -> Vec<Vec<u8>> {
+    vec![owner.as_slice().to_vec()]
+}
+
+pub fn public_key_to_eth_address(public_key: &[u8]) -> Result<Address, RpcError> {
+    let key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|err| ValidationError::Custom(format!("Invalid ECDSA public key: {}", err)))?;
+    Ok(public_key_to_address(&key))
+}
+
+async fn get_eth_address(owner: Principal) -> Result<Address, RpcError> {
+    if let Some(address) = UNSTABLE_ETH_ADDRESSES.with(|a| a.borrow().get(&owner).cloned()) {
+        return Ok(address);
+    }
+    let (response,) = ecdsa_public_key(EcdsaPublicKeyArgument {
+        canister_id: None,
+        derivation_path: derivation_path(&owner),
+        key_id: ecdsa_key_id()?,
+    })
+    .await
+    .map_err(|(code, message)| HttpOutcallError::IcError { code, message })?;
+    public_key_to_eth_address(&response.public_key)
+}
+
+/// Caches the address of a principal which signs transactions. Like usage statistics, addresses
+/// are kept for a bounded number of principals.
+fn cache_eth_address(owner: Principal, address: Address) {
+    UNSTABLE_ETH_ADDRESSES.with(|a| {
+        let mut addresses = a.borrow_mut();
+        if !addresses.contains_key(&owner) && addresses.len() >= MAX_TRACKED_CALLERS {
+            if let Some(evicted) = addresses.keys().next().copied() {
+                addresses.remove(&evicted);
+            }
+        }
+        addresses.insert(owner, address);
+    });
+}
+
+/// Returns the Ethereum address of the key which signs the transactions of a principal.
+pub async fn do_get_eth_address(owner: Principal) -> String {
+    match get_eth_address(owner).await {
+        Ok(address) => format!("{:#x}", address),
+        Err(err) => ic_cdk::trap(&format!("Unable to get Ethereum address: {:?}", err)),
+    }
+}
+
+fn nat_to_u256(value: &Nat, field: &str) -> Result<U256, RpcError> {
+    let bytes = value.0.to_bytes_be();
+    if bytes.len() > 32 {
+        return Err(ValidationError::Custom(format!("Transaction {} is too large", field)).into());
+    }
+    Ok(U256::from_big_endian(&bytes))
+}
+
+async fn get_transaction_fees(client: &CandidRpcClient) -> Result<(U256, U256), RpcError> {
+    match estimate_eip1559_fees(client, &EstimateFeesArgs::default()).await {
+        MultiRpcResult::Consistent(Ok(estimate)) => Ok((
+            nat_to_u256(&estimate.max_fee_per_gas, "maximum fee")?,
+            nat_to_u256(&estimate.max_priority_fee_per_gas, "priority fee")?,
+        )),
+        MultiRpcResult::Consistent(Err(err)) => Err(err),
+        MultiRpcResult::Inconsistent(_) => Err(ValidationError::Custom(
+            "Providers returned inconsistent fee histories".to_string(),
+        )
+        .into()),
+    }
+}
+
+async fn get_pending_count(client: &CandidRpcClient, address: &Address) -> Result<u64, RpcError> {
+    let args = GetTransactionCountArgs {
+        address: format!("{:#x}", address),
+        block: BlockTag::Pending,
+    };
+    match client.eth_get_transaction_count(args).await {
+        MultiRpcResult::Consistent(Ok(count)) => count.0.to_u64().ok_or_else(|| {
+            ValidationError::Custom("Transaction count is too large".to_string()).into()
+        }),
+        MultiRpcResult::Consistent(Err(err)) => Err(err),
+        MultiRpcResult::Inconsistent(_) => Err(ValidationError::Custom(
+            "Providers returned inconsistent transaction counts".to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Charges the caller for a threshold ECDSA signature, like for an HTTP outcall. Returns the
+/// charged cycles.
+fn charge_signing_fee(caller: &Principal, chain_id: u64) -> Result<u128, RpcError> {
+    let auth_context = AuthContext {
+        chain_id: Some(chain_id),
+        method: RpcMethod::EthSendRawTransaction.name(),
+        provider_id: None,
+    };
+    if is_authorized_for(caller, Auth::FreeRpc, &auth_context) {
+        return Ok(0);
+    }
+    let cycles_cost = SIGN_WITH_ECDSA_CYCLES;
+    charge_cycles(caller, cycles_cost)?;
+    add_caller_usage(*caller, |usage| usage.cycles_charged += cycles_cost);
+    Ok(cycles_cost)
+}
+
+/// Credits the fee of a failed signature to the caller's prepaid balance.
+fn refund_signing_fee(caller: Principal, cycles: u128) {
+    if cycles == 0 {
+        return;
+    }
+    do_deposit(caller, cycles);
+    add_caller_usage(caller, |usage| {
+        usage.cycles_charged = usage.cycles_charged.saturating_sub(cycles)
+    });
+}
+
+/// Builds an Ethereum signature from a threshold ECDSA signature (`r || s`), finding the recovery
+/// ID which yields the signing address.
+pub fn to_eth_signature(
+    signature: &[u8],
+    sighash: H256,
+    from: &Address,
+) -> Result<Signature, RpcError> {
+    if signature.len() != 64 {
+        return Err(ValidationError::Custom("Invalid ECDSA signature length".to_string()).into());
+    }
+    let order = U256::from_str(SECP256K1_ORDER).unwrap();
+    let r = U256::from_big_endian(&signature[..32]);
+    let mut s = U256::from_big_endian(&signature[32..]);
+    if s > order / 2 {
+        s = order - s;
+    }
+    (0..=1)
+        .map(|v| Signature { r, s, v })
+        .find(|signature| signature.recover(sighash).ok().as_ref() == Some(from))
+        .ok_or_else(|| {
+            ValidationError::Custom("Unable to recover the signing address".to_string()).into()
+        })
+}
+
+/// Signs a transaction with the caller's key and returns its RLP encoding.
+async fn sign_with_caller_key(
+    owner: Principal,
+    key_id: EcdsaKeyId,
+    chain_id: u64,
+    transaction: TypedTransaction,
+    from: &Address,
+) -> Result<String, RpcError> {
+    let sighash = transaction.sighash();
+    let cycles_charged = charge_signing_fee(&owner, chain_id)?;
+    let (response,) = match sign_with_ecdsa(SignWithEcdsaArgument {
+        message_hash: sighash.0.to_vec(),
+        derivation_path: derivation_path(&owner),
+        key_id,
+    })
+    .await
+    {
+        Ok(response) => response,
+        Err((code, message)) => {
+            // Refund on failure to sign
+            log!(
+                INFO,
+                "[{}] Unable to sign transaction: {:?} {}",
+                owner,
+                code,
+                message
+            );
+            refund_signing_fee(owner, cycles_charged);
+            return Err(HttpOutcallError::IcError { code, message }.into());
+        }
+    };
+    let signature = to_eth_signature(&response.signature, sighash, from)?;
+    Ok(format!(
+        "0x{}",
+        hex::encode(transaction.rlp_signed(&signature))
+    ))
+}
+
+/// Fills in the fees and a reserved nonce of a transaction request, then signs it with the
+/// caller's key. Returns the signed transaction with its sender and nonce.
+async fn sign_transaction(
+    owner: Principal,
+    client: &CandidRpcClient,
+    chain_id: u64,
+    args: SendTransactionArgs,
+) -> Result<(String, Address, u64), RpcError> {
+    let key_id = ecdsa_key_id()?;
+    let to = args
+        .to
+        .map(|to| {
+            Address::from_str(&to).map_err(|_| {
+                RpcError::from(ValidationError::Custom(format!("Invalid address: {}", to)))
+            })
+        })
+        .transpose()?;
+    let data = match args.data {
+        Some(data) => hex_to_bytes(&data).ok_or(ValidationError::InvalidHex(data))?,
+        None => vec![],
+    };
+    let value = nat_to_u256(&args.value, "value")?;
+    let gas = nat_to_u256(&args.gas, "gas")?;
+    let from = get_eth_address(owner).await?;
+    cache_eth_address(owner, from);
+    let (max_fee, priority_fee) = match (args.max_fee_per_gas, args.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(priority_fee)) => (
+            nat_to_u256(&max_fee, "maximum fee")?,
+            nat_to_u256(&priority_fee, "priority fee")?,
+        ),
+        _ => get_transaction_fees(client).await?,
+    };
+    let pending_count = get_pending_count(client, &from).await?;
+    let nonce = reserve_nonce(chain_id, &from.0, pending_count, ic_cdk::api::time())
+        .map_err(ValidationError::Custom)?;
+    let mut request = Eip1559TransactionRequest::new()
+        .from(from)
+        .value(value)
+        .data(data)
+        .gas(gas)
+        .nonce(nonce)
+        .chain_id(chain_id)
+        .max_fee_per_gas(max_fee)
+        .max_priority_fee_per_gas(priority_fee);
+    if let Some(to) = to {
+        request = request.to(to);
+    }
+    match sign_with_caller_key(
+        owner,
+        key_id,
+        chain_id,
+        TypedTransaction::Eip1559(request),
+        &from,
+    )
+    .await
+    {
+        Ok(raw_signed_transaction_hex) => Ok((raw_signed_transaction_hex, from, nonce)),
+        Err(err) => {
+            release_nonce(chain_id, &from.0, nonce);
+            Err(err)
+        }
+    }
+}
+
+/// Signs a transaction request with the caller's threshold ECDSA key and sends it.
+pub async fn do_send_transaction(
+    owner: Principal,
+    args: SendTransactionArgs,
+    config: Option<RpcConfig>,
+) -> (
+    MultiRpcResult<candid_types::SendRawTransactionStatus>,
+    Option<DecodedTransaction>,
+) {
+    if owner == Principal::anonymous() {
+        ic_cdk::trap("Anonymous principals cannot sign transactions");
+    }
+    let chain_id = args.services.chain_id();
+    let client = match CandidRpcClient::new(args.services.clone(), config) {
+        Ok(client) => client,
+        Err(err) => return (Err(err).into(), None),
+    };
+    let (raw_signed_transaction_hex, from, nonce) =
+        match sign_transaction(owner, &client, chain_id, args).await {
+            Ok(signed) => signed,
+            Err(err) => return (Err(err).into(), None),
+        };
+    let (result, transaction) = client
+        .send_raw_transaction(raw_signed_transaction_hex)
+        .await;
+    let transaction = transaction.ok();
+    match &result {
+        // The transaction may have reached some providers if their results are inconsistent
+        MultiRpcResult::Consistent(Ok(SendRawTransactionStatus::Ok(_)))
+        | MultiRpcResult::Consistent(Ok(SendRawTransactionStatus::NonceTooLow))
+        | MultiRpcResult::Inconsistent(_) => {
+            mark_nonce_sent(chain_id, &from.0, nonce, ic_cdk::api::time())
+        }
+        MultiRpcResult::Consistent(_) => release_nonce(chain_id, &from.0, nonce),
+    }
+    (result, transaction)
+}
+
+#[test]
+fn test_public_key_to_eth_address() {
+    // Public key of the private key 1
+    let public_key =
+        hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+    assert_eq!(
+        format!("{:#x}", public_key_to_eth_address(&public_key).unwrap()),
+        "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+    );
+    assert!(public_key_to_eth_address(&[2; 33]).is_err());
+}
+
+#[test]
+fn test_cache_eth_address() {
+    let owner = |id: usize| Principal::from_slice(&id.to_be_bytes());
+    for id in 0..=MAX_TRACKED_CALLERS {
+        cache_eth_address(owner(id), Address::from_low_u64_be(id as u64));
+    }
+    UNSTABLE_ETH_ADDRESSES.with(|a| {
+        let addresses = a.borrow();
+        assert_eq!(addresses.len(), MAX_TRACKED_CALLERS);
+        assert_eq!(
+            addresses.get(&owner(MAX_TRACKED_CALLERS)),
+            Some(&Address::from_low_u64_be(MAX_TRACKED_CALLERS as u64))
+        );
+    });
+}
@@ -0,0 +1,719 @@
+use cketh_common::{
+    eth_rpc::{Block, GetLogsParam},
+    logs::INFO,
+    numeric::BlockNumber,
+};
+use ic_canister_log::log;
+use ic_stable_structures::Storable;
+use num_traits::ToPrimitive;
+
+use crate::candid_types::{BlockTag, GetLogsArgs};
+use crate::*;
+
+/// Range of blocks processed by a subscription poll.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogPoll {
+    pub from: u64,
+    pub to: u64,
+    /// Whether logs were found and sent to the subscriber.
+    pub delivered: bool,
+}
+
+/// Since polls are made by a timer, they can only be paid from a prepaid balance, unless the owner
+/// has a free authorization whose scope covers the chain and method.
+fn can_pay_for_polls(owner: &Principal, chain_id: u64, method: &str) -> bool {
+    let auth_context = AuthContext {
+        chain_id: Some(chain_id),
+        method,
+        provider_id: None,
+    };
+    get_balance(owner) > 0 || is_authorized_for(owner, Auth::FreeRpc, &auth_context)
+}
+
+/// Returns the time since which the owner of a subscription cannot pay for polls, or `None` if it can.
+pub fn get_unpaid_since(
+    owner: &Principal,
+    chain_id: u64,
+    method: &str,
+    unpaid_since: Option<u64>,
+    now: u64,
+) -> Option<u64> {
+    if can_pay_for_polls(owner, chain_id, method) {
+        return None;
+    }
+    Some(unpaid_since.unwrap_or(now))
+}
+
+/// Traps unless the owner of a new subscription may call the RPC services and pay for the polls,
+/// either from a prepaid balance or with a free authorization for the chain.
+fn check_subscription_owner(owner: &Principal, services: &RpcServices, method: &str) {
+    if !is_rpc_allowed(owner) {
+        ic_cdk::trap("You are not authorized");
+    }
+    if !can_pay_for_polls(owner, services.chain_id(), method) {
+        ic_cdk::trap("Subscriptions require a prepaid balance or a free RPC authorization");
+    }
+}
+
+pub fn is_unpaid_subscription_expired(unpaid_since: u64, now: u64) -> bool {
+    now.saturating_sub(unpaid_since) >= SUBSCRIPTION_UNPAID_EXPIRY.as_nanos() as u64
+}
+
+fn next_subscription_id() -> u64 {
+    METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        let id = metadata.next_subscription_id.unwrap_or_default();
+        metadata.next_subscription_id = Some(id + 1);
+        m.borrow_mut().set(metadata).unwrap();
+        id
+    })
+}
+
+pub fn block_number_to_u64(block_number: BlockNumber) -> Result<u64, String> {
+    candid::Nat::from(block_number)
+        .0
+        .to_u64()
+        .ok_or_else(|| format!("Block number {:?} is too large", block_number))
+}
+
+pub fn get_log_subscription(id: u64) -> Option<LogSubscription> {
+    LOG_SUBSCRIPTIONS.with(|s| s.borrow().get(&id))
+}
+
+pub fn get_log_subscriptions(owner: Principal) -> Vec<LogSubscription> {
+    LOG_SUBSCRIPTIONS.with(|s| {
+        s.borrow()
+            .iter()
+            .map(|(_, subscription)| subscription)
+            .filter(|subscription| subscription.owner == owner)
+            .collect()
+    })
+}
+
+pub fn do_subscribe_logs(owner: Principal, args: SubscribeLogsArgs) -> u64 {
+    if owner == Principal::anonymous() {
+        ic_cdk::trap("Anonymous principals cannot subscribe");
+    }
+    if args.callback.is_empty() || args.callback.len() > SUBSCRIPTION_CALLBACK_MAX_LENGTH {
+        ic_cdk::trap("Invalid callback method name");
+    }
+    let confirmation = args.filter.to_block.clone().unwrap_or(BlockTag::Finalized);
+    if !matches!(
+        confirmation,
+        BlockTag::Latest | BlockTag::Safe | BlockTag::Finalized
+    ) {
+        ic_cdk::trap("Subscriptions only support the Latest, Safe and Finalized tags for toBlock");
+    }
+    let next_block = match &args.filter.from_block {
+        Some(BlockTag::Number(n)) => {
+            Some(block_number_to_u64(*n).unwrap_or_else(|err| ic_cdk::trap(&err)))
+        }
+        Some(BlockTag::Earliest) => Some(0),
+        _ => None,
+    };
+    if let Err(err) = GetLogsParam::try_from(args.filter.clone()) {
+        ic_cdk::trap(&format!("Invalid log filter: {:?}", err));
+    }
+    if get_log_subscriptions(owner).len() >= MAX_SUBSCRIPTIONS_PER_CALLER {
+        ic_cdk::trap("Too many subscriptions");
+    }
+    check_subscription_owner(&owner, &args.services, RpcMethod::EthGetLogs.name());
+    let id = next_subscription_id();
+    let subscription = LogSubscription {
+        id,
+        owner,
+        services: args.services,
+        addresses: args.filter.addresses,
+        topics: args.filter.topics,
+        confirmation,
+        callback: args.callback,
+        next_block,
+        delivered: None,
+        last_error: None,
+        unpaid_since: None,
+    };
+    if subscription.to_bytes().len() > SUBSCRIPTION_MAX_SIZE as usize {
+        ic_cdk::trap("Subscription exceeds the maximum size");
+    }
+    LOG_SUBSCRIPTIONS.with(|s| s.borrow_mut().insert(id, subscription));
+    id
+}
+
+/// Removes a subscription owned by the caller (or any subscription if `is_manager` is set).
+pub fn do_unsubscribe_logs(caller: Principal, id: u64, is_manager: bool) -> bool {
+    match get_log_subscription(id) {
+        Some(subscription) if is_manager || subscription.owner == caller => {
+            LOG_SUBSCRIPTIONS.with(|s| s.borrow_mut().remove(&id));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Acknowledges the logs up to and including the `cursor` block, which are then no longer delivered.
+pub fn do_acknowledge_logs(caller: Principal, id: u64, cursor: u64) {
+    let mut subscription = match get_log_subscription(id) {
+        Some(subscription) if subscription.owner == caller => subscription,
+        _ => ic_cdk::trap("Subscription not found"),
+    };
+    match subscription.delivered {
+        Some(delivered) if cursor <= delivered => {}
+        _ => ic_cdk::trap("Cursor has not been delivered"),
+    }
+    if subscription.next_block.map_or(true, |next| cursor >= next) {
+        subscription.next_block = Some(cursor + 1);
+    }
+    if subscription.delivered == Some(cursor) {
+        subscription.delivered = None;
+    }
+    LOG_SUBSCRIPTIONS.with(|s| s.borrow_mut().insert(id, subscription));
+}
+
+/// Records the outcome of a poll, unless the subscription was removed or acknowledged in the meantime.
+pub fn apply_log_poll(id: u64, result: Result<Option<LogPoll>, String>) {
+    let mut subscription = match get_log_subscription(id) {
+        Some(subscription) => subscription,
+        None => return,
+    };
+    match result {
+        Ok(Some(poll)) => {
+            subscription.last_error = None;
+            if subscription
+                .next_block
+                .map_or(true, |next| next == poll.from)
+            {
+                if poll.delivered {
+                    subscription.next_block = Some(poll.from);
+                    subscription.delivered = Some(subscription.delivered.unwrap_or(0).max(poll.to));
+                } else {
+                    subscription.next_block = Some(poll.to + 1);
+                    if subscription
+                        .delivered
+                        .map_or(false, |delivered| delivered <= poll.to)
+                    {
+                        subscription.delivered = None;
+                    }
+                }
+            }
+        }
+        Ok(None) => subscription.last_error = None,
+        Err(err) => subscription.last_error = Some(err),
+    }
+    subscription.unpaid_since = None;
+    LOG_SUBSCRIPTIONS.with(|s| s.borrow_mut().insert(id, subscription));
+}
+
+/// Pauses a subscription whose owner cannot pay for polls, and removes it once it has been unpaid
+/// for too long.
+fn pause_log_subscription(id: u64, unpaid_since: u64, now: u64) {
+    let mut subscription = match get_log_subscription(id) {
+        Some(subscription) => subscription,
+        None => return,
+    };
+    if is_unpaid_subscription_expired(unpaid_since, now) {
+        log!(
+            INFO,
+            "[{}] Removing unpaid log subscription {}",
+            subscription.owner,
+            id
+        );
+        LOG_SUBSCRIPTIONS.with(|s| s.borrow_mut().remove(&id));
+    } else if subscription.unpaid_since != Some(unpaid_since) {
+        subscription.unpaid_since = Some(unpaid_since);
+        LOG_SUBSCRIPTIONS.with(|s| s.borrow_mut().insert(id, subscription));
+    }
+}
+
+/// Resolves the block up to which a subscription is delivered, preferring the tracked chain head.
+pub async fn get_head_block_number(
+    client: &CandidRpcClient,
+    chain_id: u64,
+    tag: BlockTag,
+) -> Result<u64, String> {
+    let block_number = match pin_block_tag(chain_id, tag) {
+        BlockTag::Number(n) => n,
+        tag => match client.eth_get_block_by_number(tag).await {
+            MultiRpcResult::Consistent(Ok(block)) => block.number,
+            MultiRpcResult::Consistent(Err(err)) => return Err(format!("{:?}", err)),
+            MultiRpcResult::Inconsistent(_) => {
+                return Err("Providers returned inconsistent head blocks".to_string())
+            }
+        },
+    };
+    block_number_to_u64(block_number)
+}
+
+/// Fetches the next range of logs for a subscription and sends them to the subscriber.
+/// RPC calls are made on behalf of the subscriber.
+async fn poll_log_subscription(subscription: &LogSubscription) -> Result<Option<LogPoll>, String> {
+    let chain_id = subscription.services.chain_id();
+    let client = CandidRpcClient::new(subscription.services.clone(), None)
+        .map_err(|err| format!("{:?}", err))?;
+    let head = get_head_block_number(&client, chain_id, subscription.confirmation.clone()).await?;
+    let from = subscription.next_block.unwrap_or(head);
+    if from > head {
+        return Ok(None);
+    }
+    let to = head.min(from + ETH_GET_LOGS_MAX_BLOCKS as u64 - 1);
+    let args = GetLogsArgs {
+        from_block: Some(BlockTag::Number(BlockNumber::new(from as u128))),
+        to_block: Some(BlockTag::Number(BlockNumber::new(to as u128))),
+        addresses: subscription.addresses.clone(),
+        topics: subscription.topics.clone(),
+    };
+    let logs = match client.eth_get_logs(args).await {
+        MultiRpcResult::Consistent(Ok(logs)) => logs,
+        MultiRpcResult::Consistent(Err(err)) => return Err(format!("{:?}", err)),
+        MultiRpcResult::Inconsistent(_) => {
+            return Err("Providers returned inconsistent logs".to_string())
+        }
+    };
+    let delivered = !logs.is_empty();
+    if delivered {
+        let delivery = LogDelivery {
+            subscription_id: subscription.id,
+            from_block: from,
+            cursor: to,
+            logs,
+        };
+        ic_cdk::api::call::notify(subscription.owner, &subscription.callback, (delivery,))
+            .map_err(|code| format!("Unable to notify subscriber: {:?}", code))?;
+    }
+    Ok(Some(LogPoll {
+        from,
+        to,
+        delivered,
+    }))
+}
+
+/// Polls all log subscriptions, charging each subscriber for its RPC calls, unless a poll is in
+/// progress. Subscriptions whose owner cannot pay are paused.
+pub async fn poll_log_subscriptions() {
+    let _guard = match PollGuard::new(PollTask::LogSubscriptions) {
+        Some(guard) => guard,
+        None => return,
+    };
+    let subscriptions: Vec<LogSubscription> = LOG_SUBSCRIPTIONS.with(|s| {
+        s.borrow()
+            .iter()
+            .map(|(_, subscription)| subscription)
+            .collect()
+    });
+    for subscription in subscriptions {
+        let now = ic_cdk::api::time();
+        if let Some(unpaid_since) = get_unpaid_since(
+            &subscription.owner,
+            subscription.services.chain_id(),
+            RpcMethod::EthGetLogs.name(),
+            subscription.unpaid_since,
+            now,
+        ) {
+            pause_log_subscription(subscription.id, unpaid_since, now);
+            continue;
+        }
+        let result =
+            with_rpc_caller(subscription.owner, poll_log_subscription(&subscription)).await;
+        if let Err(err) = &result {
+            log!(
+                INFO,
+                "Unable to poll log subscription {}: {}",
+                subscription.id,
+                err
+            );
+        }
+        apply_log_poll(subscription.id, result);
+    }
+}
+
+pub fn get_block_subscription(id: u64) -> Option<BlockSubscription> {
+    BLOCK_SUBSCRIPTIONS.with(|s| s.borrow().get(&id))
+}
+
+pub fn get_block_subscriptions(owner: Principal) -> Vec<BlockSubscription> {
+    BLOCK_SUBSCRIPTIONS.with(|s| {
+        s.borrow()
+            .iter()
+            .map(|(_, subscription)| subscription)
+            .filter(|subscription| subscription.owner == owner)
+            .collect()
+    })
+}
+
+pub fn do_subscribe_blocks(owner: Principal, args: SubscribeBlocksArgs) -> u64 {
+    if owner == Principal::anonymous() {
+        ic_cdk::trap("Anonymous principals cannot subscribe");
+    }
+    if args.callback.is_empty() || args.callback.len() > SUBSCRIPTION_CALLBACK_MAX_LENGTH {
+        ic_cdk::trap("Invalid callback method name");
+    }
+    if get_block_subscriptions(owner).len() >= MAX_SUBSCRIPTIONS_PER_CALLER {
+        ic_cdk::trap("Too many subscriptions");
+    }
+    check_subscription_owner(
+        &owner,
+        &args.services,
+        RpcMethod::EthGetBlockByNumber.name(),
+    );
+    let id = next_subscription_id();
+    let subscription = BlockSubscription {
+        id,
+        owner,
+        services: args.services,
+        callback: args.callback,
+        next_block: args.from_block,
+        recent_blocks: vec![],
+        last_error: None,
+        unpaid_since: None,
+    };
+    if subscription.to_bytes().len() > SUBSCRIPTION_MAX_SIZE as usize {
+        ic_cdk::trap("Subscription exceeds the maximum size");
+    }
+    BLOCK_SUBSCRIPTIONS.with(|s| s.borrow_mut().insert(id, subscription));
+    id
+}
+
+/// Removes a subscription owned by the caller (or any subscription if `is_manager` is set).
+pub fn do_unsubscribe_blocks(caller: Principal, id: u64, is_manager: bool) -> bool {
+    match get_block_subscription(id) {
+        Some(subscription) if is_manager || subscription.owner == caller => {
+            BLOCK_SUBSCRIPTIONS.with(|s| s.borrow_mut().remove(&id));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Remembers a delivered block, keeping only the most recent ones.
+pub fn push_recent_block(recent_blocks: &mut Vec<(u64, String)>, number: u64, hash: String) {
+    recent_blocks.retain(|(n, _)| *n < number);
+    recent_blocks.push((number, hash));
+    if recent_blocks.len() > BLOCK_SUBSCRIPTION_HISTORY_LENGTH {
+        recent_blocks.remove(0);
+    }
+}
+
+fn get_recent_block_hash(recent_blocks: &[(u64, String)], number: u64) -> Option<&String> {
+    recent_blocks
+        .iter()
+        .find(|(n, _)| *n == number)
+        .map(|(_, hash)| hash)
+}
+
+async fn fetch_block(client: &CandidRpcClient, number: u64) -> Result<Block, String> {
+    match client
+        .eth_get_block_by_number(BlockTag::Number(BlockNumber::new(number as u128)))
+        .await
+    {
+        MultiRpcResult::Consistent(Ok(block)) => Ok(block),
+        MultiRpcResult::Consistent(Err(err)) => Err(format!("{:?}", err)),
+        MultiRpcResult::Inconsistent(_) => Err(format!(
+            "Providers returned inconsistent blocks for {}",
+            number
+        )),
+    }
+}
+
+/// Returns the first delivered block which is no longer part of the chain, walking back from
+/// `number` until a block matches its recorded hash (or the recorded blocks are exhausted).
+async fn find_orphaned_block(
+    client: &CandidRpcClient,
+    recent_blocks: &[(u64, String)],
+    number: u64,
+) -> Result<u64, String> {
+    let mut orphaned = number;
+    while let Some(previous) = orphaned.checked_sub(1) {
+        let hash = match get_recent_block_hash(recent_blocks, previous) {
+            Some(hash) => hash,
+            None => break,
+        };
+        if fetch_block(client, previous).await?.hash == *hash {
+            break;
+        }
+        orphaned = previous;
+    }
+    Ok(orphaned)
+}
+
+/// Fetches the next block headers for a subscription, detecting reorgs by comparing parent hashes
+/// with the recorded ones, and sends them to the subscriber. Returns the next block and the
+/// updated recent blocks.
+async fn poll_block_subscription(
+    subscription: &BlockSubscription,
+) -> Result<Option<(u64, Vec<(u64, String)>)>, String> {
+    let chain_id = subscription.services.chain_id();
+    let client = CandidRpcClient::new(subscription.services.clone(), None)
+        .map_err(|err| format!("{:?}", err))?;
+    let head = get_head_block_number(&client, chain_id, BlockTag::Latest).await?;
+    let mut number = subscription.next_block.unwrap_or(head);
+    if number > head {
+        return Ok(None);
+    }
+    let mut recent_blocks = subscription.recent_blocks.clone();
+    let mut events = vec![];
+    let mut fetched = 0;
+    while number <= head && fetched < BLOCK_SUBSCRIPTION_MAX_BLOCKS_PER_POLL {
+        let block = fetch_block(&client, number).await?;
+        fetched += 1;
+        let parent_hash = number
+            .checked_sub(1)
+            .and_then(|parent| get_recent_block_hash(&recent_blocks, parent));
+        if let Some(parent_hash) = parent_hash {
+            if *parent_hash != block.parent_hash {
+                let orphaned = find_orphaned_block(&client, &recent_blocks, number - 1).await?;
+                events.push(BlockEvent::Reorg(BlockReorg {
+                    from_block: orphaned,
+                    to_block: recent_blocks.last().map_or(number - 1, |(n, _)| *n),
+                }));
+                recent_blocks.retain(|(n, _)| *n < orphaned);
+                number = orphaned;
+                continue;
+            }
+        }
+        push_recent_block(&mut recent_blocks, number, block.hash.clone());
+        events.push(BlockEvent::Block(block_header(block)));
+        number += 1;
+    }
+    if !events.is_empty() {
+        let delivery = BlockDelivery {
+            subscription_id: subscription.id,
+            events,
+        };
+        ic_cdk::api::call::notify(subscription.owner, &subscription.callback, (delivery,))
+            .map_err(|code| format!("Unable to notify subscriber: {:?}", code))?;
+    }
+    Ok(Some((number, recent_blocks)))
+}
+
+/// Pauses a subscription whose owner cannot pay for polls, and removes it once it has been unpaid
+/// for too long.
+fn pause_block_subscription(id: u64, unpaid_since: u64, now: u64) {
+    let mut subscription = match get_block_subscription(id) {
+        Some(subscription) => subscription,
+        None => return,
+    };
+    if is_unpaid_subscription_expired(unpaid_since, now) {
+        log!(
+            INFO,
+            "[{}] Removing unpaid block subscription {}",
+            subscription.owner,
+            id
+        );
+        BLOCK_SUBSCRIPTIONS.with(|s| s.borrow_mut().remove(&id));
+    } else if subscription.unpaid_since != Some(unpaid_since) {
+        subscription.unpaid_since = Some(unpaid_since);
+        BLOCK_SUBSCRIPTIONS.with(|s| s.borrow_mut().insert(id, subscription));
+    }
+}
+
+/// Polls all block subscriptions, charging each subscriber for its RPC calls, unless a poll is in
+/// progress. Subscriptions whose owner cannot pay are paused.
+pub async fn poll_block_subscriptions() {
+    let _guard = match PollGuard::new(PollTask::BlockSubscriptions) {
+        Some(guard) => guard,
+        None => return,
+    };
+    let subscriptions: Vec<BlockSubscription> = BLOCK_SUBSCRIPTIONS.with(|s| {
+        s.borrow()
+            .iter()
+            .map(|(_, subscription)| subscription)
+            .collect()
+    });
+    for subscription in subscriptions {
+        let now = ic_cdk::api::time();
+        if let Some(unpaid_since) = get_unpaid_since(
+            &subscription.owner,
+            subscription.services.chain_id(),
+            RpcMethod::EthGetBlockByNumber.name(),
+            subscription.unpaid_since,
+            now,
+        ) {
+            pause_block_subscription(subscription.id, unpaid_since, now);
+            continue;
+        }
+        let result =
+            with_rpc_caller(subscription.owner, poll_block_subscription(&subscription)).await;
+        // The subscription may have been removed in the meantime
+        let mut current = match get_block_subscription(subscription.id) {
+            Some(current) => current,
+            None => continue,
+        };
+        match result {
+            Ok(Some((next_block, recent_blocks))) => {
+                current.next_block = Some(next_block);
+                current.recent_blocks = recent_blocks;
+                current.last_error = None;
+            }
+            Ok(None) => current.last_error = None,
+            Err(err) => {
+                log!(
+                    INFO,
+                    "Unable to poll block subscription {}: {}",
+                    subscription.id,
+                    err
+                );
+                current.last_error = Some(err);
+            }
+        }
+        current.unpaid_since = None;
+        BLOCK_SUBSCRIPTIONS.with(|s| s.borrow_mut().insert(subscription.id, current));
+    }
+}
+
+/// Returns a principal which can pay for subscriptions.
+#[cfg(test)]
+fn test_subscriber(id: u8) -> Principal {
+    let owner = Principal::from_slice(&[id]);
+    do_deposit(owner, 1);
+    owner
+}
+
+#[cfg(test)]
+fn test_subscribe_logs_args(from_block: Option<BlockTag>) -> SubscribeLogsArgs {
+    SubscribeLogsArgs {
+        services: RpcServices::EthMainnet(None),
+        filter: GetLogsArgs {
+            from_block,
+            to_block: None,
+            addresses: vec!["0xdac17f958d2ee523a2206206994597c13d831ec7".to_string()],
+            topics: None,
+        },
+        callback: "onLogs".to_string(),
+    }
+}
+
+#[test]
+fn test_log_subscription_cursor() {
+    let owner = test_subscriber(1);
+    let other = Principal::from_slice(&[2]);
+    let id = do_subscribe_logs(
+        owner,
+        test_subscribe_logs_args(Some(BlockTag::Number(BlockNumber::new(100)))),
+    );
+    let subscription = get_log_subscription(id).unwrap();
+    assert_eq!(subscription.confirmation, BlockTag::Finalized);
+    assert_eq!(subscription.next_block, Some(100));
+    assert_eq!(get_log_subscriptions(owner).len(), 1);
+    assert_eq!(get_log_subscriptions(other).len(), 0);
+
+    // Empty ranges are skipped without delivery
+    let poll = |from, to, delivered| {
+        Ok(Some(LogPoll {
+            from,
+            to,
+            delivered,
+        }))
+    };
+    apply_log_poll(id, poll(100, 109, false));
+    assert_eq!(get_log_subscription(id).unwrap().next_block, Some(110));
+
+    // Delivered logs are redelivered until acknowledged
+    apply_log_poll(id, poll(110, 119, true));
+    let subscription = get_log_subscription(id).unwrap();
+    assert_eq!(subscription.next_block, Some(110));
+    assert_eq!(subscription.delivered, Some(119));
+    apply_log_poll(id, poll(110, 125, true));
+    assert_eq!(get_log_subscription(id).unwrap().delivered, Some(125));
+    do_acknowledge_logs(owner, id, 125);
+    let subscription = get_log_subscription(id).unwrap();
+    assert_eq!(subscription.next_block, Some(126));
+    assert_eq!(subscription.delivered, None);
+
+    // Stale poll results do not move the cursor
+    apply_log_poll(id, poll(110, 119, false));
+    assert_eq!(get_log_subscription(id).unwrap().next_block, Some(126));
+    apply_log_poll(id, Err("error".to_string()));
+    assert_eq!(
+        get_log_subscription(id).unwrap().last_error,
+        Some("error".to_string())
+    );
+
+    assert!(!do_unsubscribe_logs(other, id, false));
+    assert!(do_unsubscribe_logs(owner, id, false));
+    assert!(get_log_subscription(id).is_none());
+}
+
+#[test]
+fn test_log_subscription_starting_at_head() {
+    let owner = test_subscriber(1);
+    let id = do_subscribe_logs(owner, test_subscribe_logs_args(None));
+    assert_eq!(get_log_subscription(id).unwrap().next_block, None);
+    apply_log_poll(
+        id,
+        Ok(Some(LogPoll {
+            from: 200,
+            to: 200,
+            delivered: true,
+        })),
+    );
+    let subscription = get_log_subscription(id).unwrap();
+    assert_eq!(subscription.next_block, Some(200));
+    assert_eq!(subscription.delivered, Some(200));
+}
+
+#[test]
+fn test_unpaid_subscription() {
+    let owner = Principal::from_slice(&[3]);
+    let unpaid_since = |unpaid_since, chain_id| {
+        get_unpaid_since(&owner, chain_id, "eth_getLogs", unpaid_since, 10)
+    };
+    assert_eq!(unpaid_since(None, ETH_MAINNET_CHAIN_ID), Some(10));
+    assert_eq!(unpaid_since(Some(5), ETH_MAINNET_CHAIN_ID), Some(5));
+
+    // Free authorizations only pay for polls within their scope
+    do_authorize_with(
+        owner,
+        Auth::FreeRpc,
+        None,
+        Some(AuthScope {
+            chain_ids: Some(vec![ETH_SEPOLIA_CHAIN_ID]),
+            methods: None,
+            provider_ids: None,
+        }),
+    );
+    assert_eq!(unpaid_since(Some(5), ETH_SEPOLIA_CHAIN_ID), None);
+    assert_eq!(unpaid_since(Some(5), ETH_MAINNET_CHAIN_ID), Some(5));
+
+    do_deposit(owner, 1);
+    assert_eq!(unpaid_since(Some(5), ETH_MAINNET_CHAIN_ID), None);
+
+    let expiry = SUBSCRIPTION_UNPAID_EXPIRY.as_nanos() as u64;
+    assert!(!is_unpaid_subscription_expired(5, 4 + expiry));
+    assert!(is_unpaid_subscription_expired(5, 5 + expiry));
+}
+
+#[test]
+fn test_block_subscription() {
+    let owner = test_subscriber(1);
+    let id = do_subscribe_blocks(
+        owner,
+        SubscribeBlocksArgs {
+            services: RpcServices::EthMainnet(None),
+            from_block: Some(100),
+            callback: "onBlocks".to_string(),
+        },
+    );
+    assert_eq!(get_block_subscription(id).unwrap().next_block, Some(100));
+    assert_eq!(get_block_subscriptions(owner).len(), 1);
+    assert!(!do_unsubscribe_blocks(
+        Principal::from_slice(&[2]),
+        id,
+        false
+    ));
+    assert!(do_unsubscribe_blocks(Principal::from_slice(&[2]), id, true));
+    assert_eq!(get_block_subscriptions(owner).len(), 0);
+}
+
+#[test]
+fn test_push_recent_block() {
+    let mut recent_blocks = vec![];
+    for number in 0..(BLOCK_SUBSCRIPTION_HISTORY_LENGTH as u64 + 5) {
+        push_recent_block(&mut recent_blocks, number, format!("0x{:x}", number));
+    }
+    assert_eq!(recent_blocks.len(), BLOCK_SUBSCRIPTION_HISTORY_LENGTH);
+    assert_eq!(recent_blocks[0].0, 5);
+
+    // Replacing a block forgets the blocks after it
+    push_recent_block(&mut recent_blocks, 20, "0xabc".to_string());
+    assert_eq!(recent_blocks.last(), Some(&(20, "0xabc".to_string())));
+    assert_eq!(get_recent_block_hash(&recent_blocks, 21), None);
+}
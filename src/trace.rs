@@ -0,0 +1,252 @@
+use std::cell::Cell as StdCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread::LocalKey;
+
+use candid::Principal;
+use ic_canister_log::{declare_log_buffer, export, log};
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+// Structured entries for each HTTP outcall, served with `/logs?priority=TraceRpc`
+declare_log_buffer!(name = TRACE_RPC_BUF, capacity = 1000);
+
+/// Structured log entry for an HTTP outcall made while handling an RPC request.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutcallTrace {
+    pub request_id: Option<u64>,
+    pub provider_id: Option<u64>,
+    pub host: String,
+    pub method: String,
+    pub status: Option<u32>,
+    pub response_size: Option<u64>,
+    pub cycles: u128,
+    pub outcome: String,
+}
+
+/// Log entry in the same JSON format as the entries of the other log priorities.
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceLogEntry {
+    pub timestamp: u64,
+    pub priority: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub message: String,
+    pub counter: u64,
+}
+
+pub fn next_request_id() -> u64 {
+    UNSTABLE_NEXT_REQUEST_ID.with(|id| {
+        let next = id.get();
+        id.set(next + 1);
+        next
+    })
+}
+
+/// Saves the request ID counter in stable memory (e.g. before an upgrade).
+pub fn save_next_request_id() {
+    METADATA.with(|m| {
+        let mut metadata = m.borrow().get().clone();
+        metadata.next_request_id = Some(UNSTABLE_NEXT_REQUEST_ID.with(|id| id.get()));
+        m.borrow_mut().set(metadata).unwrap();
+    });
+}
+
+/// Restores the request ID counter saved in stable memory (e.g. after an upgrade).
+pub fn restore_next_request_id() {
+    let next = METADATA.with(|m| m.borrow().get().next_request_id.unwrap_or_default());
+    UNSTABLE_NEXT_REQUEST_ID.with(|id| id.set(next));
+}
+
+/// Returns the ID of the request being handled, if any.
+pub fn current_request_id() -> Option<u64> {
+    UNSTABLE_CURRENT_REQUEST_ID.with(|id| id.get())
+}
+
+/// Future which sets a thread-local value (such as the request ID) whenever it is polled,
+/// including after resuming from inter-canister calls, and restores the previous value afterwards.
+pub struct WithContext<T: Copy + 'static, F: Future> {
+    key: &'static LocalKey<StdCell<Option<T>>>,
+    value: T,
+    future: Pin<Box<F>>,
+}
+
+impl<T: Copy + 'static, F: Future> WithContext<T, F> {
+    pub fn new(key: &'static LocalKey<StdCell<Option<T>>>, value: T, future: F) -> Self {
+        Self {
+            key,
+            value,
+            future: Box::pin(future),
+        }
+    }
+}
+
+impl<T: Copy + Unpin + 'static, F: Future> Future for WithContext<T, F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (key, value) = (self.key, self.value);
+        let previous = key.with(|v| v.replace(Some(value)));
+        let result = self.future.as_mut().poll(cx);
+        key.with(|v| v.set(previous));
+        result
+    }
+}
+
+/// Handles an RPC request with a new request ID, which is returned together with the result.
+pub async fn with_request_id<F: Future>(future: F) -> (F::Output, u64) {
+    let request_id = next_request_id();
+    let output = WithContext::new(&UNSTABLE_CURRENT_REQUEST_ID, request_id, future).await;
+    (output, request_id)
+}
+
+/// Handles a Candid RPC request with a new request ID, which the caller can look up with
+/// `getRecentRequests`.
+pub async fn trace_request<F: Future>(method: &str, future: F) -> F::Output {
+    let caller = ic_cdk::caller();
+    let request_id = next_request_id();
+    record_recent_request(
+        caller,
+        RecentRequest {
+            request_id,
+            method: method.to_string(),
+            timestamp: ic_cdk::api::time(),
+        },
+    );
+    WithContext::new(&UNSTABLE_CURRENT_REQUEST_ID, request_id, future).await
+}
+
+fn record_recent_request(caller: Principal, request: RecentRequest) {
+    UNSTABLE_RECENT_REQUESTS.with(|r| {
+        let mut recent = r.borrow_mut();
+        // Like usage statistics, recent requests are kept for a bounded number of callers
+        if !recent.contains_key(&caller) && recent.len() >= MAX_TRACKED_CALLERS {
+            let least_recent = recent
+                .iter()
+                .min_by_key(|(_, requests)| requests.back().map(|r| r.request_id))
+                .map(|(caller, _)| *caller);
+            if let Some(least_recent) = least_recent {
+                recent.remove(&least_recent);
+            }
+        }
+        let requests = recent.entry(caller).or_default();
+        if requests.len() >= RECENT_REQUESTS_PER_CALLER {
+            requests.pop_front();
+        }
+        requests.push_back(request);
+    });
+}
+
+/// Returns the most recent requests of a caller, starting with the latest.
+pub fn get_recent_requests(caller: &Principal) -> Vec<RecentRequest> {
+    UNSTABLE_RECENT_REQUESTS.with(|r| {
+        r.borrow()
+            .get(caller)
+            .map(|requests| requests.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
+pub fn trace_outcall(trace: &OutcallTrace) {
+    log!(
+        TRACE_RPC_BUF,
+        "{}",
+        serde_json::to_string(trace).expect("Unable to serialize outcall trace")
+    );
+}
+
+/// Returns trace log entries, optionally only for the given request ID.
+pub fn get_trace_log_entries(request_id: Option<u64>) -> Vec<TraceLogEntry> {
+    export(&TRACE_RPC_BUF)
+        .into_iter()
+        .filter(|entry| match request_id {
+            Some(request_id) => serde_json::from_str::<OutcallTrace>(&entry.message)
+                .map(|trace| trace.request_id == Some(request_id))
+                .unwrap_or(false),
+            None => true,
+        })
+        .map(|entry| TraceLogEntry {
+            timestamp: entry.timestamp,
+            priority: "TraceRpc",
+            file: entry.file,
+            line: entry.line,
+            message: entry.message,
+            counter: entry.counter,
+        })
+        .collect()
+}
+
+/// Serializes trace log entries in the same format as `/logs` uses for the other priorities.
+pub fn serialize_trace_log_entries(entries: Vec<TraceLogEntry>) -> String {
+    serde_json::json!({ "entries": entries }).to_string()
+}
+
+#[test]
+fn test_trace_log_entries() {
+    let trace = |request_id| OutcallTrace {
+        request_id,
+        provider_id: Some(0),
+        host: "rpc.example.com".to_string(),
+        method: "eth_getLogs".to_string(),
+        status: Some(200),
+        response_size: Some(100),
+        cycles: 1000,
+        outcome: "Ok".to_string(),
+    };
+    trace_outcall(&trace(Some(1)));
+    trace_outcall(&trace(Some(2)));
+    trace_outcall(&trace(None));
+    assert_eq!(get_trace_log_entries(None).len(), 3);
+    let entries = get_trace_log_entries(Some(2));
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        serde_json::from_str::<OutcallTrace>(&entries[0].message).unwrap(),
+        trace(Some(2))
+    );
+}
+
+#[test]
+fn test_recent_requests() {
+    let caller = Principal::management_canister();
+    let request = |request_id| RecentRequest {
+        request_id,
+        method: "eth_getLogs".to_string(),
+        timestamp: 0,
+    };
+    for request_id in 0..RECENT_REQUESTS_PER_CALLER as u64 + 2 {
+        record_recent_request(caller, request(request_id));
+    }
+    let requests = get_recent_requests(&caller);
+    assert_eq!(requests.len(), RECENT_REQUESTS_PER_CALLER);
+    assert_eq!(requests[0], request(RECENT_REQUESTS_PER_CALLER as u64 + 1));
+    assert_eq!(requests[RECENT_REQUESTS_PER_CALLER - 1], request(2));
+    assert_eq!(get_recent_requests(&Principal::anonymous()), vec![]);
+}
+
+#[test]
+fn test_with_request_id() {
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut future = Box::pin(with_request_id(async { current_request_id() }));
+    let (inner_request_id, request_id) =
+        match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("Expected future to be ready"),
+        };
+    assert_eq!(inner_request_id, Some(request_id));
+    assert_eq!(current_request_id(), None);
+    assert_eq!(next_request_id(), request_id + 1);
+}
@@ -0,0 +1,289 @@
+use cketh_common::eth_rpc::ValidationError;
+use ethers_core::{
+    types::{transaction::eip2718::TypedTransaction, Signature, H256, U256},
+    utils::{keccak256, rlp},
+};
+
+use crate::*;
+
+const EIP2930_TX_TYPE: u8 = 0x01;
+const EIP1559_TX_TYPE: u8 = 0x02;
+const EIP4844_TX_TYPE: u8 = 0x03;
+
+/// Number of fields of an EIP-4844 transaction which are covered by its signature.
+const EIP4844_UNSIGNED_FIELDS: usize = 11;
+
+impl From<TransactionError> for ValidationError {
+    fn from(err: TransactionError) -> Self {
+        match err {
+            TransactionError::InvalidHex(hex) => ValidationError::InvalidHex(hex),
+            TransactionError::Empty => ValidationError::Custom("Empty transaction".to_string()),
+            TransactionError::UnsupportedType(transaction_type) => ValidationError::Custom(
+                format!("Unsupported transaction type {:#04x}", transaction_type),
+            ),
+            TransactionError::InvalidEncoding(err) => {
+                ValidationError::Custom(format!("Invalid transaction encoding: {}", err))
+            }
+            TransactionError::InvalidSignature(err) => {
+                ValidationError::Custom(format!("Invalid transaction signature: {}", err))
+            }
+            TransactionError::ValueTooLarge(field) => {
+                ValidationError::Custom(format!("Transaction {} is too large", field))
+            }
+            TransactionError::ChainIdMismatch { expected, actual } => {
+                ValidationError::Custom(format!(
+                    "Transaction is signed for chain ID {} instead of {}",
+                    actual, expected
+                ))
+            }
+        }
+    }
+}
+
+fn invalid_encoding(err: rlp::DecoderError) -> TransactionError {
+    TransactionError::InvalidEncoding(err.to_string())
+}
+
+fn to_u64(value: U256, field: &str) -> Result<u64, TransactionError> {
+    if value > U256::from(u64::MAX) {
+        return Err(TransactionError::ValueTooLarge(field.to_string()));
+    }
+    Ok(value.as_u64())
+}
+
+fn recover_sender(signature: &Signature, sighash: H256) -> Result<String, TransactionError> {
+    signature
+        .recover(sighash)
+        .map(|address| format!("{:#x}", address))
+        .map_err(|err| TransactionError::InvalidSignature(err.to_string()))
+}
+
+/// Decodes a signed EIP-4844 transaction, with or without its blobs (network form).
+fn decode_eip4844_transaction(bytes: &[u8]) -> Result<DecodedTransaction, TransactionError> {
+    let envelope = rlp::Rlp::new(&bytes[1..]);
+    let transaction = match envelope.at(0).map_err(invalid_encoding)? {
+        // Network form: transaction, blobs, commitments and proofs
+        first if first.is_list() => first,
+        _ => envelope,
+    };
+    if transaction.item_count().map_err(invalid_encoding)? != EIP4844_UNSIGNED_FIELDS + 3 {
+        return Err(TransactionError::InvalidEncoding(
+            "unexpected number of fields".to_string(),
+        ));
+    }
+    let value = |index: usize| -> Result<U256, TransactionError> {
+        transaction.val_at(index).map_err(invalid_encoding)
+    };
+    let signature = Signature {
+        r: value(EIP4844_UNSIGNED_FIELDS + 1)?,
+        s: value(EIP4844_UNSIGNED_FIELDS + 2)?,
+        v: to_u64(value(EIP4844_UNSIGNED_FIELDS)?, "signature parity")?,
+    };
+    let mut unsigned = rlp::RlpStream::new_list(EIP4844_UNSIGNED_FIELDS);
+    for index in 0..EIP4844_UNSIGNED_FIELDS {
+        unsigned.append_raw(transaction.at(index).map_err(invalid_encoding)?.as_raw(), 1);
+    }
+    let sighash = H256(keccak256(
+        [&[EIP4844_TX_TYPE][..], &unsigned.out()].concat(),
+    ));
+    Ok(DecodedTransaction {
+        // The hash does not cover the blobs
+        transaction_hash: format!(
+            "{:#x}",
+            H256(keccak256(
+                [&[EIP4844_TX_TYPE][..], transaction.as_raw()].concat()
+            ))
+        ),
+        transaction_type: EIP4844_TX_TYPE,
+        chain_id: Some(to_u64(value(0)?, "chain ID")?),
+        from: recover_sender(&signature, sighash)?,
+        nonce: to_u64(value(1)?, "nonce")?,
+    })
+}
+
+/// Decodes a signed legacy, EIP-2930, EIP-1559 or EIP-4844 transaction and recovers its sender.
+pub fn decode_raw_transaction(
+    raw_signed_transaction_hex: &str,
+) -> Result<DecodedTransaction, TransactionError> {
+    let bytes = hex_to_bytes(raw_signed_transaction_hex)
+        .ok_or_else(|| TransactionError::InvalidHex(raw_signed_transaction_hex.to_string()))?;
+    let transaction_type = match bytes.first() {
+        None => return Err(TransactionError::Empty),
+        // Legacy transactions are RLP lists, whose first byte is at least 0xc0
+        Some(byte) if *byte >= 0xc0 => 0,
+        Some(&EIP2930_TX_TYPE) => EIP2930_TX_TYPE,
+        Some(&EIP1559_TX_TYPE) => EIP1559_TX_TYPE,
+        Some(&EIP4844_TX_TYPE) => return decode_eip4844_transaction(&bytes),
+        Some(byte) => return Err(TransactionError::UnsupportedType(*byte)),
+    };
+    let (transaction, signature) = TypedTransaction::decode_signed(&rlp::Rlp::new(&bytes))
+        .map_err(|err| TransactionError::InvalidEncoding(err.to_string()))?;
+    Ok(DecodedTransaction {
+        transaction_hash: format!("{:#x}", H256(keccak256(&bytes))),
+        transaction_type,
+        chain_id: transaction.chain_id().map(|chain_id| chain_id.as_u64()),
+        from: recover_sender(&signature, transaction.sighash())?,
+        nonce: to_u64(transaction.nonce().copied().unwrap_or_default(), "nonce")?,
+    })
+}
+
+/// Decodes a signed transaction and checks that it was signed for the given chain. Legacy
+/// transactions without EIP-155 replay protection are valid on any chain.
+pub fn validate_raw_transaction(
+    raw_signed_transaction_hex: &str,
+    chain_id: u64,
+) -> Result<DecodedTransaction, TransactionError> {
+    let transaction = decode_raw_transaction(raw_signed_transaction_hex)?;
+    match transaction.chain_id {
+        Some(actual) if actual != chain_id => Err(TransactionError::ChainIdMismatch {
+            expected: chain_id,
+            actual,
+        }),
+        _ => Ok(transaction),
+    }
+}
+
+#[test]
+fn test_decode_legacy_transaction() {
+    let transaction = validate_raw_transaction("0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83", 1).unwrap();
+    assert_eq!(
+        transaction,
+        DecodedTransaction {
+            transaction_hash: "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+                .to_string(),
+            transaction_type: 0,
+            chain_id: Some(1),
+            // Example from EIP-155
+            from: "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".to_string(),
+            nonce: 9,
+        }
+    );
+}
+
+// Transactions signed with the private key of the EIP-155 example
+const EIP2930_TRANSACTION: &str = "0x01f8a701078504a817c800827530943535353535353535353535353535353535353535880de0b6b3a764000080f838f7943535353535353535353535353535353535353535e1a0000000000000000000000000000000000000000000000000000000000000000101a0e97f37bdfcaa4e772a7f804a16bf8fcba4b1634b9e94b1b1f9ccae7757f7740da03b85668807941283ac27ca6140ec9578af2e8537440ad9477550542e66ab50a7";
+const EIP1559_TRANSACTION: &str = "0x02f873010884773594008506fc23ac00825208943535353535353535353535353535353535353535880de0b6b3a764000080c001a0ec6a6c810448168524d16ae4846a52a41c60dd49bf4912cff50ef28baf95d6d5a05d91f0ba76b84a9db22afa5db8de15d4a6ebfc5ccf8c6ae48a3b38b490813979";
+const EIP4844_TRANSACTION: &str = "0x03f892010984773594008506fc23ac008252089435353535353535353535353535353535353535358080c0843b9aca00e1a0010000000000000000000000000000000000000000000000000000000000000080a073e16f440bbb1d745fac7b9eeff1fde34aa16a8c553ecc28cf3505718f8a8146a06787636a60ea0829bd0046af616ac48343d0b54f5b9ce13204e29aee3744d3b2";
+// Same EIP-4844 transaction with (truncated) blobs, commitments and proofs
+const EIP4844_NETWORK_TRANSACTION: &str = "0x03f9013cf892010984773594008506fc23ac008252089435353535353535353535353535353535353535358080c0843b9aca00e1a0010000000000000000000000000000000000000000000000000000000000000080a073e16f440bbb1d745fac7b9eeff1fde34aa16a8c553ecc28cf3505718f8a8146a06787636a60ea0829bd0046af616ac48343d0b54f5b9ce13204e29aee3744d3b2f842b84000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000f1b0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000f1b0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+
+#[test]
+fn test_decode_typed_transactions() {
+    let decoded = |transaction_hash: &str, transaction_type: u8, nonce: u64| DecodedTransaction {
+        transaction_hash: transaction_hash.to_string(),
+        transaction_type,
+        chain_id: Some(1),
+        from: "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".to_string(),
+        nonce,
+    };
+    assert_eq!(
+        validate_raw_transaction(EIP2930_TRANSACTION, 1),
+        Ok(decoded(
+            "0xc98f4f88465245798a5568654fd8b8b27ddc66456c697f5ddb7f1849aefc927a",
+            EIP2930_TX_TYPE,
+            7
+        ))
+    );
+    assert_eq!(
+        validate_raw_transaction(EIP1559_TRANSACTION, 1),
+        Ok(decoded(
+            "0xee12c1aa86ad5958153efb493a0739f9fe93d2c2ff84009ada34dd967c216a6d",
+            EIP1559_TX_TYPE,
+            8
+        ))
+    );
+    let eip4844 = decoded(
+        "0xb5e1ed9067ed2f092a8e25caf92f3aa69ea6971bc86bfe245a90cf5faa460ba2",
+        EIP4844_TX_TYPE,
+        9,
+    );
+    assert_eq!(
+        validate_raw_transaction(EIP4844_TRANSACTION, 1),
+        Ok(eip4844.clone())
+    );
+    assert_eq!(
+        validate_raw_transaction(EIP4844_NETWORK_TRANSACTION, 1),
+        Ok(eip4844)
+    );
+    assert_eq!(
+        validate_raw_transaction(EIP1559_TRANSACTION, 11155111),
+        Err(TransactionError::ChainIdMismatch {
+            expected: 11155111,
+            actual: 1
+        })
+    );
+}
+
+#[test]
+fn test_decode_transaction_without_replay_protection() {
+    let transaction = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000801ba00bf8b2e1878bf2aa9757679b90a0f270a25874695284c25f49d9f7e4ed37c2cda071f6e7a29c55ccde6893fda8ef292453594b09dc21f425fd1c53e3097eda4bbf";
+    let expected = DecodedTransaction {
+        transaction_hash: "0xe7f2968fe1b1b4cc3ecc67d776810cbd21a07be5cd07317afd8608223b98e017"
+            .to_string(),
+        transaction_type: 0,
+        chain_id: None,
+        from: "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".to_string(),
+        nonce: 9,
+    };
+    assert_eq!(
+        validate_raw_transaction(transaction, 1),
+        Ok(expected.clone())
+    );
+    assert_eq!(
+        validate_raw_transaction(transaction, 11155111),
+        Ok(expected)
+    );
+}
+
+#[test]
+fn test_validate_raw_transaction_errors() {
+    let legacy = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+    assert_eq!(
+        validate_raw_transaction(legacy, 11155111),
+        Err(TransactionError::ChainIdMismatch {
+            expected: 11155111,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        validate_raw_transaction("f86c", 1),
+        Err(TransactionError::InvalidHex("f86c".to_string()))
+    );
+    assert_eq!(
+        validate_raw_transaction("0x", 1),
+        Err(TransactionError::Empty)
+    );
+    assert_eq!(
+        validate_raw_transaction("0x04c0", 1),
+        Err(TransactionError::UnsupportedType(0x04))
+    );
+    assert!(matches!(
+        validate_raw_transaction(&legacy[..legacy.len() - 2], 1),
+        Err(TransactionError::InvalidEncoding(_))
+    ));
+    assert!(matches!(
+        validate_raw_transaction("0x03c0", 1),
+        Err(TransactionError::InvalidEncoding(_))
+    ));
+}
+
+#[test]
+fn test_transaction_error_into_validation_error() {
+    assert_eq!(
+        ValidationError::from(TransactionError::ChainIdMismatch {
+            expected: 11155111,
+            actual: 1
+        }),
+        ValidationError::Custom(
+            "Transaction is signed for chain ID 1 instead of 11155111".to_string()
+        )
+    );
+    assert_eq!(
+        ValidationError::from(TransactionError::InvalidHex("f86c".to_string())),
+        ValidationError::InvalidHex("f86c".to_string())
+    );
+    assert_eq!(
+        ValidationError::from(TransactionError::UnsupportedType(0x04)),
+        ValidationError::Custom("Unsupported transaction type 0x04".to_string())
+    );
+}
@@ -0,0 +1,418 @@
+use cketh_common::logs::INFO;
+use ic_canister_log::log;
+use ic_stable_structures::Storable;
+
+use crate::candid_types::{BlockTag, SendRawTransactionStatus};
+use crate::*;
+
+pub fn get_tracked_transaction(hash: &str) -> Option<TrackedTransaction> {
+    TRACKED_TRANSACTIONS.with(|t| t.borrow().get(&StringStorable(hash.to_lowercase())))
+}
+
+pub fn get_transaction_status(hash: &str) -> Option<TransactionStatus> {
+    get_tracked_transaction(hash).map(|transaction| transaction.status)
+}
+
+fn is_final_status(status: &TransactionStatus) -> bool {
+    matches!(
+        status,
+        TransactionStatus::Finalized(_) | TransactionStatus::Dropped
+    )
+}
+
+fn insert_tracked_transaction(transaction: TrackedTransaction) {
+    TRACKED_TRANSACTIONS.with(|t| {
+        t.borrow_mut()
+            .insert(StringStorable(transaction.hash.clone()), transaction)
+    });
+}
+
+/// Starts tracking a signed transaction and returns its hash.
+pub fn do_track_transaction(
+    owner: Principal,
+    args: TrackTransactionArgs,
+    timestamp: u64,
+) -> String {
+    if owner == Principal::anonymous() {
+        ic_cdk::trap("Anonymous principals cannot track transactions");
+    }
+    if let Some(callback) = &args.callback {
+        if callback.is_empty() || callback.len() > SUBSCRIPTION_CALLBACK_MAX_LENGTH {
+            ic_cdk::trap("Invalid callback method name");
+        }
+    }
+    let hash = match validate_raw_transaction(
+        &args.raw_signed_transaction_hex,
+        args.services.chain_id(),
+    ) {
+        Ok(transaction) => transaction.transaction_hash,
+        Err(err) => ic_cdk::trap(&format!("Invalid raw signed transaction: {:?}", err)),
+    };
+    if let Some(existing) = get_tracked_transaction(&hash) {
+        if existing.owner != owner {
+            ic_cdk::trap("Transaction is already tracked by another principal");
+        }
+    } else {
+        let tracked = TRACKED_TRANSACTIONS.with(|t| {
+            t.borrow()
+                .iter()
+                .filter(|(_, transaction)| transaction.owner == owner)
+                .count()
+        });
+        if tracked >= MAX_TRACKED_TRANSACTIONS_PER_CALLER {
+            ic_cdk::trap("Too many tracked transactions");
+        }
+    }
+    let transaction = TrackedTransaction {
+        hash: hash.clone(),
+        owner,
+        services: args.services,
+        raw_signed_transaction_hex: args.raw_signed_transaction_hex,
+        callback: args.callback,
+        status: TransactionStatus::Pending,
+        rebroadcasts: 0,
+        missed_polls: 0,
+        updated_at: timestamp,
+        last_error: None,
+        unpaid_since: None,
+    };
+    if transaction.to_bytes().len() > TRACKED_TRANSACTION_MAX_SIZE as usize {
+        ic_cdk::trap("Transaction is too large to be tracked");
+    }
+    insert_tracked_transaction(transaction);
+    hash
+}
+
+/// Stops tracking a transaction submitted by the caller (or any transaction if `is_manager` is set).
+pub fn do_untrack_transaction(caller: Principal, hash: &str, is_manager: bool) -> bool {
+    match get_tracked_transaction(hash) {
+        Some(transaction) if is_manager || transaction.owner == caller => {
+            TRACKED_TRANSACTIONS.with(|t| t.borrow_mut().remove(&StringStorable(transaction.hash)));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Updates the status of a transaction from the block of its receipt (if any). Returns whether the
+/// transaction missed enough receipts to be looked up in the providers' mempool.
+pub fn apply_transaction_receipt(
+    transaction: &mut TrackedTransaction,
+    receipt_block: Option<u64>,
+    finalized_block: Option<u64>,
+) -> bool {
+    match receipt_block {
+        Some(block) => {
+            transaction.missed_polls = 0;
+            transaction.status = if finalized_block.map_or(false, |finalized| block <= finalized) {
+                TransactionStatus::Finalized(block)
+            } else {
+                TransactionStatus::Included(block)
+            };
+            false
+        }
+        None => {
+            // An included transaction without receipt was reorged out
+            transaction.status = TransactionStatus::Pending;
+            transaction.missed_polls += 1;
+            transaction.missed_polls >= TRANSACTION_REBROADCAST_AFTER_POLLS
+        }
+    }
+}
+
+/// Updates a transaction without receipt depending on whether the providers still know it.
+/// Returns whether the transaction should be sent again.
+pub fn apply_transaction_lookup(transaction: &mut TrackedTransaction, is_known: bool) -> bool {
+    if is_known {
+        // Still waiting in the mempool
+        transaction.missed_polls = 0;
+        return false;
+    }
+    if transaction.rebroadcasts >= TRANSACTION_MAX_REBROADCASTS {
+        transaction.status = TransactionStatus::Dropped;
+        return false;
+    }
+    true
+}
+
+/// Records the result of sending a transaction again. Returns whether the nonce of the transaction
+/// is too low, i.e. either the transaction or another one with the same nonce was included.
+pub fn apply_transaction_rebroadcast(
+    transaction: &mut TrackedTransaction,
+    result: MultiRpcResult<SendRawTransactionStatus>,
+) -> bool {
+    transaction.rebroadcasts += 1;
+    transaction.missed_polls = 0;
+    match result {
+        MultiRpcResult::Consistent(Ok(SendRawTransactionStatus::NonceTooLow)) => true,
+        MultiRpcResult::Consistent(Ok(_)) => false,
+        MultiRpcResult::Consistent(Err(err)) => {
+            transaction.last_error = Some(format!("{:?}", err));
+            false
+        }
+        MultiRpcResult::Inconsistent(_) => {
+            transaction.last_error =
+                Some("Providers returned inconsistent results when sending again".to_string());
+            false
+        }
+    }
+}
+
+/// Updates a transaction whose nonce is too low from its receipt. A transaction without receipt
+/// was replaced by another one.
+pub fn apply_replaced_transaction(
+    transaction: &mut TrackedTransaction,
+    receipt_block: Option<u64>,
+    finalized_block: Option<u64>,
+) {
+    if receipt_block.is_some() {
+        apply_transaction_receipt(transaction, receipt_block, finalized_block);
+    } else {
+        transaction.status = TransactionStatus::Dropped;
+    }
+}
+
+/// Returns the block of a transaction's receipt (if any), along with the finalized block if there
+/// is a receipt.
+async fn get_transaction_receipt_block(
+    client: &CandidRpcClient,
+    chain_id: u64,
+    hash: &str,
+) -> Result<(Option<u64>, Option<u64>), String> {
+    let receipt_block = match client.eth_get_transaction_receipt(hash.to_string()).await {
+        MultiRpcResult::Consistent(Ok(receipt)) => receipt
+            .map(|receipt| block_number_to_u64(receipt.block_number))
+            .transpose()?,
+        MultiRpcResult::Consistent(Err(err)) => return Err(format!("{:?}", err)),
+        MultiRpcResult::Inconsistent(_) => {
+            return Err("Providers returned inconsistent receipts".to_string())
+        }
+    };
+    let finalized_block = match receipt_block {
+        Some(_) => Some(get_head_block_number(client, chain_id, BlockTag::Finalized).await?),
+        None => None,
+    };
+    Ok((receipt_block, finalized_block))
+}
+
+/// Polls the receipt of a transaction on behalf of its submitter and sends the transaction again
+/// if no receipt appears and the providers no longer know the transaction.
+async fn poll_tracked_transaction(
+    mut transaction: TrackedTransaction,
+) -> Result<TrackedTransaction, String> {
+    let chain_id = transaction.services.chain_id();
+    let client = CandidRpcClient::new(transaction.services.clone(), None)
+        .map_err(|err| format!("{:?}", err))?;
+    let (receipt_block, finalized_block) =
+        get_transaction_receipt_block(&client, chain_id, &transaction.hash).await?;
+    transaction.last_error = None;
+    if !apply_transaction_receipt(&mut transaction, receipt_block, finalized_block) {
+        return Ok(transaction);
+    }
+    let is_known = match client
+        .eth_get_transaction_by_hash(transaction.hash.clone())
+        .await
+    {
+        MultiRpcResult::Consistent(Ok(is_known)) => is_known,
+        MultiRpcResult::Consistent(Err(err)) => return Err(format!("{:?}", err)),
+        MultiRpcResult::Inconsistent(_) => {
+            return Err("Providers returned inconsistent transactions".to_string())
+        }
+    };
+    if !apply_transaction_lookup(&mut transaction, is_known) {
+        return Ok(transaction);
+    }
+    let result = client
+        .eth_send_raw_transaction(transaction.raw_signed_transaction_hex.clone())
+        .await;
+    if apply_transaction_rebroadcast(&mut transaction, result) {
+        // The transaction may have been included since its receipt was polled
+        let (receipt_block, finalized_block) =
+            get_transaction_receipt_block(&client, chain_id, &transaction.hash).await?;
+        apply_replaced_transaction(&mut transaction, receipt_block, finalized_block);
+    }
+    Ok(transaction)
+}
+
+/// Pauses the polls of a transaction whose submitter cannot pay for them, and stops tracking it once
+/// it has been unpaid for too long.
+fn pause_tracked_transaction(mut transaction: TrackedTransaction, unpaid_since: u64, now: u64) {
+    if is_unpaid_subscription_expired(unpaid_since, now) {
+        log!(
+            INFO,
+            "[{}] Untracking unpaid transaction {}",
+            transaction.owner,
+            transaction.hash
+        );
+        TRACKED_TRANSACTIONS.with(|t| t.borrow_mut().remove(&StringStorable(transaction.hash)));
+    } else if transaction.unpaid_since != Some(unpaid_since) {
+        transaction.unpaid_since = Some(unpaid_since);
+        insert_tracked_transaction(transaction);
+    }
+}
+
+/// Polls all pending transactions, notifying submitters of status changes, and removes final
+/// transactions after `TRACKED_TRANSACTION_RETENTION`. A poll is skipped while the previous one is
+/// still running, and transactions whose submitter cannot pay are paused like subscriptions.
+pub async fn poll_tracked_transactions() {
+    let _guard = match PollGuard::new(PollTask::TrackedTransactions) {
+        Some(guard) => guard,
+        None => return,
+    };
+    let now = ic_cdk::api::time();
+    let transactions: Vec<TrackedTransaction> = TRACKED_TRANSACTIONS.with(|t| {
+        t.borrow()
+            .iter()
+            .map(|(_, transaction)| transaction)
+            .collect()
+    });
+    for transaction in transactions {
+        if is_final_status(&transaction.status) {
+            let retention = TRACKED_TRANSACTION_RETENTION.as_nanos() as u64;
+            if now.saturating_sub(transaction.updated_at) > retention {
+                TRACKED_TRANSACTIONS
+                    .with(|t| t.borrow_mut().remove(&StringStorable(transaction.hash)));
+            }
+            continue;
+        }
+        if let Some(unpaid_since) = get_unpaid_since(
+            &transaction.owner,
+            transaction.services.chain_id(),
+            RpcMethod::EthGetTransactionReceipt.name(),
+            transaction.unpaid_since,
+            now,
+        ) {
+            pause_tracked_transaction(transaction, unpaid_since, now);
+            continue;
+        }
+        let result = with_rpc_caller(
+            transaction.owner,
+            poll_tracked_transaction(transaction.clone()),
+        )
+        .await;
+        // The transaction may have been untracked in the meantime
+        if get_tracked_transaction(&transaction.hash).is_none() {
+            continue;
+        }
+        let mut updated = match result {
+            Ok(updated) => updated,
+            Err(err) => {
+                log!(
+                    INFO,
+                    "Unable to poll transaction {}: {}",
+                    transaction.hash,
+                    err
+                );
+                TrackedTransaction {
+                    last_error: Some(err),
+                    ..transaction.clone()
+                }
+            }
+        };
+        updated.unpaid_since = None;
+        if updated.status == transaction.status {
+            insert_tracked_transaction(updated);
+            continue;
+        }
+        updated.updated_at = now;
+        insert_tracked_transaction(updated.clone());
+        if let Some(callback) = &updated.callback {
+            let update = TransactionStatusUpdate {
+                transaction_hash: updated.hash.clone(),
+                status: updated.status.clone(),
+            };
+            if let Err(code) = ic_cdk::api::call::notify(updated.owner, callback, (update,)) {
+                log!(
+                    INFO,
+                    "Unable to notify {} of transaction {}: {:?}",
+                    updated.owner,
+                    updated.hash,
+                    code
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_transaction_status() {
+    let owner = Principal::from_slice(&[1]);
+    let hash = do_track_transaction(
+        owner,
+        TrackTransactionArgs {
+            services: RpcServices::EthMainnet(None),
+            raw_signed_transaction_hex: "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83".to_string(),
+            callback: None,
+        },
+        0,
+    );
+    assert_eq!(
+        hash,
+        "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+    );
+    assert_eq!(
+        get_transaction_status(&hash.to_uppercase().replace("0X", "0x")),
+        Some(TransactionStatus::Pending)
+    );
+    let mut transaction = get_tracked_transaction(&hash).unwrap();
+
+    // Included, then finalized
+    assert!(!apply_transaction_receipt(
+        &mut transaction,
+        Some(100),
+        Some(99)
+    ));
+    assert_eq!(transaction.status, TransactionStatus::Included(100));
+    assert!(!apply_transaction_receipt(
+        &mut transaction,
+        Some(100),
+        Some(100)
+    ));
+    assert_eq!(transaction.status, TransactionStatus::Finalized(100));
+
+    // Looked up after missing receipts, and kept while in the mempool
+    transaction.status = TransactionStatus::Included(100);
+    for _ in 1..TRANSACTION_REBROADCAST_AFTER_POLLS {
+        assert!(!apply_transaction_receipt(&mut transaction, None, None));
+        assert_eq!(transaction.status, TransactionStatus::Pending);
+    }
+    assert!(apply_transaction_receipt(&mut transaction, None, None));
+    assert!(!apply_transaction_lookup(&mut transaction, true));
+    assert_eq!(transaction.missed_polls, 0);
+    assert_eq!(transaction.status, TransactionStatus::Pending);
+
+    // Sent again once unknown to the providers, until dropped
+    transaction.missed_polls = TRANSACTION_REBROADCAST_AFTER_POLLS;
+    assert!(apply_transaction_lookup(&mut transaction, false));
+    assert!(!apply_transaction_rebroadcast(
+        &mut transaction,
+        MultiRpcResult::Consistent(Ok(SendRawTransactionStatus::Ok(None))),
+    ));
+    assert_eq!(transaction.rebroadcasts, 1);
+    assert_eq!(transaction.missed_polls, 0);
+    transaction.rebroadcasts = TRANSACTION_MAX_REBROADCASTS;
+    assert!(!apply_transaction_lookup(&mut transaction, true));
+    assert_eq!(transaction.status, TransactionStatus::Pending);
+    assert!(!apply_transaction_lookup(&mut transaction, false));
+    assert_eq!(transaction.status, TransactionStatus::Dropped);
+
+    // A nonce too low means the transaction was either included or replaced
+    transaction.status = TransactionStatus::Pending;
+    assert!(apply_transaction_rebroadcast(
+        &mut transaction,
+        MultiRpcResult::Consistent(Ok(SendRawTransactionStatus::NonceTooLow)),
+    ));
+    assert_eq!(transaction.status, TransactionStatus::Pending);
+    apply_replaced_transaction(&mut transaction, Some(101), Some(100));
+    assert_eq!(transaction.status, TransactionStatus::Included(101));
+    apply_replaced_transaction(&mut transaction, None, None);
+    assert_eq!(transaction.status, TransactionStatus::Dropped);
+
+    assert!(!do_untrack_transaction(
+        Principal::from_slice(&[2]),
+        &hash,
+        false
+    ));
+    assert!(do_untrack_transaction(owner, &hash, false));
+    assert_eq!(get_transaction_status(&hash), None);
+}
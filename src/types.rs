@@ -1,5 +1,5 @@
 use candid::{CandidType, Decode, Deserialize, Encode, Principal};
-use cketh_common::eth_rpc::RpcError;
+use cketh_common::eth_rpc::{Block, LogEntry, RpcError};
 use cketh_common::eth_rpc_client::providers::{
     EthMainnetService, EthSepoliaService, RpcApi, RpcService,
 };
@@ -11,9 +11,16 @@ use serde::Serialize;
 use std::borrow::Cow;
 use std::collections::HashMap;
 
-use crate::constants::STRING_STORABLE_MAX_SIZE;
+use crate::constants::{
+    METRICS_CANDID_ENCODING_VERSION, METRICS_ENCODING_VERSION, STRING_STORABLE_MAX_SIZE,
+};
 use crate::{
-    AUTH_SET_STORABLE_MAX_SIZE, DEFAULT_OPEN_RPC_ACCESS, PROVIDER_MAX_SIZE, RPC_SERVICE_MAX_SIZE,
+    ADDRESS_NONCES_MAX_SIZE, AUTH_GRANTS_STORABLE_MAX_SIZE, AUTH_SET_STORABLE_MAX_SIZE,
+    CHAIN_HEAD_MAX_SIZE, DEFAULT_OPEN_RPC_ACCESS, ETH_MAINNET_CHAIN_ID, ETH_SEPOLIA_CHAIN_ID,
+    LOG_CURSOR_ENTRY_MAX_SIZE, LOG_CURSOR_KEY_SIZE, NONCE_KEY_SIZE, PROVIDER_MAX_SIZE,
+    QUOTA_MAX_SIZE, QUOTA_TARGET_MAX_SIZE, RESPONSE_CACHE_ENTRY_MAX_SIZE, RESPONSE_CACHE_KEY_SIZE,
+    RPC_SERVICES_MAX_SIZE, RPC_SERVICE_MAX_SIZE, SUBSCRIPTION_MAX_SIZE,
+    TRACKED_TRANSACTION_MAX_SIZE,
 };
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -79,7 +86,7 @@ impl<A: MetricLabels, B: MetricLabels, C: MetricLabels> MetricLabels for (A, B,
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Serialize, Deserialize)]
 pub struct MetricRpcMethod(pub String);
 
 impl From<RpcMethod> for MetricRpcMethod {
@@ -94,7 +101,7 @@ impl MetricLabels for MetricRpcMethod {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Serialize, Deserialize)]
 pub struct MetricRpcHost(pub String);
 
 impl<'a> From<&'a str> for MetricRpcHost {
@@ -109,7 +116,7 @@ impl MetricLabels for MetricRpcHost {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Serialize, Deserialize)]
 pub struct MetricHttpStatusCode(pub String);
 
 impl From<u32> for MetricHttpStatusCode {
@@ -124,7 +131,117 @@ impl MetricLabels for MetricHttpStatusCode {
     }
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Serialize, Deserialize)]
+pub struct MetricJsonRpcErrorCode(pub String);
+
+impl From<i64> for MetricJsonRpcErrorCode {
+    fn from(value: i64) -> Self {
+        MetricJsonRpcErrorCode(value.to_string())
+    }
+}
+
+impl MetricLabels for MetricJsonRpcErrorCode {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![("code", &self.0)]
+    }
+}
+
+/// Kind of an `RpcError`, such as `ProviderError::TooFewCycles`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Serialize, Deserialize)]
+pub struct MetricRpcError(pub String);
+
+impl<'a> From<&'a str> for MetricRpcError {
+    fn from(kind: &str) -> Self {
+        MetricRpcError(kind.to_string())
+    }
+}
+
+impl<'a> From<&'a RpcError> for MetricRpcError {
+    fn from(error: &RpcError) -> Self {
+        use cketh_common::eth_rpc::{HttpOutcallError, ProviderError, ValidationError};
+
+        // Use the names of the error type and variant, omitting any details
+        let kind = match error {
+            RpcError::JsonRpcError(_) => "JsonRpcError",
+            RpcError::ProviderError(error) => match error {
+                ProviderError::NoPermission => "ProviderError::NoPermission",
+                ProviderError::TooFewCycles { .. } => "ProviderError::TooFewCycles",
+                ProviderError::ProviderNotFound => "ProviderError::ProviderNotFound",
+                ProviderError::MissingRequiredProvider => "ProviderError::MissingRequiredProvider",
+            },
+            RpcError::HttpOutcallError(error) => match error {
+                HttpOutcallError::IcError { .. } => "HttpOutcallError::IcError",
+                HttpOutcallError::InvalidHttpJsonRpcResponse { .. } => {
+                    "HttpOutcallError::InvalidHttpJsonRpcResponse"
+                }
+            },
+            RpcError::ValidationError(error) => match error {
+                ValidationError::Custom(_) => "ValidationError::Custom",
+                ValidationError::HostNotAllowed(_) => "ValidationError::HostNotAllowed",
+                ValidationError::UrlParseError(_) => "ValidationError::UrlParseError",
+                ValidationError::InvalidHex(_) => "ValidationError::InvalidHex",
+                ValidationError::CredentialPathNotAllowed => {
+                    "ValidationError::CredentialPathNotAllowed"
+                }
+                ValidationError::CredentialHeaderNotAllowed => {
+                    "ValidationError::CredentialHeaderNotAllowed"
+                }
+            },
+        };
+        kind.into()
+    }
+}
+
+impl MetricLabels for MetricRpcError {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![("error", &self.0)]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+pub struct MetricCaller(pub String);
+
+impl MetricLabels for MetricCaller {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![("caller", &self.0)]
+    }
+}
+
+/// Histogram with fixed bucket bounds. The last bucket (`u64::MAX`) counts all values above the other bounds.
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct MetricHistogram {
+    /// Inclusive upper bound and (non-cumulative) number of observations for each bucket.
+    pub buckets: Vec<(u64, u64)>,
+    pub sum: u64,
+}
+
+impl MetricHistogram {
+    pub fn new(bounds: &[u64]) -> Self {
+        Self {
+            buckets: bounds
+                .iter()
+                .chain(std::iter::once(&u64::MAX))
+                .map(|bound| (*bound, 0))
+                .collect(),
+            sum: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: u64) {
+        if let Some((_, count)) = self.buckets.iter_mut().find(|(bound, _)| value <= *bound) {
+            *count += 1;
+        }
+        self.sum = self.sum.saturating_add(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|(_, count)| count).sum()
+    }
+}
+
+// Missing fields default to empty, so that metrics saved by an older version can be restored
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Metrics {
     pub requests: HashMap<(MetricRpcMethod, MetricRpcHost), u64>,
     pub responses: HashMap<(MetricRpcMethod, MetricRpcHost, MetricHttpStatusCode), u64>,
@@ -140,24 +257,86 @@ pub struct Metrics {
     pub err_http_outcall: HashMap<(MetricRpcMethod, MetricRpcHost), u64>,
     #[serde(rename = "errHostNotAllowed")]
     pub err_host_not_allowed: HashMap<MetricRpcHost, u64>,
+    #[serde(rename = "errRateLimited")]
+    pub err_rate_limited: u64,
+    /// JSON-RPC error codes in provider responses.
+    #[serde(rename = "errJsonRpc")]
+    pub err_json_rpc: HashMap<(MetricRpcMethod, MetricRpcHost, MetricJsonRpcErrorCode), u64>,
+    /// Errors returned to callers.
+    #[serde(rename = "errRpc")]
+    pub err_rpc: HashMap<(MetricRpcMethod, MetricRpcError), u64>,
+    /// Duration of HTTP outcalls in milliseconds.
+    #[serde(rename = "responseLatencies")]
+    pub response_latencies: HashMap<(MetricRpcMethod, MetricRpcHost), MetricHistogram>,
+    /// Size of HTTP outcall response bodies in bytes.
+    #[serde(rename = "responseSizes")]
+    pub response_sizes: HashMap<(MetricRpcMethod, MetricRpcHost), MetricHistogram>,
+    #[serde(rename = "cacheHits")]
+    pub cache_hits: HashMap<MetricRpcMethod, u64>,
+    #[serde(rename = "cacheMisses")]
+    pub cache_misses: HashMap<MetricRpcMethod, u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Deserialize)]
+pub struct CallerUsage {
+    pub requests: u64,
+    #[serde(rename = "cyclesCharged")]
+    pub cycles_charged: u128,
+    pub errors: u64,
+}
+
+/// Metrics saved in stable memory during upgrades. The encoding starts with a version byte.
+/// Metrics are encoded as CBOR, which ignores unknown fields and (with `#[serde(default)]`)
+/// fills in missing ones, so that adding or removing a metric keeps the saved values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorableMetrics(pub Option<Metrics>);
+
+impl Storable for StorableMetrics {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        match &self.0 {
+            Some(metrics) => {
+                let mut bytes = vec![METRICS_ENCODING_VERSION];
+                bytes.extend(serde_cbor::to_vec(metrics).expect("Unable to encode metrics"));
+                Cow::Owned(bytes)
+            }
+            None => Cow::Borrowed(&[]),
+        }
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        match bytes.split_first() {
+            Some((&METRICS_ENCODING_VERSION, cbor)) => Self(serde_cbor::from_slice(cbor).ok()),
+            // Candid encoding used before switching to CBOR
+            Some((&METRICS_CANDID_ENCODING_VERSION, candid)) => Self(Decode!(candid, Metrics).ok()),
+            _ => Self(None),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RpcMethod {
+    EthBlobBaseFee,
+    EthCall,
     EthFeeHistory,
     EthGetLogs,
     EthGetBlockByNumber,
+    EthGetCode,
+    EthGetTransactionByHash,
     EthGetTransactionCount,
     EthGetTransactionReceipt,
     EthSendRawTransaction,
 }
 
 impl RpcMethod {
-    fn name(self) -> &'static str {
+    pub fn name(self) -> &'static str {
         match self {
+            RpcMethod::EthBlobBaseFee => "eth_blobBaseFee",
+            RpcMethod::EthCall => "eth_call",
             RpcMethod::EthFeeHistory => "eth_feeHistory",
             RpcMethod::EthGetLogs => "eth_getLogs",
             RpcMethod::EthGetBlockByNumber => "eth_getBlockByNumber",
+            RpcMethod::EthGetCode => "eth_getCode",
+            RpcMethod::EthGetTransactionByHash => "eth_getTransactionByHash",
             RpcMethod::EthGetTransactionCount => "eth_getTransactionCount",
             RpcMethod::EthGetTransactionReceipt => "eth_getTransactionReceipt",
             RpcMethod::EthSendRawTransaction => "eth_sendRawTransaction",
@@ -165,7 +344,7 @@ impl RpcMethod {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, CandidType, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
 pub enum Auth {
     Manage,
     RegisterProvider,
@@ -229,10 +408,110 @@ impl BoundedStorable for AuthSet {
     const IS_FIXED_SIZE: bool = false;
 }
 
+/// Restricts an authorization to a subset of chains, RPC methods and providers.
+/// Each `None` field leaves the corresponding dimension unrestricted.
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct AuthScope {
+    #[serde(rename = "chainIds")]
+    pub chain_ids: Option<Vec<u64>>,
+    pub methods: Option<Vec<String>>,
+    #[serde(rename = "providerIds")]
+    pub provider_ids: Option<Vec<u64>>,
+}
+
+impl AuthScope {
+    pub fn allows(&self, context: &AuthContext) -> bool {
+        fn allows_value<T: PartialEq>(allowed: &Option<Vec<T>>, value: Option<&T>) -> bool {
+            match (allowed, value) {
+                (None, _) => true,
+                (Some(allowed), Some(value)) => allowed.contains(value),
+                (Some(_), None) => false,
+            }
+        }
+        allows_value(&self.chain_ids, context.chain_id.as_ref())
+            && allows_value(&self.methods, Some(&context.method.to_string()))
+            && allows_value(&self.provider_ids, context.provider_id.as_ref())
+    }
+}
+
+/// Describes an outgoing RPC request for checking an `AuthScope`.
+pub struct AuthContext<'a> {
+    pub chain_id: Option<u64>,
+    pub method: &'a str,
+    pub provider_id: Option<u64>,
+}
+
+/// Expiry and scope of an authorization. Permanent, unscoped authorizations have no grant.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct AuthGrant {
+    pub auth: Auth,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<u64>,
+    pub scope: Option<AuthScope>,
+}
+
+impl AuthGrant {
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, CandidType, Serialize, Deserialize, Default)]
+pub struct AuthGrants(pub Vec<AuthGrant>);
+
+impl AuthGrants {
+    pub fn get(&self, auth: Auth) -> Option<&AuthGrant> {
+        self.0.iter().find(|grant| grant.auth == auth)
+    }
+
+    /// Replaces the grant for the given `Auth`. Returns `true` if the grants changed.
+    pub fn set(&mut self, auth: Auth, grant: Option<AuthGrant>) -> bool {
+        let previous = self.get(auth).cloned();
+        if previous == grant {
+            return false;
+        }
+        self.0.retain(|grant| grant.auth != auth);
+        self.0.extend(grant);
+        true
+    }
+}
+
+// Using explicit JSON representation for consistency with `AuthSet`
+impl Storable for AuthGrants {
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Unable to deserialize AuthGrants")
+    }
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(serde_json::to_vec(self).expect("Unable to serialize AuthGrants"))
+    }
+}
+
+impl BoundedStorable for AuthGrants {
+    const MAX_SIZE: u32 = AUTH_GRANTS_STORABLE_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub struct Metadata {
     pub next_provider_id: u64,
     pub open_rpc_access: bool,
+    /// Restricts `Custom` RPC services to allowlisted hosts (`None` for state saved before this option existed).
+    pub host_allowlist_enabled: Option<bool>,
+    /// ID assigned to the next traced RPC request as of the last upgrade (`None` for state saved before request tracing existed).
+    pub next_request_id: Option<u64>,
+    /// Maximum number of cached responses (`None` or zero if the response cache is disabled).
+    pub response_cache_max_entries: Option<u64>,
+    /// Insertion sequence number of the next cached response, used for eviction.
+    pub next_response_cache_sequence: Option<u64>,
+    /// ID assigned to the next subscription.
+    pub next_subscription_id: Option<u64>,
+    /// ID assigned to the next log cursor.
+    pub next_log_cursor_id: Option<u64>,
+    /// Threshold ECDSA key used to sign transactions (`None` if signing is disabled).
+    pub ecdsa_key_name: Option<String>,
+    /// Maximum cycles the HTTP JSON-RPC account may spend on a single HTTP request (`None` if unlimited).
+    pub http_rpc_max_cycles_per_request: Option<u128>,
 }
 
 impl Default for Metadata {
@@ -240,10 +519,674 @@ impl Default for Metadata {
         Self {
             next_provider_id: 0,
             open_rpc_access: DEFAULT_OPEN_RPC_ACCESS,
+            host_allowlist_enabled: None,
+            next_request_id: None,
+            response_cache_max_entries: None,
+            next_response_cache_sequence: None,
+            next_subscription_id: None,
+            next_log_cursor_id: None,
+            ecdsa_key_name: None,
+            http_rpc_max_cycles_per_request: None,
         }
     }
 }
 
+/// Chain ID followed by the SHA-256 hash of the RPC method and its canonical parameters.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ResponseCacheKey(pub [u8; RESPONSE_CACHE_KEY_SIZE as usize]);
+
+impl ResponseCacheKey {
+    pub fn chain_id(&self) -> u64 {
+        u64::from_be_bytes(self.0[..8].try_into().unwrap())
+    }
+}
+
+impl Storable for ResponseCacheKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(
+            bytes
+                .as_ref()
+                .try_into()
+                .expect("Invalid response cache key"),
+        )
+    }
+}
+
+impl BoundedStorable for ResponseCacheKey {
+    const MAX_SIZE: u32 = RESPONSE_CACHE_KEY_SIZE;
+    const IS_FIXED_SIZE: bool = true;
+}
+
+/// Candid-encoded response together with its insertion sequence number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResponseCacheEntry {
+    pub sequence: u64,
+    pub data: Vec<u8>,
+}
+
+impl Storable for ResponseCacheEntry {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.sequence.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.data);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (sequence, data) = bytes.split_at(8);
+        Self {
+            sequence: u64::from_be_bytes(sequence.try_into().unwrap()),
+            data: data.to_vec(),
+        }
+    }
+}
+
+impl BoundedStorable for ResponseCacheEntry {
+    const MAX_SIZE: u32 = 8 + RESPONSE_CACHE_ENTRY_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct ResponseCacheStats {
+    pub entries: u64,
+    #[serde(rename = "maxEntries")]
+    pub max_entries: u64,
+}
+
+/// RPC request recently made by a caller, used to find its outcalls in the trace log.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct RecentRequest {
+    #[serde(rename = "requestId")]
+    pub request_id: u64,
+    pub method: String,
+    pub timestamp: u64,
+}
+
+/// Account which pays for JSON-RPC requests served over HTTP.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct HttpRpcAccount {
+    pub principal: Principal,
+    pub balance: u128,
+    #[serde(rename = "maxCyclesPerRequest")]
+    pub max_cycles_per_request: Option<u128>,
+}
+
+/// Block headers agreed upon by the RPC providers of a tracked chain.
+#[derive(Clone, Debug, Default, PartialEq, CandidType, Deserialize)]
+pub struct ChainHead {
+    pub latest: Option<Block>,
+    pub finalized: Option<Block>,
+    /// Timestamp of the last successful update (in nanoseconds).
+    #[serde(rename = "updatedAt")]
+    pub updated_at: u64,
+}
+
+impl Storable for ChainHead {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("Unable to encode chain head"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Unable to decode chain head")
+    }
+}
+
+impl BoundedStorable for ChainHead {
+    const MAX_SIZE: u32 = CHAIN_HEAD_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct SubscribeLogsArgs {
+    pub services: RpcServices,
+    /// Logs are delivered starting at `fromBlock` (or the current head) up to the `toBlock` tag
+    /// (`Finalized` by default).
+    pub filter: candid_types::GetLogsArgs,
+    /// Method of the subscribing canister which receives `LogDelivery` messages.
+    pub callback: String,
+}
+
+/// Log subscription of a canister, polled by a timer.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct LogSubscription {
+    pub id: u64,
+    pub owner: Principal,
+    pub services: RpcServices,
+    pub addresses: Vec<String>,
+    pub topics: Option<Vec<Vec<String>>>,
+    /// Block tag up to which logs are delivered.
+    pub confirmation: candid_types::BlockTag,
+    pub callback: String,
+    /// First block whose logs have not been acknowledged yet (`None` to start at the head).
+    #[serde(rename = "nextBlock")]
+    pub next_block: Option<u64>,
+    /// Cursor of the last delivery which has not been acknowledged yet.
+    pub delivered: Option<u64>,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    /// Time since which the owner cannot pay for polls, which are paused in the meantime.
+    #[serde(rename = "unpaidSince")]
+    pub unpaid_since: Option<u64>,
+}
+
+impl Storable for LogSubscription {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("Unable to encode log subscription"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Unable to decode log subscription")
+    }
+}
+
+impl BoundedStorable for LogSubscription {
+    const MAX_SIZE: u32 = SUBSCRIPTION_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+/// Logs sent to a subscriber, which acknowledges them by calling `acknowledgeLogs` with the cursor.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct LogDelivery {
+    #[serde(rename = "subscriptionId")]
+    pub subscription_id: u64,
+    #[serde(rename = "fromBlock")]
+    pub from_block: u64,
+    /// Last block included in this delivery.
+    pub cursor: u64,
+    pub logs: Vec<LogEntry>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct SubscribeBlocksArgs {
+    pub services: RpcServices,
+    /// First block to deliver (the current head if not specified).
+    #[serde(rename = "fromBlock")]
+    pub from_block: Option<u64>,
+    /// Method of the subscribing canister which receives `BlockDelivery` messages.
+    pub callback: String,
+}
+
+/// Block header subscription of a canister, polled by a timer.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct BlockSubscription {
+    pub id: u64,
+    pub owner: Principal,
+    pub services: RpcServices,
+    pub callback: String,
+    /// Next block to deliver (`None` to start at the head).
+    #[serde(rename = "nextBlock")]
+    pub next_block: Option<u64>,
+    /// Numbers and hashes of the most recently delivered blocks, used to detect reorgs.
+    #[serde(rename = "recentBlocks")]
+    pub recent_blocks: Vec<(u64, String)>,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    /// Time since which the owner cannot pay for polls, which are paused in the meantime.
+    #[serde(rename = "unpaidSince")]
+    pub unpaid_since: Option<u64>,
+}
+
+impl Storable for BlockSubscription {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("Unable to encode block subscription"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Unable to decode block subscription")
+    }
+}
+
+impl BoundedStorable for BlockSubscription {
+    const MAX_SIZE: u32 = SUBSCRIPTION_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+/// Previously delivered blocks which are no longer part of the chain.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct BlockReorg {
+    #[serde(rename = "fromBlock")]
+    pub from_block: u64,
+    #[serde(rename = "toBlock")]
+    pub to_block: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, CandidType, Deserialize)]
+pub enum BlockEvent {
+    Block(Block),
+    Reorg(BlockReorg),
+}
+
+/// Block headers and reorgs sent to a subscriber, in chain order.
+#[derive(Clone, Debug, PartialEq, CandidType, Deserialize)]
+pub struct BlockDelivery {
+    #[serde(rename = "subscriptionId")]
+    pub subscription_id: u64,
+    pub events: Vec<BlockEvent>,
+}
+
+/// Stateful `eth_getLogs` filter which remembers the logs it returned to detect reorgs.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct LogCursor {
+    pub id: u64,
+    pub owner: Principal,
+    pub services: RpcServices,
+    pub addresses: Vec<String>,
+    pub topics: Option<Vec<Vec<String>>>,
+    /// Block tag up to which logs are returned.
+    pub confirmation: candid_types::BlockTag,
+    /// First block of the cursor (`None` to start at the head on the first poll).
+    #[serde(rename = "startBlock")]
+    pub start_block: Option<u64>,
+    /// First block which has not been returned yet.
+    #[serde(rename = "nextBlock")]
+    pub next_block: Option<u64>,
+}
+
+impl Storable for LogCursor {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("Unable to encode log cursor"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Unable to decode log cursor")
+    }
+}
+
+impl BoundedStorable for LogCursor {
+    const MAX_SIZE: u32 = SUBSCRIPTION_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+/// Cursor ID, block number and log index of a log returned by a log cursor (big-endian).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LogCursorEntryKey(pub [u8; LOG_CURSOR_KEY_SIZE as usize]);
+
+impl LogCursorEntryKey {
+    pub fn new(cursor_id: u64, block_number: u64, log_index: u64) -> Self {
+        let mut key = [0; LOG_CURSOR_KEY_SIZE as usize];
+        key[..8].copy_from_slice(&cursor_id.to_be_bytes());
+        key[8..16].copy_from_slice(&block_number.to_be_bytes());
+        key[16..].copy_from_slice(&log_index.to_be_bytes());
+        Self(key)
+    }
+
+    pub fn block_number(&self) -> u64 {
+        u64::from_be_bytes(self.0[8..16].try_into().unwrap())
+    }
+}
+
+impl Storable for LogCursorEntryKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(
+            bytes
+                .as_ref()
+                .try_into()
+                .expect("Invalid log cursor entry key"),
+        )
+    }
+}
+
+impl BoundedStorable for LogCursorEntryKey {
+    const MAX_SIZE: u32 = LOG_CURSOR_KEY_SIZE;
+    const IS_FIXED_SIZE: bool = true;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorableLogEntry(pub LogEntry);
+
+impl Storable for StorableLogEntry {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&self.0).expect("Unable to encode log entry"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(Decode!(&bytes, LogEntry).expect("Unable to decode log entry"))
+    }
+}
+
+impl BoundedStorable for StorableLogEntry {
+    const MAX_SIZE: u32 = LOG_CURSOR_ENTRY_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, CandidType, Deserialize)]
+pub enum FeeUrgency {
+    /// Leaves room for the base fee to rise for one full block.
+    Low,
+    /// Leaves room for the base fee to double (about 6 full blocks).
+    #[default]
+    Medium,
+    /// Leaves room for the base fee to quadruple (about 12 full blocks).
+    High,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize)]
+pub struct EstimateFeesArgs {
+    /// Percentile of the priority fees paid in each recent block (50 by default).
+    pub percentile: Option<u8>,
+    /// Number of recent blocks (5 by default).
+    #[serde(rename = "blockCount")]
+    pub block_count: Option<u64>,
+    pub urgency: Option<FeeUrgency>,
+    /// Whether to suggest blob fees (by default only if the providers support `eth_blobBaseFee`).
+    #[serde(rename = "blobFees")]
+    pub blob_fees: Option<bool>,
+}
+
+/// Suggested EIP-1559 fees (and EIP-4844 blob fees if the chain supports them), in wei per gas.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct FeeEstimate {
+    /// Base fee of the next block.
+    #[serde(rename = "baseFeePerGas")]
+    pub base_fee_per_gas: candid::Nat,
+    #[serde(rename = "maxFeePerGas")]
+    pub max_fee_per_gas: candid::Nat,
+    #[serde(rename = "maxPriorityFeePerGas")]
+    pub max_priority_fee_per_gas: candid::Nat,
+    #[serde(rename = "baseFeePerBlobGas")]
+    pub base_fee_per_blob_gas: Option<candid::Nat>,
+    #[serde(rename = "maxFeePerBlobGas")]
+    pub max_fee_per_blob_gas: Option<candid::Nat>,
+}
+
+/// ERC-20 token contract and block of a read-only token call (the latest block by default).
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct Erc20CallArgs {
+    pub token: String,
+    pub block: Option<candid_types::BlockTag>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct Erc20BalanceOfArgs {
+    pub token: String,
+    pub owner: String,
+    pub block: Option<candid_types::BlockTag>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct Erc20AllowanceArgs {
+    pub token: String,
+    pub owner: String,
+    pub spender: String,
+    pub block: Option<candid_types::BlockTag>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub enum Erc20EventKind {
+    Transfer {
+        from: String,
+        to: String,
+        value: candid::Nat,
+    },
+    Approval {
+        owner: String,
+        spender: String,
+        value: candid::Nat,
+    },
+}
+
+/// ERC-20 event decoded from a log entry.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct Erc20Event {
+    /// Address of the token contract which emitted the event.
+    pub token: String,
+    pub event: Erc20EventKind,
+    #[serde(rename = "blockNumber")]
+    pub block_number: Option<candid::Nat>,
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: Option<String>,
+    #[serde(rename = "logIndex")]
+    pub log_index: Option<candid::Nat>,
+    pub removed: bool,
+}
+
+/// Solidity ABI value. Addresses are hex strings, integers are checked against the size of their
+/// type, and arrays and tuples contain nested values.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub enum AbiValue {
+    Address(String),
+    Uint(candid::Nat),
+    Int(candid::Int),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    FixedBytes(Vec<u8>),
+    String(String),
+    Array(Vec<AbiValue>),
+    Tuple(Vec<AbiValue>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct CallAbiArgs {
+    /// Address of the contract.
+    pub to: String,
+    /// Human-readable function signature, e.g.
+    /// `function getReserves() view returns (uint112,uint112,uint32)`.
+    pub signature: String,
+    pub args: Vec<AbiValue>,
+    pub block: Option<candid_types::BlockTag>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct AbiParam {
+    pub name: String,
+    pub value: AbiValue,
+}
+
+/// Event decoded from a log entry according to an event signature.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct DecodedLog {
+    /// Address of the contract which emitted the event.
+    pub address: String,
+    pub params: Vec<AbiParam>,
+    #[serde(rename = "blockNumber")]
+    pub block_number: Option<candid::Nat>,
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: Option<String>,
+    #[serde(rename = "logIndex")]
+    pub log_index: Option<candid::Nat>,
+    pub removed: bool,
+}
+
+/// Transaction request signed with the caller's threshold ECDSA key. The nonce is filled in, and
+/// the fees are estimated unless both are given.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct SendTransactionArgs {
+    pub services: RpcServices,
+    /// Recipient (`None` to deploy a contract).
+    pub to: Option<String>,
+    pub value: candid::Nat,
+    pub data: Option<String>,
+    pub gas: candid::Nat,
+    #[serde(rename = "maxFeePerGas")]
+    pub max_fee_per_gas: Option<candid::Nat>,
+    #[serde(rename = "maxPriorityFeePerGas")]
+    pub max_priority_fee_per_gas: Option<candid::Nat>,
+}
+
+/// Fields of a raw signed transaction decoded before it is sent.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct DecodedTransaction {
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: String,
+    /// EIP-2718 transaction type (0 for legacy transactions).
+    #[serde(rename = "transactionType")]
+    pub transaction_type: u8,
+    /// Signed chain ID (`None` for legacy transactions without EIP-155 replay protection, which are
+    /// valid on any chain).
+    #[serde(rename = "chainId")]
+    pub chain_id: Option<u64>,
+    /// Address recovered from the signature.
+    pub from: String,
+    pub nonce: u64,
+}
+
+/// Reason why a raw signed transaction is rejected before it is sent.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub enum TransactionError {
+    InvalidHex(String),
+    Empty,
+    UnsupportedType(u8),
+    InvalidEncoding(String),
+    InvalidSignature(String),
+    /// Name of a field which does not fit into 64 bits.
+    ValueTooLarge(String),
+    ChainIdMismatch {
+        expected: u64,
+        actual: u64,
+    },
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct TrackTransactionArgs {
+    pub services: RpcServices,
+    #[serde(rename = "rawSignedTransactionHex")]
+    pub raw_signed_transaction_hex: String,
+    /// Method of the submitting canister which receives `TransactionStatusUpdate` messages.
+    pub callback: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub enum TransactionStatus {
+    Pending,
+    /// Included in the given block, which is not finalized yet.
+    Included(u64),
+    Finalized(u64),
+    /// No longer expected to be included (e.g. replaced by another transaction with the same nonce).
+    Dropped,
+}
+
+/// Transaction whose receipt is polled by a timer.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct TrackedTransaction {
+    pub hash: String,
+    pub owner: Principal,
+    pub services: RpcServices,
+    #[serde(rename = "rawSignedTransactionHex")]
+    pub raw_signed_transaction_hex: String,
+    pub callback: Option<String>,
+    pub status: TransactionStatus,
+    /// Number of times the transaction was sent again.
+    pub rebroadcasts: u32,
+    /// Number of consecutive polls without a receipt.
+    #[serde(rename = "missedPolls")]
+    pub missed_polls: u32,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: u64,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    /// Time since which the owner cannot pay for polls, which are paused in the meantime.
+    #[serde(rename = "unpaidSince")]
+    pub unpaid_since: Option<u64>,
+}
+
+impl Storable for TrackedTransaction {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("Unable to encode tracked transaction"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Unable to decode tracked transaction")
+    }
+}
+
+impl BoundedStorable for TrackedTransaction {
+    const MAX_SIZE: u32 = TRACKED_TRANSACTION_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+/// Chain ID and address of a sending account (big-endian).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NonceKey(pub [u8; NONCE_KEY_SIZE as usize]);
+
+impl NonceKey {
+    pub fn new(chain_id: u64, address: &[u8; 20]) -> Self {
+        let mut key = [0; NONCE_KEY_SIZE as usize];
+        key[..8].copy_from_slice(&chain_id.to_be_bytes());
+        key[8..].copy_from_slice(address);
+        Self(key)
+    }
+}
+
+impl Storable for NonceKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(bytes.as_ref().try_into().expect("Invalid nonce key"))
+    }
+}
+
+impl BoundedStorable for NonceKey {
+    const MAX_SIZE: u32 = NONCE_KEY_SIZE;
+    const IS_FIXED_SIZE: bool = true;
+}
+
+/// Nonce reserved for a transaction which is being signed or was sent.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct NonceReservation {
+    pub nonce: u64,
+    pub sent: bool,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: u64,
+}
+
+/// Nonces of an address signing with a threshold ECDSA key.
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Deserialize)]
+pub struct AddressNonces {
+    /// Nonce after the highest reserved one.
+    #[serde(rename = "nextNonce")]
+    pub next_nonce: u64,
+    /// Nonces below `next_nonce` which are not used by any pending transaction, e.g. after a
+    /// failed send. They are reserved before `next_nonce`.
+    pub gaps: Vec<u64>,
+    #[serde(rename = "inFlight")]
+    pub in_flight: Vec<NonceReservation>,
+}
+
+impl Storable for AddressNonces {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("Unable to encode address nonces"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("Unable to decode address nonces")
+    }
+}
+
+impl BoundedStorable for AddressNonces {
+    const MAX_SIZE: u32 = ADDRESS_NONCES_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+/// Sent to the submitter of a tracked transaction whenever its status changes.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct TransactionStatusUpdate {
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: String,
+    pub status: TransactionStatus,
+}
+
+/// Administrative action recorded in the audit log.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct AuditLogEntry {
+    pub timestamp: u64,
+    pub caller: Principal,
+    pub action: String,
+    pub target: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl Storable for AuditLogEntry {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap()
+    }
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct StringStorable(pub String);
 
@@ -281,6 +1224,29 @@ impl BoundedStorable for PrincipalStorable {
     const IS_FIXED_SIZE: bool = false;
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CyclesStorable(pub u128);
+
+impl Storable for CyclesStorable {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(self.0.to_le_bytes().to_vec())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(u128::from_le_bytes(
+            bytes
+                .as_ref()
+                .try_into()
+                .expect("Unable to deserialize CyclesStorable"),
+        ))
+    }
+}
+
+impl BoundedStorable for CyclesStorable {
+    const MAX_SIZE: u32 = 16;
+    const IS_FIXED_SIZE: bool = true;
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, CandidType, Deserialize)]
 pub struct ProviderView {
     #[serde(rename = "providerId")]
@@ -441,6 +1407,94 @@ impl BoundedStorable for StorableRpcService {
     const IS_FIXED_SIZE: bool = false;
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum QuotaTarget {
+    Principal(Principal),
+    Role(Auth),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct StorableQuotaTarget(Vec<u8>);
+
+impl TryFrom<StorableQuotaTarget> for QuotaTarget {
+    type Error = serde_json::Error;
+    fn try_from(value: StorableQuotaTarget) -> Result<Self, Self::Error> {
+        serde_json::from_slice(&value.0)
+    }
+}
+
+impl StorableQuotaTarget {
+    pub fn new(target: &QuotaTarget) -> Self {
+        // Store as JSON string for the same reasons as `StorableRpcService`
+        Self(
+            serde_json::to_vec(target)
+                .expect("BUG: unexpected error while serializing QuotaTarget"),
+        )
+    }
+}
+
+impl Storable for StorableQuotaTarget {
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        StorableQuotaTarget(bytes.to_vec())
+    }
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(self.0.to_owned())
+    }
+}
+
+impl BoundedStorable for StorableQuotaTarget {
+    const MAX_SIZE: u32 = QUOTA_TARGET_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Deserialize)]
+pub struct Quota {
+    #[serde(rename = "requestsPerMinute")]
+    pub requests_per_minute: Option<u64>,
+    #[serde(rename = "cyclesPerDay")]
+    pub cycles_per_day: Option<u128>,
+}
+
+impl Storable for Quota {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).unwrap()
+    }
+}
+
+impl BoundedStorable for Quota {
+    const MAX_SIZE: u32 = QUOTA_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+/// Usage of a principal within the current quota windows.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub minute_start: u64,
+    pub requests: u64,
+    pub day_start: u64,
+    pub cycles: u128,
+    /// ID of the last request recorded, whose further outcalls are not counted again.
+    pub last_request_id: Option<u64>,
+}
+
+/// Quota which applies to a principal together with its usage in the current quota windows.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct QuotaStatus {
+    pub quota: Quota,
+    #[serde(rename = "requestsThisMinute")]
+    pub requests_this_minute: u64,
+    #[serde(rename = "cyclesToday")]
+    pub cycles_today: u128,
+    /// Time (in nanoseconds since the epoch) after which requests are accepted again, if the
+    /// quota is exceeded.
+    #[serde(rename = "retryAfter")]
+    pub retry_after: Option<u64>,
+}
+
 pub type RpcResult<T> = Result<T, RpcError>;
 
 #[derive(Clone, Debug, Eq, PartialEq, CandidType, Deserialize)]
@@ -470,6 +1524,18 @@ impl<T> MultiRpcResult<T> {
         }
     }
 
+    pub fn and_then<R>(self, mut f: impl FnMut(T) -> RpcResult<R>) -> MultiRpcResult<R> {
+        match self {
+            MultiRpcResult::Consistent(result) => MultiRpcResult::Consistent(result.and_then(f)),
+            MultiRpcResult::Inconsistent(results) => MultiRpcResult::Inconsistent(
+                results
+                    .into_iter()
+                    .map(|(service, result)| (service, result.and_then(&mut f)))
+                    .collect(),
+            ),
+        }
+    }
+
     pub fn consistent(self) -> Option<RpcResult<T>> {
         match self {
             MultiRpcResult::Consistent(result) => Some(result),
@@ -499,7 +1565,7 @@ impl<T> From<RpcResult<T>> for MultiRpcResult<T> {
     }
 }
 
-#[derive(Clone, CandidType, Deserialize)]
+#[derive(Clone, Debug, CandidType, Deserialize)]
 pub enum RpcServices {
     EthMainnet(Option<Vec<EthMainnetService>>),
     EthSepolia(Option<Vec<EthSepoliaService>>),
@@ -510,6 +1576,34 @@ pub enum RpcServices {
     },
 }
 
+impl RpcServices {
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            RpcServices::EthMainnet(_) => ETH_MAINNET_CHAIN_ID,
+            RpcServices::EthSepolia(_) => ETH_SEPOLIA_CHAIN_ID,
+            RpcServices::Custom { chain_id, .. } => *chain_id,
+        }
+    }
+}
+
+/// Default `RpcServices` used for a chain when serving JSON-RPC requests over HTTP.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct StorableRpcServices(pub RpcServices);
+
+impl Storable for StorableRpcServices {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&self.0).unwrap())
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(Decode!(&bytes, RpcServices).unwrap())
+    }
+}
+
+impl BoundedStorable for StorableRpcServices {
+    const MAX_SIZE: u32 = RPC_SERVICES_MAX_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
 pub mod candid_types {
     use std::str::FromStr;
 
@@ -674,6 +1768,12 @@ pub mod candid_types {
         }
     }
 
+    #[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+    pub struct GetCodeArgs {
+        pub address: String,
+        pub block: BlockTag,
+    }
+
     #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
     pub enum SendRawTransactionStatus {
         Ok(Option<Hash>),
@@ -742,3 +1842,109 @@ fn test_multi_rpc_result_map() {
         ])
     );
 }
+
+#[test]
+fn test_metric_histogram() {
+    let mut histogram = MetricHistogram::new(&[10, 100]);
+    assert_eq!(histogram.count(), 0);
+    histogram.observe(0);
+    histogram.observe(10);
+    histogram.observe(11);
+    histogram.observe(1000);
+    assert_eq!(histogram.buckets, vec![(10, 2), (100, 1), (u64::MAX, 1)]);
+    assert_eq!(histogram.sum, 1021);
+    assert_eq!(histogram.count(), 4);
+}
+
+#[test]
+fn test_metric_rpc_error() {
+    use cketh_common::eth_rpc::{HttpOutcallError, JsonRpcError, ProviderError, ValidationError};
+
+    assert_eq!(
+        MetricRpcError::from(&RpcError::ProviderError(ProviderError::TooFewCycles {
+            expected: 10,
+            received: 5,
+        })),
+        "ProviderError::TooFewCycles".into()
+    );
+    assert_eq!(
+        MetricRpcError::from(&RpcError::ValidationError(ValidationError::InvalidHex(
+            "0xz".to_string()
+        ))),
+        "ValidationError::InvalidHex".into()
+    );
+    assert_eq!(
+        MetricRpcError::from(&RpcError::ValidationError(
+            ValidationError::CredentialPathNotAllowed
+        )),
+        "ValidationError::CredentialPathNotAllowed".into()
+    );
+    assert_eq!(
+        MetricRpcError::from(&RpcError::HttpOutcallError(
+            HttpOutcallError::InvalidHttpJsonRpcResponse {
+                status: 500,
+                body: "::".to_string(),
+                parsing_error: None,
+            }
+        )),
+        "HttpOutcallError::InvalidHttpJsonRpcResponse".into()
+    );
+    assert_eq!(
+        MetricRpcError::from(&RpcError::JsonRpcError(JsonRpcError {
+            code: -32000,
+            message: "error".to_string(),
+        })),
+        "JsonRpcError".into()
+    );
+}
+
+#[test]
+fn test_storable_metrics() {
+    let metrics = Metrics {
+        cycles_withdrawn: 100,
+        requests: maplit::hashmap! {
+            (RpcMethod::EthGetLogs.into(), "example.com".into()) => 5,
+        },
+        ..Default::default()
+    };
+    let bytes = StorableMetrics(Some(metrics.clone())).to_bytes();
+    assert_eq!(bytes[0], METRICS_ENCODING_VERSION);
+    assert_eq!(
+        StorableMetrics::from_bytes(bytes),
+        StorableMetrics(Some(metrics.clone()))
+    );
+    assert_eq!(
+        StorableMetrics::from_bytes(StorableMetrics(None).to_bytes()),
+        StorableMetrics(None)
+    );
+    // Unknown encoding versions are ignored
+    assert_eq!(
+        StorableMetrics::from_bytes(Cow::Borrowed(&[u8::MAX, 0, 1])),
+        StorableMetrics(None)
+    );
+
+    // Metrics saved with the previous Candid encoding are restored
+    let mut bytes = vec![METRICS_CANDID_ENCODING_VERSION];
+    bytes.extend(Encode!(&metrics).unwrap());
+    assert_eq!(
+        StorableMetrics::from_bytes(Cow::Owned(bytes)),
+        StorableMetrics(Some(metrics))
+    );
+
+    // Added metrics start empty and removed metrics are ignored
+    let mut bytes = vec![METRICS_ENCODING_VERSION];
+    bytes.extend(
+        serde_cbor::to_vec(&maplit::btreemap! {
+            "cyclesWithdrawn" => 100_u64,
+            "removedMetric" => 1,
+        })
+        .unwrap(),
+    );
+    assert_eq!(
+        StorableMetrics::from_bytes(Cow::Owned(bytes)),
+        StorableMetrics(Some(Metrics {
+            cycles_withdrawn: 100,
+            ..Default::default()
+        }))
+    );
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use candid::Principal;
+
+use crate::*;
+
+/// Usage statistics for a bounded number of callers. When full, the least recently active
+/// caller is evicted to make room for a new one.
+#[derive(Clone, Debug)]
+pub struct CallerUsageMap {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<Principal, (u64, CallerUsage)>,
+}
+
+impl CallerUsageMap {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, caller: &Principal) -> Option<&CallerUsage> {
+        self.entries.get(caller).map(|(_, usage)| usage)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Principal, &CallerUsage)> {
+        self.entries
+            .iter()
+            .map(|(caller, (_, usage))| (caller, usage))
+    }
+
+    pub fn update(&mut self, caller: Principal, f: impl FnOnce(&mut CallerUsage)) {
+        self.clock += 1;
+        if !self.entries.contains_key(&caller) && self.entries.len() >= self.capacity {
+            let least_recent = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (last_used, _))| *last_used)
+                .map(|(caller, _)| *caller);
+            if let Some(least_recent) = least_recent {
+                self.entries.remove(&least_recent);
+            }
+        }
+        let (last_used, usage) = self.entries.entry(caller).or_default();
+        *last_used = self.clock;
+        f(usage);
+    }
+}
+
+impl Default for CallerUsageMap {
+    fn default() -> Self {
+        Self::new(MAX_TRACKED_CALLERS)
+    }
+}
+
+pub fn add_caller_usage(caller: Principal, f: impl FnOnce(&mut CallerUsage)) {
+    UNSTABLE_CALLER_USAGE.with(|u| u.borrow_mut().update(caller, f));
+}
+
+pub fn get_caller_usage(caller: &Principal) -> Option<CallerUsage> {
+    UNSTABLE_CALLER_USAGE.with(|u| u.borrow().get(caller).cloned())
+}
+
+#[test]
+fn test_caller_usage_eviction() {
+    let [a, b, c] = [1, 2, 3].map(|i| Principal::from_slice(&[i]));
+    let mut usage = CallerUsageMap::new(2);
+    usage.update(a, |u| u.requests += 1);
+    usage.update(b, |u| u.requests += 1);
+    usage.update(a, |u| u.cycles_charged += 100);
+    usage.update(c, |u| u.errors += 1);
+    assert_eq!(
+        usage.get(&a),
+        Some(&CallerUsage {
+            requests: 1,
+            cycles_charged: 100,
+            errors: 0,
+        })
+    );
+    assert_eq!(usage.get(&b), None);
+    assert_eq!(
+        usage.get(&c),
+        Some(&CallerUsage {
+            requests: 0,
+            cycles_charged: 0,
+            errors: 1,
+        })
+    );
+}
@@ -12,6 +12,12 @@ pub fn canonicalize_json(text: &[u8]) -> Option<Vec<u8>> {
     serde_json::to_vec(&json).ok()
 }
 
+/// Returns the `error.code` of a JSON-RPC response.
+pub fn get_json_rpc_error_code(text: &[u8]) -> Option<i64> {
+    let json = serde_json::from_slice::<Value>(text).ok()?;
+    json.get("error")?.get("code")?.as_i64()
+}
+
 #[test]
 fn test_hex_to_bytes() {
     assert_eq!(hex_to_bytes("aa"), None);
@@ -27,3 +33,22 @@ fn test_canonicalize_json() {
         canonicalize_json(r#"{"B":2,"A":1}"#.as_bytes()).unwrap()
     );
 }
+
+#[test]
+fn test_get_json_rpc_error_code() {
+    assert_eq!(
+        get_json_rpc_error_code(
+            r#"{"jsonrpc":"2.0","id":0,"error":{"code":-32005,"message":"Limit exceeded"}}"#
+                .as_bytes()
+        ),
+        Some(-32005)
+    );
+    assert_eq!(
+        get_json_rpc_error_code(r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#.as_bytes()),
+        None
+    );
+    assert_eq!(
+        get_json_rpc_error_code("Service unavailable".as_bytes()),
+        None
+    );
+}
@@ -9,7 +9,7 @@ use cketh_common::{
     checked_amount::CheckedAmountOf,
     eth_rpc::{
         Block, Data, FeeHistory, FixedSizeData, Hash, HttpOutcallError, JsonRpcError, LogEntry,
-        ProviderError, RpcError,
+        ProviderError, RpcError, ValidationError,
     },
     eth_rpc_client::{
         providers::{EthMainnetService, EthSepoliaService, RpcApi, RpcService},
@@ -18,6 +18,7 @@ use cketh_common::{
     numeric::{BlockNumber, Wei},
 };
 use ic_base_types::{CanisterId, PrincipalId};
+use ic_canisters_http_types::HttpRequest as AssetHttpRequest;
 use ic_cdk::api::management_canister::http_request::{
     CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse as OutCallHttpResponse,
     TransformArgs, TransformContext, TransformFunc,
@@ -30,6 +31,7 @@ use ic_state_machine_tests::{
 use ic_test_utilities_load_wasm::load_wasm;
 use maplit::hashmap;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_bytes::ByteBuf;
 
 use evm_rpc::*;
 use mock::*;
@@ -46,9 +48,57 @@ const MOCK_REQUEST_PAYLOAD: &str = r#"{"id":1,"jsonrpc":"2.0","method":"eth_gasP
 const MOCK_REQUEST_RESPONSE: &str = r#"{"id":1,"jsonrpc":"2.0","result":"0x00112233"}"#;
 const MOCK_REQUEST_RESPONSE_BYTES: u64 = 1000;
 
+/// Canister which forwards the Candid arguments of its `deposit` method to `depositCycles`,
+/// attaching `DEPOSIT_PROXY_CYCLES`. It is installed with the EVM RPC canister ID as argument.
+const DEPOSIT_PROXY_WAT: &str = r#"
+(module
+  (import "ic0" "msg_arg_data_size" (func $msg_arg_data_size (result i32)))
+  (import "ic0" "msg_arg_data_copy" (func $msg_arg_data_copy (param i32 i32 i32)))
+  (import "ic0" "msg_reply_data_append" (func $msg_reply_data_append (param i32 i32)))
+  (import "ic0" "msg_reply" (func $msg_reply))
+  (import "ic0" "trap" (func $trap (param i32 i32)))
+  (import "ic0" "call_new"
+    (func $call_new (param i32 i32 i32 i32 i32 i32 i32 i32)))
+  (import "ic0" "call_data_append" (func $call_data_append (param i32 i32)))
+  (import "ic0" "call_cycles_add128" (func $call_cycles_add128 (param i64 i64)))
+  (import "ic0" "call_perform" (func $call_perform (result i32)))
+  (memory 1)
+  (table 2 funcref)
+  (elem (i32.const 0) $on_reply $on_reject)
+  (data (i32.const 100) "depositCycles")
+  (data (i32.const 200) "deposit rejected")
+  (func $init
+    (i32.store (i32.const 0) (call $msg_arg_data_size))
+    (call $msg_arg_data_copy (i32.const 4) (i32.const 0) (call $msg_arg_data_size)))
+  (func $deposit
+    (call $msg_arg_data_copy (i32.const 1024) (i32.const 0) (call $msg_arg_data_size))
+    (call $call_new
+      (i32.const 4) (i32.load (i32.const 0))
+      (i32.const 100) (i32.const 13)
+      (i32.const 0) (i32.const 0)
+      (i32.const 1) (i32.const 0))
+    (call $call_data_append (i32.const 1024) (call $msg_arg_data_size))
+    (call $call_cycles_add128 (i64.const 0) (i64.const 10000000000))
+    (if (call $call_perform) (then (call $trap (i32.const 200) (i32.const 16)))))
+  (func $on_reply (param i32)
+    (call $msg_arg_data_copy (i32.const 1024) (i32.const 0) (call $msg_arg_data_size))
+    (call $msg_reply_data_append (i32.const 1024) (call $msg_arg_data_size))
+    (call $msg_reply))
+  (func $on_reject (param i32)
+    (call $trap (i32.const 200) (i32.const 16)))
+  (export "canister_init" (func $init))
+  (export "canister_update deposit" (func $deposit)))
+"#;
+const DEPOSIT_PROXY_CYCLES: u128 = 10_000_000_000;
+
 const MOCK_TRANSACTION: &str="0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
 const MOCK_TRANSACTION_HASH: &str =
     "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788";
+const MOCK_LOGS_RESPONSE: &str = r#"{"id":0,"jsonrpc":"2.0","result":[{"address":"0xdac17f958d2ee523a2206206994597c13d831ec7","topics":["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef","0x000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43","0x00000000000000000000000078cccfb3d517cd4ed6d045e263e134712288ace2"],"data":"0x000000000000000000000000000000000000000000000000000000003b9c6433","blockNumber":"0x11dc77e","transactionHash":"0xf3ed91a03ddf964281ac7a24351573efd535b80fc460a5c2ad2b9d23153ec678","transactionIndex":"0x65","blockHash":"0xd5c72ad752b2f0144a878594faf8bd9f570f2f72af8e7f0940d3545a6388f629","logIndex":"0xe8","removed":false}]}"#;
+
+const MOCK_RECEIPT_RESPONSE: &str = r#"{"jsonrpc":"2.0","id":2,"result":{"blockHash":"0x5115c07eb1f20a9d6410db0916ed3df626cfdab161d3904f45c8c8b65c90d0be","blockNumber":"0x11a85ab","contractAddress":null,"cumulativeGasUsed":"0xf02aed","effectiveGasPrice":"0x63c00ee76","from":"0x0aa8ebb6ad5a8e499e550ae2c461197624c6e667","gasUsed":"0x7d89","logs":[],"logsBloom":"0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000","status":"0x1","to":"0x356cfd6e6d0000400000003900b415f80669009e","transactionHash":"0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f","transactionIndex":"0xd9","type":"0x2"}}"#;
+
+const MOCK_BLOCK_RESPONSE: &str = r#"{"jsonrpc":"2.0","result":{"baseFeePerGas":"0xd7232aa34","difficulty":"0x0","extraData":"0x546974616e2028746974616e6275696c6465722e78797a29","gasLimit":"0x1c9c380","gasUsed":"0xa768c4","hash":"0xc3674be7b9d95580d7f23c03d32e946f2b453679ee6505e3a778f003c5a3cfae","logsBloom":"0x3e6b8420e1a13038902c24d6c2a9720a7ad4860cdc870cd5c0490011e43631134f608935bd83171247407da2c15d85014f9984608c03684c74aad48b20bc24022134cdca5f2e9d2dee3b502a8ccd39eff8040b1d96601c460e119c408c620b44fa14053013220847045556ea70484e67ec012c322830cf56ef75e09bd0db28a00f238adfa587c9f80d7e30d3aba2863e63a5cad78954555966b1055a4936643366a0bb0b1bac68d0e6267fc5bf8304d404b0c69041125219aa70562e6a5a6362331a414a96d0716990a10161b87dd9568046a742d4280014975e232b6001a0360970e569d54404b27807d7a44c949ac507879d9d41ec8842122da6772101bc8b","miner":"0x388c818ca8b9251b393131c08a736a67ccb19297","mixHash":"0x516a58424d4883a3614da00a9c6f18cd5cd54335a08388229a993a8ecf05042f","nonce":"0x0000000000000000","number":"0x11db01d","parentHash":"0x43325027f6adf9befb223f8ae80db057daddcd7b48e41f60cd94bfa8877181ae","receiptsRoot":"0x66934c3fd9c547036fe0e56ad01bc43c84b170be7c4030a86805ddcdab149929","sha3Uncles":"0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347","size":"0xcd35","stateRoot":"0x13552447dd62f11ad885f21a583c4fa34144efe923c7e35fb018d6710f06b2b6","timestamp":"0x656f96f3","totalDifficulty":"0xc70d815d562d3cfa955","withdrawalsRoot":"0xecae44b2c53871003c5cc75285995764034c9b5978a904229d36c1280b141d48"},"id":0}"#;
 
 fn evm_rpc_wasm() -> Vec<u8> {
     load_wasm(std::env::var("CARGO_MANIFEST_DIR").unwrap(), "evm_rpc", &[])
@@ -136,6 +186,12 @@ impl EvmRpcSetup {
         self
     }
 
+    pub fn upgrade_canister(&self, args: InitArgs) {
+        self.env
+            .upgrade_canister(self.canister_id, evm_rpc_wasm(), Encode!(&args).unwrap())
+            .unwrap();
+    }
+
     fn call_update<R: CandidType + DeserializeOwned>(
         &self,
         method: &str,
@@ -163,11 +219,98 @@ impl EvmRpcSetup {
         }
     }
 
+    /// Responds to the oldest pending HTTP outcall, including outcalls made by timers.
+    pub fn mock_pending_http_request(&self, mock: &MockOutcall) -> bool {
+        let contexts = self.env.canister_http_request_contexts();
+        let (id, context) = match contexts.first_key_value() {
+            Some(kv) => kv,
+            None => return false,
+        };
+
+        mock.assert_matches(&CanisterHttpRequestArgument {
+            url: context.url.clone(),
+            max_response_bytes: context.max_response_bytes.map(|n| n.get()),
+            // Convert HTTP method type by name
+            method: serde_json::from_str(
+                &serde_json::to_string(&context.http_method)
+                    .unwrap()
+                    .to_lowercase(),
+            )
+            .unwrap(),
+            headers: context
+                .headers
+                .iter()
+                .map(|h| HttpHeader {
+                    name: h.name.clone(),
+                    value: h.value.clone(),
+                })
+                .collect(),
+            body: context.body.clone(),
+            transform: context.transform.clone().map(|t| TransformContext {
+                context: t.context,
+                function: TransformFunc::new(self.canister_id.get().0, t.method_name),
+            }),
+        });
+        let mut response = OutCallHttpResponse {
+            status: mock.response.status.clone(),
+            headers: mock.response.headers.clone(),
+            body: mock.response.body.clone(),
+        };
+        if let Some(transform) = &context.transform {
+            let transform_args = TransformArgs {
+                response,
+                context: transform.context.to_vec(),
+            };
+            response = Decode!(
+                &assert_reply(
+                    self.env
+                        .execute_ingress(
+                            self.canister_id,
+                            transform.method_name.clone(),
+                            Encode!(&transform_args).unwrap(),
+                        )
+                        .expect("failed to query transform HTTP response")
+                ),
+                OutCallHttpResponse
+            )
+            .unwrap();
+        }
+        let http_response = CanisterHttpResponsePayload {
+            status: response.status.0.try_into().unwrap(),
+            headers: response
+                .headers
+                .into_iter()
+                .map(|h| ic_ic00_types::HttpHeader {
+                    name: h.name,
+                    value: h.value,
+                })
+                .collect(),
+            body: response.body,
+        };
+        let payload = PayloadBuilder::new().http_response(*id, &http_response);
+        self.env.execute_payload(payload);
+        true
+    }
+
     pub fn authorize(&self, principal: &PrincipalId, auth: Auth) -> bool {
         self.call_update("authorize", Encode!(&principal.0, &auth).unwrap())
             .wait()
     }
 
+    pub fn authorize_with(
+        &self,
+        principal: &PrincipalId,
+        auth: Auth,
+        expires_at: Option<u64>,
+        scope: Option<AuthScope>,
+    ) -> bool {
+        self.call_update(
+            "authorize",
+            Encode!(&principal.0, &auth, &expires_at, &scope).unwrap(),
+        )
+        .wait()
+    }
+
     pub fn deauthorize(&self, principal: &PrincipalId, auth: Auth) -> bool {
         self.call_update("deauthorize", Encode!(&principal.0, &auth).unwrap())
             .wait()
@@ -181,10 +324,59 @@ impl EvmRpcSetup {
         self.call_query("getMetrics", Encode!().unwrap())
     }
 
+    pub fn get_usage(&self, principal: &PrincipalId) -> Option<CallerUsage> {
+        self.call_query("getUsage", Encode!(&principal.0).unwrap())
+    }
+
+    pub fn get_balance(&self, principal: &PrincipalId) -> u128 {
+        self.call_query("getBalance", Encode!(&Some(principal.0)).unwrap())
+    }
+
+    /// Deposits `DEPOSIT_PROXY_CYCLES` into the prepaid balance of a principal through a
+    /// canister, since ingress messages cannot carry cycles.
+    pub fn deposit_cycles(&self, principal: &PrincipalId) -> u128 {
+        let proxy_id = self.env.install_canister_wat(
+            DEPOSIT_PROXY_WAT,
+            self.canister_id.get().as_slice().to_vec(),
+            None,
+        );
+        self.env.add_cycles(proxy_id, INITIAL_CYCLES);
+        let result = self
+            .env
+            .execute_ingress(proxy_id, "deposit", Encode!(&Some(principal.0)).unwrap())
+            .expect("error while depositing cycles");
+        Decode!(&assert_reply(result), u128).unwrap()
+    }
+
+    pub fn reset_metrics(&self) {
+        self.call_update("resetMetrics", Encode!().unwrap()).wait()
+    }
+
+    /// Returns the metrics without the latency and response size histograms.
+    pub fn get_metrics_without_histograms(&self) -> Metrics {
+        Metrics {
+            response_latencies: Default::default(),
+            response_sizes: Default::default(),
+            ..self.get_metrics()
+        }
+    }
+
     pub fn get_providers(&self) -> Vec<ProviderView> {
         self.call_query("getProviders", Encode!().unwrap())
     }
 
+    pub fn get_certified_providers(&self) -> CertifiedResponse {
+        self.call_query("getCertifiedProviders", Encode!().unwrap())
+    }
+
+    pub fn get_certified_service_provider_map(&self) -> CertifiedResponse {
+        self.call_query("getCertifiedServiceProviderMap", Encode!().unwrap())
+    }
+
+    pub fn get_certified_metrics(&self) -> CertifiedResponse {
+        self.call_query("getCertifiedMetrics", Encode!().unwrap())
+    }
+
     pub fn get_service_provider_map(&self) -> Vec<(RpcService, u64)> {
         self.call_query("getServiceProviderMap", Encode!().unwrap())
     }
@@ -224,6 +416,177 @@ impl EvmRpcSetup {
             .wait()
     }
 
+    pub fn get_audit_log(&self, offset: u64, limit: u64) -> Vec<AuditLogEntry> {
+        self.call_query("getAuditLog", Encode!(&offset, &limit).unwrap())
+    }
+
+    pub fn block_host(&self, host: &str) -> bool {
+        self.call_update("blockHost", Encode!(&host).unwrap())
+            .wait()
+    }
+
+    pub fn allow_host(&self, pattern: &str) -> bool {
+        self.call_update("allowHost", Encode!(&pattern).unwrap())
+            .wait()
+    }
+
+    pub fn set_host_allowlist_enabled(&self, enabled: bool) {
+        self.call_update("setHostAllowlistEnabled", Encode!(&enabled).unwrap())
+            .wait()
+    }
+
+    pub fn get_quota_status(&self) -> Option<QuotaStatus> {
+        self.call_query(
+            "getQuotaStatus",
+            Encode!(&None::<candid::Principal>).unwrap(),
+        )
+    }
+
+    pub fn set_quota(&self, target: QuotaTarget, quota: Option<Quota>) {
+        self.call_update("setQuota", Encode!(&target, &quota).unwrap())
+            .wait()
+    }
+
+    pub fn set_http_rpc_services(&self, chain_id: u64, services: Option<RpcServices>) {
+        self.call_update("setHttpRpcServices", Encode!(&chain_id, &services).unwrap())
+            .wait()
+    }
+
+    pub fn get_recent_requests(&self) -> Vec<RecentRequest> {
+        self.call_query("getRecentRequests", Encode!().unwrap())
+    }
+
+    pub fn get_http_rpc_account(&self) -> HttpRpcAccount {
+        self.call_query("getHttpRpcAccount", Encode!().unwrap())
+    }
+
+    pub fn set_http_rpc_spending_limit(&self, max_cycles_per_request: Option<u128>) {
+        self.call_update(
+            "setHttpRpcSpendingLimit",
+            Encode!(&max_cycles_per_request).unwrap(),
+        )
+        .wait()
+    }
+
+    pub fn set_chain_head_services(&self, chain_id: u64, services: Option<RpcServices>) {
+        self.call_update(
+            "setChainHeadServices",
+            Encode!(&chain_id, &services).unwrap(),
+        )
+        .wait()
+    }
+
+    pub fn get_chain_head(&self, chain_id: u64) -> Option<ChainHead> {
+        self.call_query("getChainHead", Encode!(&chain_id).unwrap())
+    }
+
+    pub fn subscribe_logs(&self, args: SubscribeLogsArgs) -> u64 {
+        self.call_update("subscribeLogs", Encode!(&args).unwrap())
+            .wait()
+    }
+
+    pub fn acknowledge_logs(&self, subscription_id: u64, cursor: u64) {
+        self.call_update(
+            "acknowledgeLogs",
+            Encode!(&subscription_id, &cursor).unwrap(),
+        )
+        .wait()
+    }
+
+    pub fn get_log_subscriptions(&self) -> Vec<LogSubscription> {
+        self.call_query("getLogSubscriptions", Encode!().unwrap())
+    }
+
+    pub fn subscribe_blocks(&self, args: SubscribeBlocksArgs) -> u64 {
+        self.call_update("subscribeBlocks", Encode!(&args).unwrap())
+            .wait()
+    }
+
+    pub fn get_block_subscriptions(&self) -> Vec<BlockSubscription> {
+        self.call_query("getBlockSubscriptions", Encode!().unwrap())
+    }
+
+    pub fn create_log_cursor(&self, source: RpcServices, args: candid_types::GetLogsArgs) -> u64 {
+        self.call_update("createLogCursor", Encode!(&source, &args).unwrap())
+            .wait()
+    }
+
+    pub fn poll_log_cursor(&self, cursor_id: u64) -> CallFlow<MultiRpcResult<Vec<LogEntry>>> {
+        self.call_update(
+            "pollLogCursor",
+            Encode!(&cursor_id, &(None as Option<RpcConfig>)).unwrap(),
+        )
+    }
+
+    pub fn get_log_cursors(&self) -> Vec<LogCursor> {
+        self.call_query("getLogCursors", Encode!().unwrap())
+    }
+
+    pub fn track_transaction(&self, args: TrackTransactionArgs) -> String {
+        self.call_update("trackTransaction", Encode!(&args).unwrap())
+            .wait()
+    }
+
+    pub fn decode_raw_transaction(
+        &self,
+        raw_signed_transaction_hex: &str,
+    ) -> Result<DecodedTransaction, TransactionError> {
+        self.call_query(
+            "decodeRawTransaction",
+            Encode!(&raw_signed_transaction_hex).unwrap(),
+        )
+    }
+
+    pub fn get_transaction_status(&self, hash: &str) -> Option<TransactionStatus> {
+        self.call_query("getTransactionStatus", Encode!(&hash).unwrap())
+    }
+
+    pub fn set_ecdsa_key_name(&self, key_name: Option<&str>) {
+        self.call_update("setEcdsaKeyName", Encode!(&key_name).unwrap())
+            .wait()
+    }
+
+    pub fn get_eth_address(&self, principal: PrincipalId) -> String {
+        self.call_update("getEthAddress", Encode!(&principal.0).unwrap())
+            .wait()
+    }
+
+    pub fn get_address_nonces(&self, chain_id: u64, address: &str) -> Option<AddressNonces> {
+        self.call_query("getAddressNonces", Encode!(&chain_id, &address).unwrap())
+    }
+
+    pub fn send_transaction(
+        &self,
+        args: SendTransactionArgs,
+    ) -> CallFlow<MultiRpcResult<candid_types::SendRawTransactionStatus>> {
+        self.call_update(
+            "sendTransaction",
+            Encode!(&args, &(None as Option<RpcConfig>)).unwrap(),
+        )
+    }
+
+    pub fn set_response_cache_size(&self, max_entries: u64) {
+        self.call_update("setResponseCacheSize", Encode!(&max_entries).unwrap())
+            .wait()
+    }
+
+    pub fn get_response_cache_stats(&self) -> ResponseCacheStats {
+        self.call_query("getResponseCacheStats", Encode!().unwrap())
+    }
+
+    pub fn clear_response_cache(&self, chain_id: Option<u64>) -> u64 {
+        self.call_update("clearResponseCache", Encode!(&chain_id).unwrap())
+            .wait()
+    }
+
+    pub fn http_request(&self, request: AssetHttpRequest) -> HttpGatewayResponse {
+        self.call_query("http_request", Encode!(&request).unwrap())
+    }
+
+    pub fn http_request_update(&self, request: AssetHttpRequest) -> CallFlow<HttpGatewayResponse> {
+        self.call_update("http_request_update", Encode!(&request).unwrap())
+    }
+
     pub fn request_cost(
         &self,
         source: RpcService,
@@ -293,6 +656,15 @@ impl EvmRpcSetup {
         )
     }
 
+    pub fn eth_get_code(
+        &self,
+        source: RpcServices,
+        config: Option<RpcConfig>,
+        args: candid_types::GetCodeArgs,
+    ) -> CallFlow<MultiRpcResult<String>> {
+        self.call_update("eth_getCode", Encode!(&source, &config, &args).unwrap())
+    }
+
     pub fn eth_fee_history(
         &self,
         source: RpcServices,
@@ -302,6 +674,42 @@ impl EvmRpcSetup {
         self.call_update("eth_feeHistory", Encode!(&source, &config, &args).unwrap())
     }
 
+    pub fn estimate_fees(
+        &self,
+        source: RpcServices,
+        config: Option<RpcConfig>,
+        args: EstimateFeesArgs,
+    ) -> CallFlow<MultiRpcResult<FeeEstimate>> {
+        self.call_update("estimateFees", Encode!(&source, &config, &args).unwrap())
+    }
+
+    pub fn erc20_balance_of(
+        &self,
+        source: RpcServices,
+        config: Option<RpcConfig>,
+        args: Erc20BalanceOfArgs,
+    ) -> CallFlow<MultiRpcResult<Nat>> {
+        self.call_update("erc20BalanceOf", Encode!(&source, &config, &args).unwrap())
+    }
+
+    pub fn erc20_symbol(
+        &self,
+        source: RpcServices,
+        config: Option<RpcConfig>,
+        args: Erc20CallArgs,
+    ) -> CallFlow<MultiRpcResult<String>> {
+        self.call_update("erc20Symbol", Encode!(&source, &config, &args).unwrap())
+    }
+
+    pub fn eth_call_abi(
+        &self,
+        source: RpcServices,
+        config: Option<RpcConfig>,
+        args: CallAbiArgs,
+    ) -> CallFlow<MultiRpcResult<Vec<AbiValue>>> {
+        self.call_update("eth_callAbi", Encode!(&source, &config, &args).unwrap())
+    }
+
     pub fn eth_send_raw_transaction(
         &self,
         source: RpcServices,
@@ -380,89 +788,31 @@ impl<R: CandidType + DeserializeOwned> CallFlow<R> {
             }
             _ => (),
         }
-        let contexts = self.setup.env.canister_http_request_contexts();
-        let (id, context) = match contexts.first_key_value() {
-            Some(kv) => kv,
-            None => return false,
-        };
+        self.setup.mock_pending_http_request(mock)
+    }
 
-        mock.assert_matches(&CanisterHttpRequestArgument {
-            url: context.url.clone(),
-            max_response_bytes: context.max_response_bytes.map(|n| n.get()),
-            // Convert HTTP method type by name
-            method: serde_json::from_str(
-                &serde_json::to_string(&context.http_method)
-                    .unwrap()
-                    .to_lowercase(),
-            )
-            .unwrap(),
-            headers: context
-                .headers
-                .iter()
-                .map(|h| HttpHeader {
-                    name: h.name.clone(),
-                    value: h.value.clone(),
-                })
-                .collect(),
-            body: context.body.clone(),
-            transform: context.transform.clone().map(|t| TransformContext {
-                context: t.context,
-                function: TransformFunc::new(self.setup.canister_id.get().0, t.method_name),
-            }),
-        });
-        let mut response = OutCallHttpResponse {
-            status: mock.response.status.clone(),
-            headers: mock.response.headers.clone(),
-            body: mock.response.body.clone(),
-        };
-        if let Some(transform) = &context.transform {
-            let transform_args = TransformArgs {
-                response,
-                context: transform.context.to_vec(),
-            };
-            response = Decode!(
-                &assert_reply(
-                    self.setup
-                        .env
-                        .execute_ingress(
-                            self.setup.canister_id,
-                            transform.method_name.clone(),
-                            Encode!(&transform_args).unwrap(),
-                        )
-                        .expect("failed to query transform HTTP response")
-                ),
-                OutCallHttpResponse
-            )
-            .unwrap();
-        }
-        let http_response = CanisterHttpResponsePayload {
-            status: response.status.0.try_into().unwrap(),
-            headers: response
-                .headers
-                .into_iter()
-                .map(|h| ic_ic00_types::HttpHeader {
-                    name: h.name,
-                    value: h.value,
-                })
-                .collect(),
-            body: response.body,
-        };
-        let payload = PayloadBuilder::new().http_response(*id, &http_response);
-        self.setup.env.execute_payload(payload);
-        true
-    }
-
-    pub fn wait(self) -> R {
-        let candid = &assert_reply(
+    fn await_reply(&self) -> Vec<u8> {
+        assert_reply(
             self.setup
                 .env
-                .await_ingress(self.message_id, MAX_TICKS)
+                .await_ingress(self.message_id.clone(), MAX_TICKS)
                 .unwrap_or_else(|err| {
                     panic!("error during update call to `{}()`: {}", self.method, err)
                 }),
-        );
+        )
+    }
+
+    pub fn wait(self) -> R {
+        let candid = &self.await_reply();
         Decode!(candid, R).expect("error while decoding Candid response from update call")
     }
+
+    /// Waits for a reply with an additional result, e.g. the decoded transaction returned by
+    /// `eth_sendRawTransaction`.
+    pub fn wait_with<T: CandidType + DeserializeOwned>(self) -> (R, T) {
+        let candid = &self.await_reply();
+        Decode!(candid, R, T).expect("error while decoding Candid response from update call")
+    }
 }
 
 fn mock_request(builder_fn: impl Fn(MockOutcallBuilder) -> MockOutcallBuilder) {
@@ -489,6 +839,127 @@ fn mock_request(builder_fn: impl Fn(MockOutcallBuilder) -> MockOutcallBuilder) {
     );
 }
 
+#[test]
+fn should_record_audit_log() {
+    let setup = EvmRpcSetup::new().as_controller();
+    let principal = PrincipalId::new_user_test_id(1);
+    setup.authorize(&principal, Auth::RegisterProvider);
+    setup.set_open_rpc_access(false);
+    setup.deauthorize(&principal, Auth::RegisterProvider);
+
+    let entries = setup.get_audit_log(0, 10);
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| entry.action.as_str())
+            .collect::<Vec<_>>(),
+        vec!["authorize", "setOpenRpcAccess", "deauthorize"]
+    );
+    assert!(entries.iter().all(|entry| entry.caller == setup.caller.0));
+    assert_eq!(entries[0].target, principal.0.to_string());
+    assert_eq!(entries[0].before, None);
+    assert_matches!(&entries[0].after, Some(after) if after.contains("RegisterProvider"));
+    assert_eq!(entries[1].before, Some("true".to_string()));
+    assert_eq!(entries[1].after, Some("false".to_string()));
+    assert_eq!(entries[2].after, None);
+
+    assert_eq!(setup.get_audit_log(1, 1), entries[1..2]);
+}
+
+#[test]
+fn should_record_unregister_provider_in_audit_log() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::RegisterProvider);
+    let provider_id = setup.register_provider(RegisterProviderArgs {
+        chain_id: 1,
+        hostname: ANKR_HOSTNAME.to_string(),
+        credential_path: "".to_string(),
+        credential_headers: None,
+        cycles_per_call: 0,
+        cycles_per_message_byte: 0,
+    });
+    assert!(setup.unregister_provider(provider_id));
+
+    let entries = setup.clone().as_controller().get_audit_log(0, 10);
+    let entry = entries.last().unwrap();
+    assert_eq!(entry.action, "unregisterProvider");
+    assert_eq!(entry.caller, setup.caller.0);
+    assert_eq!(entry.target, provider_id.to_string());
+    assert_matches!(&entry.before, Some(before) if before.contains(ANKR_HOSTNAME));
+    assert_eq!(entry.after, None);
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_unauthorized_get_audit_log() {
+    let setup = EvmRpcSetup::new();
+    setup.get_audit_log(0, 10);
+}
+
+fn custom_request(setup: &EvmRpcSetup) -> CallFlow<RpcResult<String>> {
+    setup.request(
+        RpcService::Custom(RpcApi {
+            url: MOCK_REQUEST_URL.to_string(),
+            headers: None,
+        }),
+        MOCK_REQUEST_PAYLOAD,
+        MOCK_REQUEST_RESPONSE_BYTES,
+    )
+}
+
+#[test]
+fn should_reject_blocked_host() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let host = CLOUDFLARE_HOSTNAME.to_string();
+    assert!(setup.clone().as_controller().block_host(&host));
+    assert_eq!(
+        custom_request(&setup).wait(),
+        Err(RpcError::ValidationError(ValidationError::HostNotAllowed(
+            host.clone()
+        )))
+    );
+    assert_eq!(
+        setup.get_metrics().err_host_not_allowed,
+        hashmap! { MetricRpcHost(host) => 1 }
+    );
+}
+
+#[test]
+fn should_restrict_custom_hosts_to_allowlist() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let host = CLOUDFLARE_HOSTNAME.to_string();
+    setup
+        .clone()
+        .as_controller()
+        .set_host_allowlist_enabled(true);
+    assert_eq!(
+        custom_request(&setup).wait(),
+        Err(RpcError::ValidationError(ValidationError::HostNotAllowed(
+            host.clone()
+        )))
+    );
+    assert!(setup.clone().as_controller().allow_host(&host));
+    assert_matches!(
+        custom_request(&setup)
+            .mock_http(MockOutcallBuilder::new(200, MOCK_REQUEST_RESPONSE))
+            .wait(),
+        Ok(_)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Host must not be longer than 100 bytes")]
+fn should_panic_if_blocked_host_too_long() {
+    let setup = EvmRpcSetup::new().as_controller();
+    setup.block_host(&format!("{}.example.com", "a".repeat(100)));
+}
+
+#[test]
+#[should_panic(expected = "Host must not be longer than 100 bytes")]
+fn should_panic_if_allowed_host_pattern_too_long() {
+    let setup = EvmRpcSetup::new().as_controller();
+    setup.allow_host(&format!("*.{}.example.com", "a".repeat(100)));
+}
+
 #[test]
 fn mock_request_should_succeed() {
     mock_request(|builder| builder)
@@ -777,6 +1248,87 @@ fn should_panic_if_manage_auth_unregister_provider() {
     setup.unregister_provider(3);
 }
 
+#[test]
+fn should_certify_providers() {
+    let setup = EvmRpcSetup::new();
+    let response = setup.get_certified_providers();
+    assert_eq!(
+        Decode!(&response.data, Vec<ProviderView>).unwrap(),
+        setup.get_providers()
+    );
+    assert!(!response.witness.is_empty());
+
+    let setup = setup.authorize_caller(Auth::RegisterProvider);
+    setup.register_provider(RegisterProviderArgs {
+        chain_id: 1,
+        hostname: "cloudflare-eth.com".to_string(),
+        credential_path: "".to_string(),
+        credential_headers: None,
+        cycles_per_call: 0,
+        cycles_per_message_byte: 0,
+    });
+    let updated_response = setup.get_certified_providers();
+    assert_ne!(updated_response.data, response.data);
+    assert_ne!(updated_response.witness, response.witness);
+    assert_eq!(
+        Decode!(&updated_response.data, Vec<ProviderView>).unwrap(),
+        setup.get_providers()
+    );
+}
+
+#[test]
+fn should_certify_service_provider_map_for_rpc_callers() {
+    let setup = EvmRpcSetup::new();
+    let response = setup.get_certified_service_provider_map();
+    assert_eq!(
+        Decode!(&response.data, Vec<(RpcService, u64)>).unwrap(),
+        setup.clone().as_controller().get_service_provider_map()
+    );
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_certified_service_provider_map_without_rpc_access() {
+    let setup = EvmRpcSetup::new();
+    setup.clone().as_controller().set_open_rpc_access(false);
+    setup.get_certified_service_provider_map();
+}
+
+#[test]
+fn should_certify_metrics_snapshot() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response = setup.get_certified_metrics();
+    assert!(!response.witness.is_empty());
+    assert_eq!(
+        Decode!(&response.data, Metrics).unwrap().requests,
+        Default::default()
+    );
+
+    setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            candid_types::GetTransactionCountArgs {
+                address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+                block: candid_types::BlockTag::Latest,
+            },
+        )
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .wait();
+    // The snapshot is only updated by the timer
+    assert_eq!(setup.get_certified_metrics().data, response.data);
+    setup.env.advance_time(CERTIFIED_METRICS_INTERVAL);
+    setup.env.tick();
+    let updated_response = setup.get_certified_metrics();
+    assert_eq!(
+        Decode!(&updated_response.data, Metrics).unwrap().requests,
+        setup.get_metrics().requests
+    );
+}
+
 #[test]
 fn should_replace_service_provider() {
     let setup = EvmRpcSetup::new()
@@ -974,7 +1526,7 @@ fn eth_get_logs_should_succeed() {
                 topics: None,
             },
         )
-        .mock_http(MockOutcallBuilder::new(200, r#"{"id":0,"jsonrpc":"2.0","result":[{"address":"0xdac17f958d2ee523a2206206994597c13d831ec7","topics":["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef","0x000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43","0x00000000000000000000000078cccfb3d517cd4ed6d045e263e134712288ace2"],"data":"0x000000000000000000000000000000000000000000000000000000003b9c6433","blockNumber":"0x11dc77e","transactionHash":"0xf3ed91a03ddf964281ac7a24351573efd535b80fc460a5c2ad2b9d23153ec678","transactionIndex":"0x65","blockHash":"0xd5c72ad752b2f0144a878594faf8bd9f570f2f72af8e7f0940d3545a6388f629","logIndex":"0xe8","removed":false}]}"#))
+        .mock_http(MockOutcallBuilder::new(200, MOCK_LOGS_RESPONSE))
         .wait()
         .expect_consistent()
         .unwrap();
@@ -1023,7 +1575,7 @@ fn eth_get_block_by_number_should_succeed() {
             None,
             candid_types::BlockTag::Latest,
         )
-        .mock_http(MockOutcallBuilder::new(200, r#"{"jsonrpc":"2.0","result":{"baseFeePerGas":"0xd7232aa34","difficulty":"0x0","extraData":"0x546974616e2028746974616e6275696c6465722e78797a29","gasLimit":"0x1c9c380","gasUsed":"0xa768c4","hash":"0xc3674be7b9d95580d7f23c03d32e946f2b453679ee6505e3a778f003c5a3cfae","logsBloom":"0x3e6b8420e1a13038902c24d6c2a9720a7ad4860cdc870cd5c0490011e43631134f608935bd83171247407da2c15d85014f9984608c03684c74aad48b20bc24022134cdca5f2e9d2dee3b502a8ccd39eff8040b1d96601c460e119c408c620b44fa14053013220847045556ea70484e67ec012c322830cf56ef75e09bd0db28a00f238adfa587c9f80d7e30d3aba2863e63a5cad78954555966b1055a4936643366a0bb0b1bac68d0e6267fc5bf8304d404b0c69041125219aa70562e6a5a6362331a414a96d0716990a10161b87dd9568046a742d4280014975e232b6001a0360970e569d54404b27807d7a44c949ac507879d9d41ec8842122da6772101bc8b","miner":"0x388c818ca8b9251b393131c08a736a67ccb19297","mixHash":"0x516a58424d4883a3614da00a9c6f18cd5cd54335a08388229a993a8ecf05042f","nonce":"0x0000000000000000","number":"0x11db01d","parentHash":"0x43325027f6adf9befb223f8ae80db057daddcd7b48e41f60cd94bfa8877181ae","receiptsRoot":"0x66934c3fd9c547036fe0e56ad01bc43c84b170be7c4030a86805ddcdab149929","sha3Uncles":"0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347","size":"0xcd35","stateRoot":"0x13552447dd62f11ad885f21a583c4fa34144efe923c7e35fb018d6710f06b2b6","timestamp":"0x656f96f3","totalDifficulty":"0xc70d815d562d3cfa955","withdrawalsRoot":"0xecae44b2c53871003c5cc75285995764034c9b5978a904229d36c1280b141d48"},"id":0}"#))
+        .mock_http(MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE))
         .wait()
         .expect_consistent()
         .unwrap();
@@ -1064,7 +1616,7 @@ fn eth_get_transaction_receipt_should_succeed() {
             None,
             "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
         )
-        .mock_http(MockOutcallBuilder::new(200, r#"{"jsonrpc":"2.0","id":2,"result":{"blockHash":"0x5115c07eb1f20a9d6410db0916ed3df626cfdab161d3904f45c8c8b65c90d0be","blockNumber":"0x11a85ab","contractAddress":null,"cumulativeGasUsed":"0xf02aed","effectiveGasPrice":"0x63c00ee76","from":"0x0aa8ebb6ad5a8e499e550ae2c461197624c6e667","gasUsed":"0x7d89","logs":[],"logsBloom":"0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000","status":"0x1","to":"0x356cfd6e6d0000400000003900b415f80669009e","transactionHash":"0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f","transactionIndex":"0xd9","type":"0x2"}}"#))
+        .mock_http(MockOutcallBuilder::new(200, MOCK_RECEIPT_RESPONSE))
         .wait()
         .expect_consistent()
         .unwrap();
@@ -1145,106 +1697,454 @@ fn eth_fee_history_should_succeed() {
 }
 
 #[test]
-fn eth_send_raw_transaction_should_succeed() {
+fn estimate_fees_should_succeed() {
     let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
     let response = setup
-        .eth_send_raw_transaction(RpcServices::EthMainnet(None), None, MOCK_TRANSACTION)
-        .mock_http(MockOutcallBuilder::new(
+        .estimate_fees(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Cloudflare])),
+            None,
+            EstimateFeesArgs {
+                urgency: Some(FeeUrgency::High),
+                ..Default::default()
+            },
+        )
+        .mock_http_once(MockOutcallBuilder::new(
             200,
-            r#"{"id":0,"jsonrpc":"2.0","result":"Ok"}"#,
+            r#"{"id":0,"jsonrpc":"2.0","result":{"oldestBlock":"0x11e57f5","baseFeePerGas":["0x64","0x6e","0x78"],"reward":[["0x3"],["0x1"],["0x2"]]}}"#,
+        ))
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"0x1"}"#,
         ))
         .wait()
         .expect_consistent()
         .unwrap();
     assert_eq!(
         response,
-        candid_types::SendRawTransactionStatus::Ok(Some(
-            Hash::from_str(MOCK_TRANSACTION_HASH).unwrap()
-        ))
+        FeeEstimate {
+            base_fee_per_gas: 120_u128.into(),
+            max_fee_per_gas: 482_u128.into(),
+            max_priority_fee_per_gas: 2_u128.into(),
+            base_fee_per_blob_gas: Some(1_u128.into()),
+            max_fee_per_blob_gas: Some(4_u128.into()),
+        }
     );
 }
 
 #[test]
-fn candid_rpc_should_allow_unexpected_response_fields() {
+fn estimate_fees_should_skip_blob_fees_without_blob_support() {
     let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
     let response = setup
-        .eth_get_transaction_receipt(
-            RpcServices::EthMainnet(None),
+        .estimate_fees(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Cloudflare])),
             None,
-            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+            EstimateFeesArgs::default(),
         )
-        .mock_http(MockOutcallBuilder::new(200, r#"{"jsonrpc":"2.0","id":0,"result":{"unexpectedKey":"unexpectedValue","blockHash":"0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35","blockNumber":"0x5bad55","contractAddress":null,"cumulativeGasUsed":"0xb90b0","effectiveGasPrice":"0x746a528800","from":"0x398137383b3d25c92898c656696e41950e47316b","gasUsed":"0x1383f","logs":[],"logsBloom":"0x0","status":"0x1","to":"0x06012c8cf97bead5deae237070f9587f8e7a266d","transactionHash":"0xbb3a336e3f823ec18197f1e13ee875700f08f03e2cab75f0d0b118dabb44cba0","transactionIndex":"0x11","type":"0x0"}}"#))
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":{"oldestBlock":"0x11e57f5","baseFeePerGas":["0x64","0x6e","0x78"],"reward":[["0x3"],["0x1"],["0x2"]]}}"#,
+        ))
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","error":{"code":-32601,"message":"the method eth_blobBaseFee does not exist/is not available"}}"#,
+        ))
         .wait()
         .expect_consistent()
-        .unwrap()
-        .expect("receipt was None");
-    assert_eq!(
-        response.block_hash,
-        "0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35".to_string()
-    );
+        .unwrap();
+    assert_eq!(response.base_fee_per_blob_gas, None);
+    assert_eq!(response.max_fee_per_blob_gas, None);
 }
 
 #[test]
-fn candid_rpc_should_err_without_cycles() {
-    let setup = EvmRpcSetup::new();
-    let result = setup
-        .eth_get_transaction_receipt(
+fn estimate_fees_should_return_error_for_requested_blob_fees() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response = setup
+        .estimate_fees(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Cloudflare])),
+            None,
+            EstimateFeesArgs {
+                blob_fees: Some(true),
+                ..Default::default()
+            },
+        )
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":{"oldestBlock":"0x11e57f5","baseFeePerGas":["0x64","0x6e","0x78"],"reward":[["0x3"],["0x1"],["0x2"]]}}"#,
+        ))
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","error":{"code":-32601,"message":"the method eth_blobBaseFee does not exist/is not available"}}"#,
+        ))
+        .wait()
+        .expect_consistent();
+    assert_matches!(response, Err(RpcError::JsonRpcError(_)));
+}
+
+#[test]
+fn estimate_fees_should_reject_invalid_percentile() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response = setup
+        .estimate_fees(
             RpcServices::EthMainnet(None),
             None,
-            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+            EstimateFeesArgs {
+                percentile: Some(101),
+                ..Default::default()
+            },
         )
         .wait()
         .expect_consistent();
     assert_matches!(
-        result,
-        Err(RpcError::ProviderError(ProviderError::TooFewCycles {
-            expected: _,
-            received: 0,
-        }))
+        response,
+        Err(RpcError::ValidationError(ValidationError::Custom(_)))
     );
 }
 
 #[test]
-fn candid_rpc_should_err_during_restricted_access() {
+fn erc20_balance_of_should_succeed() {
     let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
-    setup.clone().as_controller().set_open_rpc_access(false);
-    let result = setup
-        .eth_get_transaction_receipt(
-            RpcServices::EthMainnet(Some(vec![
-                EthMainnetService::Cloudflare,
-                EthMainnetService::BlockPi,
-            ])),
+    let response = setup
+        .erc20_balance_of(
+            RpcServices::EthMainnet(None),
             None,
-            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+            Erc20BalanceOfArgs {
+                token: "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+                owner: "0xa9d1e08c7793af67e9d92fe308d5697fb81d3e43".to_string(),
+                block: None,
+            },
         )
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"0x000000000000000000000000000000000000000000000000000000003b9c6433"}"#,
+        ))
         .wait()
-        .expect_consistent();
-    assert_eq!(
-        result,
-        Err(RpcError::ProviderError(ProviderError::NoPermission))
-    );
-    assert_eq!(
-        setup.get_metrics(),
-        Metrics {
-            err_no_permission: 1,
-            ..Default::default()
-        }
-    );
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(response, Nat::from(0x3b9c6433_u64));
 }
 
 #[test]
-fn candid_rpc_should_err_when_service_unavailable() {
+fn erc20_symbol_should_succeed() {
     let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
-    let result = setup
-        .eth_get_transaction_receipt(
+    let response = setup
+        .erc20_symbol(
             RpcServices::EthMainnet(None),
             None,
-            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+            Erc20CallArgs {
+                token: "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+                block: None,
+            },
         )
-        .mock_http(MockOutcallBuilder::new(503, "Service unavailable"))
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"0x000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000045553445400000000000000000000000000000000000000000000000000000000"}"#,
+        ))
         .wait()
-        .expect_consistent();
-    assert_eq!(
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(response, "USDT");
+}
+
+#[test]
+fn erc20_balance_of_should_reject_invalid_owner() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response = setup
+        .erc20_balance_of(
+            RpcServices::EthMainnet(None),
+            None,
+            Erc20BalanceOfArgs {
+                token: "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+                owner: "0x123".to_string(),
+                block: None,
+            },
+        )
+        .wait()
+        .expect_consistent();
+    assert_matches!(
+        response,
+        Err(RpcError::ValidationError(ValidationError::Custom(_)))
+    );
+}
+
+#[test]
+fn eth_call_abi_should_succeed() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response = setup
+        .eth_call_abi(
+            RpcServices::EthMainnet(None),
+            None,
+            CallAbiArgs {
+                to: "0x0d4a11d5eeaac28ec3f61d100daf4d40471f1852".to_string(),
+                signature: "function getReserves() view returns (uint112,uint112,uint32)"
+                    .to_string(),
+                args: vec![],
+                block: None,
+            },
+        )
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"0x000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000065d3c1a7"}"#,
+        ))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(
+        response,
+        vec![
+            AbiValue::Uint(1_u64.into()),
+            AbiValue::Uint(2_u64.into()),
+            AbiValue::Uint(0x65d3c1a7_u64.into()),
+        ]
+    );
+}
+
+#[test]
+fn eth_call_abi_should_reject_invalid_arguments() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response = setup
+        .eth_call_abi(
+            RpcServices::EthMainnet(None),
+            None,
+            CallAbiArgs {
+                to: "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+                signature: "function balanceOf(address) view returns (uint256)".to_string(),
+                args: vec![AbiValue::Uint(1_u64.into())],
+                block: None,
+            },
+        )
+        .wait()
+        .expect_consistent();
+    assert_matches!(
+        response,
+        Err(RpcError::ValidationError(ValidationError::Custom(_)))
+    );
+}
+
+#[test]
+fn eth_send_raw_transaction_should_succeed() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let (response, transaction) = setup
+        .eth_send_raw_transaction(RpcServices::EthMainnet(None), None, MOCK_TRANSACTION)
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"Ok"}"#,
+        ))
+        .wait_with::<Option<Result<DecodedTransaction, TransactionError>>>();
+    assert_eq!(
+        response.expect_consistent().unwrap(),
+        candid_types::SendRawTransactionStatus::Ok(Some(
+            Hash::from_str(MOCK_TRANSACTION_HASH).unwrap()
+        ))
+    );
+    let transaction = transaction.unwrap().unwrap();
+    assert_eq!(
+        transaction.from,
+        "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"
+    );
+    assert_eq!(transaction.nonce, 9);
+}
+
+#[test]
+fn should_decode_raw_transaction() {
+    let setup = EvmRpcSetup::new();
+    let transaction = setup.decode_raw_transaction(MOCK_TRANSACTION).unwrap();
+    assert_eq!(transaction.transaction_hash, MOCK_TRANSACTION_HASH);
+    assert_eq!(transaction.chain_id, Some(1));
+    assert_eq!(
+        transaction.from,
+        "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"
+    );
+    assert_eq!(transaction.nonce, 9);
+    assert_eq!(
+        setup.decode_raw_transaction("0x"),
+        Err(TransactionError::Empty)
+    );
+}
+
+#[test]
+fn eth_send_raw_transaction_should_reject_transaction_for_other_chain() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let (result, transaction) = setup
+        .eth_send_raw_transaction(RpcServices::EthSepolia(None), None, MOCK_TRANSACTION)
+        .wait_with::<Option<Result<DecodedTransaction, TransactionError>>>();
+    assert_eq!(
+        result.expect_consistent(),
+        Err(RpcError::ValidationError(ValidationError::Custom(
+            "Transaction is signed for chain ID 1 instead of 11155111".to_string()
+        )))
+    );
+    assert_eq!(
+        transaction,
+        Some(Err(TransactionError::ChainIdMismatch {
+            expected: ETH_SEPOLIA_CHAIN_ID,
+            actual: ETH_MAINNET_CHAIN_ID
+        }))
+    );
+    assert_eq!(setup.get_metrics().requests, Default::default());
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_manager_sets_ecdsa_key_name() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::Manage);
+    setup.set_ecdsa_key_name(Some("master_ecdsa_public_key"));
+}
+
+#[test]
+fn should_sign_and_send_transaction() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    setup
+        .clone()
+        .as_controller()
+        .set_ecdsa_key_name(Some("master_ecdsa_public_key"));
+    let address = setup.get_eth_address(setup.caller);
+    assert_eq!(address, setup.get_eth_address(setup.caller));
+    assert_ne!(address, setup.get_eth_address(setup.controller));
+
+    let reply = setup
+        .send_transaction(SendTransactionArgs {
+            services: RpcServices::EthMainnet(Some(vec![EthMainnetService::Cloudflare])),
+            to: Some("0xdac17f958d2ee523a2206206994597c13d831ec7".to_string()),
+            value: 1_000_000_u64.into(),
+            data: None,
+            gas: 21_000_u64.into(),
+            max_fee_per_gas: Some(20_000_000_000_u64.into()),
+            max_priority_fee_per_gas: Some(1_000_000_000_u64.into()),
+        })
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"0x5"}"#,
+        ))
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"Ok"}"#,
+        ))
+        .await_reply();
+    let (result, transaction) = Decode!(
+        &reply,
+        MultiRpcResult<candid_types::SendRawTransactionStatus>,
+        Option<DecodedTransaction>
+    )
+    .unwrap();
+    let transaction = transaction.unwrap();
+    assert_eq!(
+        result.expect_consistent(),
+        Ok(candid_types::SendRawTransactionStatus::Ok(Some(
+            Hash::from_str(&transaction.transaction_hash).unwrap()
+        )))
+    );
+    assert_eq!(transaction.transaction_type, 2);
+    assert_eq!(transaction.chain_id, Some(1));
+    assert_eq!(transaction.from, address);
+    assert_eq!(transaction.nonce, 5);
+
+    let nonces = setup.get_address_nonces(1, &address).unwrap();
+    assert_eq!(nonces.next_nonce, 6);
+    assert_eq!(nonces.gaps, Vec::<u64>::new());
+    assert_matches!(
+        nonces.in_flight.as_slice(),
+        [NonceReservation {
+            nonce: 5,
+            sent: true,
+            ..
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Transaction signing is disabled")]
+fn should_panic_if_signing_is_disabled() {
+    let setup = EvmRpcSetup::new();
+    setup.get_eth_address(setup.caller);
+}
+
+#[test]
+fn candid_rpc_should_allow_unexpected_response_fields() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response = setup
+        .eth_get_transaction_receipt(
+            RpcServices::EthMainnet(None),
+            None,
+            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+        )
+        .mock_http(MockOutcallBuilder::new(200, r#"{"jsonrpc":"2.0","id":0,"result":{"unexpectedKey":"unexpectedValue","blockHash":"0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35","blockNumber":"0x5bad55","contractAddress":null,"cumulativeGasUsed":"0xb90b0","effectiveGasPrice":"0x746a528800","from":"0x398137383b3d25c92898c656696e41950e47316b","gasUsed":"0x1383f","logs":[],"logsBloom":"0x0","status":"0x1","to":"0x06012c8cf97bead5deae237070f9587f8e7a266d","transactionHash":"0xbb3a336e3f823ec18197f1e13ee875700f08f03e2cab75f0d0b118dabb44cba0","transactionIndex":"0x11","type":"0x0"}}"#))
+        .wait()
+        .expect_consistent()
+        .unwrap()
+        .expect("receipt was None");
+    assert_eq!(
+        response.block_hash,
+        "0xb3b20624f8f0f86eb50dd04688409e5cea4bd02d700bf6e79e9384d47d6a5a35".to_string()
+    );
+}
+
+#[test]
+fn candid_rpc_should_err_without_cycles() {
+    let setup = EvmRpcSetup::new();
+    let result = setup
+        .eth_get_transaction_receipt(
+            RpcServices::EthMainnet(None),
+            None,
+            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+        )
+        .wait()
+        .expect_consistent();
+    assert_matches!(
+        result,
+        Err(RpcError::ProviderError(ProviderError::TooFewCycles {
+            expected: _,
+            received: 0,
+        }))
+    );
+}
+
+#[test]
+fn candid_rpc_should_err_during_restricted_access() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    setup.clone().as_controller().set_open_rpc_access(false);
+    let result = setup
+        .eth_get_transaction_receipt(
+            RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Cloudflare,
+                EthMainnetService::BlockPi,
+            ])),
+            None,
+            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+        )
+        .wait()
+        .expect_consistent();
+    assert_eq!(
+        result,
+        Err(RpcError::ProviderError(ProviderError::NoPermission))
+    );
+    assert_eq!(
+        setup.get_metrics_without_histograms(),
+        Metrics {
+            err_no_permission: 1,
+            err_rpc: hashmap! {
+                (
+                    RpcMethod::EthGetTransactionReceipt.into(),
+                    "ProviderError::NoPermission".into(),
+                ) => 1,
+            },
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn candid_rpc_should_err_when_service_unavailable() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let result = setup
+        .eth_get_transaction_receipt(
+            RpcServices::EthMainnet(None),
+            None,
+            "0xdd5d4b18923d7aae953c7996d791118102e889bea37b48a651157a4890e4746f",
+        )
+        .mock_http(MockOutcallBuilder::new(503, "Service unavailable"))
+        .wait()
+        .expect_consistent();
+    assert_eq!(
         result,
         Err(RpcError::HttpOutcallError(
             cketh_common::eth_rpc::HttpOutcallError::InvalidHttpJsonRpcResponse {
@@ -1256,7 +2156,7 @@ fn candid_rpc_should_err_when_service_unavailable() {
     );
     let rpc_method = || RpcMethod::EthGetTransactionReceipt.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
@@ -1268,6 +2168,9 @@ fn candid_rpc_should_err_when_service_unavailable() {
                 (rpc_method(), CLOUDFLARE_HOSTNAME.into(), 503.into()) => 1,
                 (rpc_method(), PUBLICNODE_ETH_MAINNET_HOSTNAME.into(), 503.into()) => 1,
             },
+            err_rpc: hashmap! {
+                (rpc_method(), "HttpOutcallError::InvalidHttpJsonRpcResponse".into()) => 1,
+            },
             ..Default::default()
         }
     );
@@ -1300,7 +2203,7 @@ fn candid_rpc_should_recognize_json_error() {
     );
     let rpc_method = || RpcMethod::EthGetTransactionReceipt.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
@@ -1310,6 +2213,13 @@ fn candid_rpc_should_recognize_json_error() {
                 (rpc_method(), ANKR_HOSTNAME.into(), 200.into()) => 1,
                 (rpc_method(), BLOCKPI_ETH_SEPOLIA_HOSTNAME.into(), 200.into()) => 1,
             },
+            err_json_rpc: hashmap! {
+                (rpc_method(), ANKR_HOSTNAME.into(), 123.into()) => 1,
+                (rpc_method(), BLOCKPI_ETH_SEPOLIA_HOSTNAME.into(), 123.into()) => 1,
+            },
+            err_rpc: hashmap! {
+                (rpc_method(), "JsonRpcError".into()) => 1,
+            },
             ..Default::default()
         }
     );
@@ -1371,7 +2281,7 @@ fn candid_rpc_should_return_inconsistent_results() {
     );
     let rpc_method = || RpcMethod::EthSendRawTransaction.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
@@ -1433,7 +2343,7 @@ fn candid_rpc_should_return_inconsistent_results_with_error() {
     );
     let rpc_method = || RpcMethod::EthGetTransactionCount.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ALCHEMY_ETH_MAINNET_HOSTNAME.into()) => 1,
@@ -1447,6 +2357,12 @@ fn candid_rpc_should_return_inconsistent_results_with_error() {
                 (rpc_method(), ALCHEMY_ETH_MAINNET_HOSTNAME.into()) => 1,
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
             },
+            err_json_rpc: hashmap! {
+                (rpc_method(), ANKR_HOSTNAME.into(), 123.into()) => 1,
+            },
+            err_rpc: hashmap! {
+                (rpc_method(), "JsonRpcError".into()) => 1,
+            },
             ..Default::default()
         }
     );
@@ -1496,7 +2412,7 @@ fn candid_rpc_should_return_inconsistent_results_with_unexpected_http_status() {
     );
     let rpc_method = || RpcMethod::EthGetTransactionCount.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ALCHEMY_ETH_MAINNET_HOSTNAME.into()) => 1,
@@ -1510,6 +2426,12 @@ fn candid_rpc_should_return_inconsistent_results_with_unexpected_http_status() {
                 (rpc_method(), ALCHEMY_ETH_MAINNET_HOSTNAME.into()) => 1,
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
             },
+            err_json_rpc: hashmap! {
+                (rpc_method(), ANKR_HOSTNAME.into(), 123.into()) => 1,
+            },
+            err_rpc: hashmap! {
+                (rpc_method(), "HttpOutcallError::InvalidHttpJsonRpcResponse".into()) => 1,
+            },
             ..Default::default()
         }
     );
@@ -1545,7 +2467,7 @@ fn candid_rpc_should_handle_already_known() {
     );
     let rpc_method = || RpcMethod::EthSendRawTransaction.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
@@ -1555,6 +2477,9 @@ fn candid_rpc_should_handle_already_known() {
                 (rpc_method(), ANKR_HOSTNAME.into(), 200.into()) => 1,
                 (rpc_method(), CLOUDFLARE_HOSTNAME.into(), 200.into()) => 1,
             },
+            err_json_rpc: hashmap! {
+                (rpc_method(), CLOUDFLARE_HOSTNAME.into(), (-32000).into()) => 1,
+            },
             ..Default::default()
         }
     );
@@ -1587,7 +2512,7 @@ fn candid_rpc_should_recognize_rate_limit() {
     );
     let rpc_method = || RpcMethod::EthSendRawTransaction.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
@@ -1597,6 +2522,9 @@ fn candid_rpc_should_recognize_rate_limit() {
                 (rpc_method(), ANKR_HOSTNAME.into(), 429.into()) => 1,
                 (rpc_method(), CLOUDFLARE_HOSTNAME.into(), 429.into()) => 1,
             },
+            err_rpc: hashmap! {
+                (rpc_method(), "HttpOutcallError::InvalidHttpJsonRpcResponse".into()) => 1,
+            },
             ..Default::default()
         }
     );
@@ -1648,7 +2576,7 @@ fn should_restrict_rpc_access() {
     assert_eq!(result, Ok(1.into()));
     let rpc_method = || RpcMethod::EthGetTransactionCount.into();
     assert_eq!(
-        setup.get_metrics(),
+        setup.get_metrics_without_histograms(),
         Metrics {
             requests: hashmap! {
                 (rpc_method(), ANKR_HOSTNAME.into()) => 1,
@@ -1657,34 +2585,1218 @@ fn should_restrict_rpc_access() {
                 (rpc_method(), ANKR_HOSTNAME.into(), 200.into()) => 1,
             },
             err_no_permission: 1,
+            err_rpc: hashmap! {
+                (rpc_method(), "ProviderError::NoPermission".into()) => 1,
+            },
             ..Default::default()
         }
     );
 }
 
 #[test]
-fn should_use_custom_response_size_estimate() {
+fn should_persist_metrics_across_upgrades() {
     let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
-    let max_response_bytes = 1234;
-    let expected_response = r#"{"id":0,"jsonrpc":"2.0","result":[{"address":"0xdac17f958d2ee523a2206206994597c13d831ec7","topics":["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef","0x000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43","0x00000000000000000000000078cccfb3d517cd4ed6d045e263e134712288ace2"],"data":"0x000000000000000000000000000000000000000000000000000000003b9c6433","blockNumber":"0x11dc77e","transactionHash":"0xf3ed91a03ddf964281ac7a24351573efd535b80fc460a5c2ad2b9d23153ec678","transactionIndex":"0x65","blockHash":"0xd5c72ad752b2f0144a878594faf8bd9f570f2f72af8e7f0940d3545a6388f629","logIndex":"0xe8","removed":false}]}"#;
-    let response = setup
-        .eth_get_logs(
-            RpcServices::EthMainnet(Some(vec![EthMainnetService::Cloudflare])),
-            Some(RpcConfig {
-                response_size_estimate: Some(max_response_bytes),
-            }),
-            candid_types::GetLogsArgs {
-                addresses: vec!["0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()],
-                from_block: None,
-                to_block: None,
-                topics: None,
+    setup.clone().as_controller().set_open_rpc_access(false);
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            candid_types::GetTransactionCountArgs {
+                address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+                block: candid_types::BlockTag::Latest,
             },
         )
-        .mock_http_once(
-            MockOutcallBuilder::new(200, expected_response)
-                .with_max_response_bytes(max_response_bytes),
-        )
         .wait()
         .expect_consistent();
-    assert_matches!(response, Ok(_));
+    assert_eq!(
+        result,
+        Err(RpcError::ProviderError(ProviderError::NoPermission))
+    );
+    let metrics = setup.get_metrics();
+    assert_eq!(metrics.err_no_permission, 1);
+
+    setup.upgrade_canister(InitArgs {
+        nodes_in_subnet: NODES_IN_STANDARD_SUBNET,
+    });
+    assert_eq!(setup.get_metrics(), metrics);
+
+    setup.clone().as_controller().reset_metrics();
+    assert_eq!(setup.get_metrics(), Metrics::default());
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_unauthorized_reset_metrics() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::Manage);
+    setup.reset_metrics();
+}
+
+#[test]
+fn should_track_caller_usage() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    assert_eq!(setup.get_usage(&setup.caller), None);
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Alchemy,
+                EthMainnetService::Ankr,
+            ])),
+            None,
+            candid_types::GetTransactionCountArgs {
+                address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+                block: candid_types::BlockTag::Latest,
+            },
+        )
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"error":{"code":123,"message":"Unexpected"}}"#,
+        ))
+        .wait()
+        .expect_inconsistent();
+    assert_eq!(result.len(), 2);
+    let usage = CallerUsage {
+        requests: 2,
+        cycles_charged: 0,
+        errors: 1,
+    };
+    assert_eq!(setup.get_usage(&setup.caller), Some(usage.clone()));
+    assert_eq!(
+        setup.clone().as_controller().get_usage(&setup.caller),
+        Some(usage)
+    );
+}
+
+#[test]
+fn should_charge_prepaid_balance() {
+    let setup = EvmRpcSetup::new();
+    assert_eq!(setup.get_balance(&setup.caller), 0);
+    assert_eq!(setup.deposit_cycles(&setup.caller), DEPOSIT_PROXY_CYCLES);
+    assert_eq!(setup.get_balance(&setup.caller), DEPOSIT_PROXY_CYCLES);
+    let args = candid_types::GetTransactionCountArgs {
+        address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+        block: candid_types::BlockTag::Latest,
+    };
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            args.clone(),
+        )
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .wait()
+        .expect_consistent();
+    assert_eq!(result, Ok(1.into()));
+    let balance = setup.get_balance(&setup.caller);
+    assert!(balance < DEPOSIT_PROXY_CYCLES);
+    let usage = setup.get_usage(&setup.caller).unwrap();
+    assert_eq!(usage.cycles_charged, DEPOSIT_PROXY_CYCLES - balance);
+
+    // A balance which does not cover the request is reported as the received cycles
+    let service = RpcService::EthMainnet(EthMainnetService::Ankr);
+    let max_response_bytes = 2_000_000;
+    let cost = setup.request_cost(service.clone(), MOCK_REQUEST_PAYLOAD, max_response_bytes);
+    assert!(cost > Nat::from(balance));
+    let result = setup
+        .request(service, MOCK_REQUEST_PAYLOAD, max_response_bytes)
+        .wait();
+    assert_eq!(
+        result,
+        Err(RpcError::ProviderError(ProviderError::TooFewCycles {
+            expected: u128::try_from(cost.0.clone()).unwrap(),
+            received: balance,
+        }))
+    );
+    assert_eq!(setup.get_balance(&setup.caller), balance);
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_unauthorized_get_usage() {
+    let setup = EvmRpcSetup::new();
+    setup.get_usage(&PrincipalId::new_user_test_id(1));
+}
+
+#[test]
+fn should_record_response_histograms() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let response_body = r#"{"id":0,"jsonrpc":"2.0","result":"0x1"}"#;
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            candid_types::GetTransactionCountArgs {
+                address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+                block: candid_types::BlockTag::Latest,
+            },
+        )
+        .mock_http(MockOutcallBuilder::new(200, response_body))
+        .wait()
+        .expect_consistent();
+    assert_eq!(result, Ok(1.into()));
+
+    let key = (
+        RpcMethod::EthGetTransactionCount.into(),
+        ANKR_HOSTNAME.into(),
+    );
+    let metrics = setup.get_metrics();
+    assert_eq!(metrics.response_latencies.len(), 1);
+    assert_eq!(metrics.response_latencies[&key].count(), 1);
+    assert_eq!(metrics.response_sizes.len(), 1);
+    assert_eq!(metrics.response_sizes[&key].count(), 1);
+    assert_eq!(metrics.response_sizes[&key].sum, response_body.len() as u64);
+}
+
+fn http_rpc_request(path: &str, body: &str) -> AssetHttpRequest {
+    AssetHttpRequest {
+        method: "POST".to_string(),
+        url: path.to_string(),
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: ByteBuf::from(body.as_bytes().to_vec()),
+    }
+}
+
+#[test]
+fn should_serve_json_rpc_over_http() {
+    let setup = EvmRpcSetup::new();
+    setup.clone().as_controller().set_http_rpc_services(
+        ETH_MAINNET_CHAIN_ID,
+        Some(RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr]))),
+    );
+    let setup = setup.as_anonymous();
+    assert_eq!(
+        setup.http_request(http_rpc_request("/rpc/1", "{}")).upgrade,
+        Some(true)
+    );
+    let response = setup
+        .http_request_update(http_rpc_request(
+            "/rpc/1",
+            r#"[
+                {"jsonrpc":"2.0","id":1,"method":"eth_chainId"},
+                {"jsonrpc":"2.0","id":2,"method":"net_version","params":[]},
+                {"jsonrpc":"2.0","id":3,"method":"eth_accounts","params":[]},
+                {"jsonrpc":"2.0","id":4,"method":"eth_getBlockByNumber","params":["latest",false]},
+                {"jsonrpc":"2.0","id":5,"method":"eth_getBlockByNumber","params":["0xinvalid",false]}
+            ]"#,
+        ))
+        .wait();
+    assert_eq!(response.status_code, 200);
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(json[0]["id"], 1);
+    assert_eq!(json[0]["result"], "0x1");
+    assert_eq!(json[1]["result"], "1");
+    assert_eq!(json[2]["error"]["code"], JSON_RPC_METHOD_NOT_FOUND_CODE);
+    // The HTTP JSON-RPC account has no prepaid balance
+    assert_eq!(json[3]["error"]["code"], JSON_RPC_INTERNAL_ERROR_CODE);
+    assert_eq!(json[4]["error"]["code"], JSON_RPC_INVALID_PARAMS_CODE);
+
+    let response = setup
+        .http_request_update(http_rpc_request("/rpc/1", "{"))
+        .wait();
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(json["error"]["code"], JSON_RPC_PARSE_ERROR_CODE);
+
+    let response = setup
+        .http_request_update(http_rpc_request(
+            "/rpc/11155111",
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#,
+        ))
+        .wait();
+    assert_eq!(response.status_code, 404);
+}
+
+#[test]
+fn should_charge_http_rpc_account() {
+    let setup = EvmRpcSetup::new().as_controller();
+    setup.set_http_rpc_services(
+        ETH_MAINNET_CHAIN_ID,
+        Some(RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr]))),
+    );
+    let account = PrincipalId(setup.get_http_rpc_account().principal);
+    assert_eq!(setup.deposit_cycles(&account), DEPOSIT_PROXY_CYCLES);
+    let request = || {
+        http_rpc_request(
+            "/rpc/1",
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_getBlockByNumber","params":["latest",false]}"#,
+        )
+    };
+
+    let anonymous = setup.clone().as_anonymous();
+    let response = anonymous
+        .http_request_update(request())
+        .mock_http(MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE))
+        .wait();
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(json["result"]["number"], "0x11db01d");
+    let balance = setup.get_balance(&account);
+    assert!(balance < DEPOSIT_PROXY_CYCLES);
+    assert_eq!(setup.get_balance(&anonymous.caller), 0);
+
+    // Requests which would spend more than the limit are rejected without charging the account
+    setup.set_http_rpc_spending_limit(Some(1));
+    assert_eq!(
+        setup.get_http_rpc_account(),
+        HttpRpcAccount {
+            principal: account.0,
+            balance,
+            max_cycles_per_request: Some(1),
+        }
+    );
+    let response = anonymous.http_request_update(request()).wait();
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(json["error"]["code"], JSON_RPC_INVALID_PARAMS_CODE);
+    assert_eq!(setup.get_balance(&account), balance);
+}
+
+#[test]
+#[should_panic(expected = "Cannot deposit cycles for the anonymous principal")]
+fn should_panic_if_deposit_for_anonymous() {
+    let setup = EvmRpcSetup::new();
+    setup
+        .call_update::<u128>(
+            "depositCycles",
+            Encode!(&Some(PrincipalId::new_anonymous().0)).unwrap(),
+        )
+        .wait();
+}
+
+#[test]
+#[should_panic(expected = "Cannot set a quota for the anonymous principal")]
+fn should_panic_if_quota_for_anonymous() {
+    let setup = EvmRpcSetup::new().as_controller();
+    setup.set_quota(
+        QuotaTarget::Principal(PrincipalId::new_anonymous().0),
+        Some(Quota {
+            requests_per_minute: Some(1),
+            cycles_per_day: None,
+        }),
+    );
+}
+
+#[test]
+fn should_serve_dashboard() {
+    let setup = EvmRpcSetup::new();
+    let response = setup.http_request(AssetHttpRequest {
+        method: "GET".to_string(),
+        url: "/dashboard".to_string(),
+        headers: vec![],
+        body: ByteBuf::new(),
+    });
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.upgrade, None);
+    let html = String::from_utf8(response.body.into_vec()).unwrap();
+    assert!(html.contains("<h1>EVM RPC Dashboard</h1>"));
+    assert!(html.contains(&format!("<td>{}</td>", ANKR_HOSTNAME)));
+}
+
+#[test]
+fn should_trace_outcalls_by_request_id() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Alchemy,
+                EthMainnetService::Ankr,
+            ])),
+            None,
+            candid_types::GetTransactionCountArgs {
+                address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+                block: candid_types::BlockTag::Latest,
+            },
+        )
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"error":{"code":123,"message":"Unexpected"}}"#,
+        ))
+        .wait();
+    assert_matches!(result, MultiRpcResult::Inconsistent(_));
+    let recent_requests = setup.get_recent_requests();
+    assert_eq!(recent_requests.len(), 1);
+    assert_eq!(recent_requests[0].method, "eth_getTransactionCount");
+    let request_id = recent_requests[0].request_id;
+
+    let response = setup.http_request(AssetHttpRequest {
+        method: "GET".to_string(),
+        url: format!("/logs?requestId={}", request_id),
+        headers: vec![],
+        body: ByteBuf::new(),
+    });
+    assert_eq!(response.status_code, 200);
+    let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    let mut traces: Vec<OutcallTrace> = json["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| serde_json::from_str(entry["message"].as_str().unwrap()).unwrap())
+        .collect();
+    traces.sort_by(|a, b| a.outcome.cmp(&b.outcome));
+    assert_eq!(traces.len(), 2);
+    assert!(traces
+        .iter()
+        .all(|trace| trace.request_id == Some(request_id)
+            && trace.method == "eth_getTransactionCount"
+            && trace.status == Some(200)));
+    assert_eq!(traces[0].outcome, "JsonRpcError(123)");
+    assert_eq!(traces[1].outcome, "Ok");
+
+    // Request IDs keep increasing after an upgrade
+    setup.upgrade_canister(InitArgs {
+        nodes_in_subnet: NODES_IN_STANDARD_SUBNET,
+    });
+    setup
+        .request(
+            RpcService::EthMainnet(EthMainnetService::Cloudflare),
+            r#"{"jsonrpc":"2.0","id":0,"method":"eth_gasPrice"}"#,
+            1000,
+        )
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .wait();
+    let recent_requests = setup.get_recent_requests();
+    assert_eq!(recent_requests.len(), 1);
+    assert_eq!(recent_requests[0].method, "request");
+    assert_eq!(recent_requests[0].request_id, request_id + 1);
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_unauthorized_set_http_rpc_services() {
+    let setup = EvmRpcSetup::new();
+    setup.set_http_rpc_services(ETH_MAINNET_CHAIN_ID, None);
+}
+
+#[test]
+fn should_rate_limit_rpc_access() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    setup.clone().as_controller().set_open_rpc_access(false);
+    setup.clone().as_controller().set_quota(
+        QuotaTarget::Principal(setup.caller.0),
+        Some(Quota {
+            requests_per_minute: Some(1),
+            cycles_per_day: None,
+        }),
+    );
+    let args = candid_types::GetTransactionCountArgs {
+        address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+        block: candid_types::BlockTag::Latest,
+    };
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            args.clone(),
+        )
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .wait()
+        .expect_consistent();
+    assert_eq!(result, Ok(1.into()));
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            args,
+        )
+        .wait()
+        .expect_consistent();
+    let status = setup.get_quota_status().unwrap();
+    assert_eq!(status.requests_this_minute, 1);
+    let retry_after = status.retry_after.unwrap();
+    assert_eq!(
+        result,
+        Err(RpcError::JsonRpcError(JsonRpcError {
+            code: RATE_LIMITED_ERROR_CODE,
+            message: format!("Rate limit exceeded, retry after {}", retry_after),
+        }))
+    );
+    assert_eq!(setup.get_metrics().err_rate_limited, 1);
+}
+
+#[test]
+fn should_restrict_scoped_authorization() {
+    let setup = EvmRpcSetup::new();
+    setup.clone().as_controller().set_open_rpc_access(false);
+    setup.clone().as_controller().authorize_with(
+        &setup.caller,
+        Auth::PriorityRpc,
+        None,
+        Some(AuthScope {
+            chain_ids: None,
+            methods: Some(vec!["eth_getTransactionCount".to_string()]),
+            provider_ids: None,
+        }),
+    );
+    let result = setup
+        .eth_get_transaction_count(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            candid_types::GetTransactionCountArgs {
+                address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+                block: candid_types::BlockTag::Latest,
+            },
+        )
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .wait()
+        .expect_consistent();
+    assert_eq!(result, Ok(1.into()));
+    let result = setup
+        .eth_get_block_by_number(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+            None,
+            candid_types::BlockTag::Latest,
+        )
+        .wait()
+        .expect_consistent();
+    assert_eq!(
+        result,
+        Err(RpcError::ProviderError(ProviderError::NoPermission))
+    );
+}
+
+#[test]
+fn should_apply_chain_scope_to_custom_services() {
+    let setup = EvmRpcSetup::new();
+    setup.clone().as_controller().authorize_with(
+        &setup.caller,
+        Auth::FreeRpc,
+        None,
+        Some(AuthScope {
+            chain_ids: Some(vec![ETH_MAINNET_CHAIN_ID]),
+            methods: None,
+            provider_ids: None,
+        }),
+    );
+    let custom_services = |chain_id| RpcServices::Custom {
+        chain_id,
+        services: vec![RpcApi {
+            url: MOCK_REQUEST_URL.to_string(),
+            headers: None,
+        }],
+    };
+    let args = candid_types::GetTransactionCountArgs {
+        address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
+        block: candid_types::BlockTag::Latest,
+    };
+    let result = setup
+        .eth_get_transaction_count(custom_services(ETH_MAINNET_CHAIN_ID), None, args.clone())
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#,
+        ))
+        .wait()
+        .expect_consistent();
+    assert_eq!(result, Ok(1.into()));
+    assert_eq!(setup.get_usage(&setup.caller).unwrap().cycles_charged, 0);
+
+    // Calls to other chains are not free
+    let result = setup
+        .eth_get_transaction_count(custom_services(ETH_SEPOLIA_CHAIN_ID), None, args)
+        .wait()
+        .expect_consistent();
+    assert_matches!(
+        result,
+        Err(RpcError::ProviderError(ProviderError::TooFewCycles { .. }))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Authorization scope exceeds the maximum size")]
+fn should_panic_if_auth_scope_too_large() {
+    let setup = EvmRpcSetup::new().as_controller();
+    setup.authorize_with(
+        &PrincipalId::new_user_test_id(1),
+        Auth::FreeRpc,
+        None,
+        Some(AuthScope {
+            chain_ids: None,
+            methods: Some(vec!["eth_getLogs".to_string(); 500]),
+            provider_ids: None,
+        }),
+    );
+}
+
+#[test]
+fn should_reject_expired_authorization() {
+    let setup = EvmRpcSetup::new();
+    let now = setup
+        .env
+        .time()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    let expires_at = now + Duration::from_secs(60).as_nanos() as u64;
+    assert!(setup.clone().as_controller().authorize_with(
+        &setup.caller,
+        Auth::RegisterProvider,
+        Some(expires_at),
+        None,
+    ));
+    assert_eq!(
+        setup
+            .clone()
+            .as_controller()
+            .get_authorized(Auth::RegisterProvider),
+        vec![setup.caller.0]
+    );
+    setup.env.advance_time(Duration::from_secs(120));
+    assert_eq!(
+        setup
+            .clone()
+            .as_controller()
+            .get_authorized(Auth::RegisterProvider),
+        vec![]
+    );
+}
+
+#[test]
+fn should_use_custom_response_size_estimate() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let max_response_bytes = 1234;
+    let expected_response = r#"{"id":0,"jsonrpc":"2.0","result":[{"address":"0xdac17f958d2ee523a2206206994597c13d831ec7","topics":["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef","0x000000000000000000000000a9d1e08c7793af67e9d92fe308d5697fb81d3e43","0x00000000000000000000000078cccfb3d517cd4ed6d045e263e134712288ace2"],"data":"0x000000000000000000000000000000000000000000000000000000003b9c6433","blockNumber":"0x11dc77e","transactionHash":"0xf3ed91a03ddf964281ac7a24351573efd535b80fc460a5c2ad2b9d23153ec678","transactionIndex":"0x65","blockHash":"0xd5c72ad752b2f0144a878594faf8bd9f570f2f72af8e7f0940d3545a6388f629","logIndex":"0xe8","removed":false}]}"#;
+    let response = setup
+        .eth_get_logs(
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::Cloudflare])),
+            Some(RpcConfig {
+                response_size_estimate: Some(max_response_bytes),
+            }),
+            candid_types::GetLogsArgs {
+                addresses: vec!["0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()],
+                from_block: None,
+                to_block: None,
+                topics: None,
+            },
+        )
+        .mock_http_once(
+            MockOutcallBuilder::new(200, expected_response)
+                .with_max_response_bytes(max_response_bytes),
+        )
+        .wait()
+        .expect_consistent();
+    assert_matches!(response, Ok(_));
+}
+
+#[test]
+fn should_serve_finalized_blocks_from_response_cache() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let controller = setup.clone().as_controller();
+    controller.set_response_cache_size(10);
+    let get_block = |block| {
+        setup.eth_get_block_by_number(
+            RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Ankr,
+                EthMainnetService::Cloudflare,
+            ])),
+            None,
+            block,
+        )
+    };
+    let finalized = get_block(candid_types::BlockTag::Finalized)
+        .mock_http(MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(finalized.number, BlockNumber::new(18_722_845));
+    let block = candid_types::BlockTag::Number(finalized.number);
+    let response = get_block(block.clone())
+        .mock_http(MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(response, finalized);
+    assert_eq!(controller.get_response_cache_stats().entries, 1);
+
+    // Served without HTTP outcalls
+    let cached = get_block(block).wait().expect_consistent().unwrap();
+    assert_eq!(cached, finalized);
+    let metrics = setup.get_metrics();
+    let rpc_method = || RpcMethod::EthGetBlockByNumber.into();
+    assert_eq!(metrics.cache_hits, hashmap! { rpc_method() => 1 });
+    assert_eq!(metrics.cache_misses, hashmap! { rpc_method() => 1 });
+
+    // Callers without a free authorization pay a flat fee for cached responses
+    let other = setup.clone().as_caller(PrincipalId::new_user_test_id(42));
+    let get_cached_block = || {
+        other
+            .eth_get_block_by_number(
+                RpcServices::EthMainnet(Some(vec![
+                    EthMainnetService::Cloudflare,
+                    EthMainnetService::Ankr,
+                ])),
+                None,
+                block.clone(),
+            )
+            .wait()
+            .expect_consistent()
+    };
+    assert_matches!(
+        get_cached_block(),
+        Err(RpcError::ProviderError(ProviderError::TooFewCycles { .. }))
+    );
+    other.deposit_cycles(&other.caller);
+    assert_eq!(get_cached_block(), Ok(finalized.clone()));
+    assert_eq!(
+        other.get_balance(&other.caller),
+        DEPOSIT_PROXY_CYCLES - RESPONSE_CACHE_HIT_CYCLES
+    );
+
+    // Blocks after the finalized head are not cached
+    get_block(candid_types::BlockTag::Number(BlockNumber::new(18_722_846)))
+        .mock_http(MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE))
+        .wait();
+    assert_eq!(controller.get_response_cache_stats().entries, 1);
+
+    assert_eq!(
+        controller.clear_response_cache(Some(ETH_SEPOLIA_CHAIN_ID)),
+        0
+    );
+    assert_eq!(
+        controller.clear_response_cache(Some(ETH_MAINNET_CHAIN_ID)),
+        1
+    );
+    assert_eq!(
+        controller.get_response_cache_stats(),
+        ResponseCacheStats {
+            entries: 0,
+            max_entries: 10
+        }
+    );
+}
+
+#[test]
+fn should_serve_finalized_contract_code_from_response_cache() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let controller = setup.clone().as_controller();
+    controller.set_response_cache_size(10);
+    let services = || {
+        RpcServices::EthMainnet(Some(vec![
+            EthMainnetService::Ankr,
+            EthMainnetService::Cloudflare,
+        ]))
+    };
+    let finalized = setup
+        .eth_get_block_by_number(services(), None, candid_types::BlockTag::Finalized)
+        .mock_http(MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    let get_code = |address: &str| {
+        setup.eth_get_code(
+            services(),
+            None,
+            candid_types::GetCodeArgs {
+                address: address.to_string(),
+                block: candid_types::BlockTag::Number(finalized.number),
+            },
+        )
+    };
+    let code = get_code("0xdAC17F958D2ee523a2206206994597C13D831ec7")
+        .mock_http(MockOutcallBuilder::new(
+            200,
+            r#"{"id":0,"jsonrpc":"2.0","result":"0x6080604052"}"#,
+        ))
+        .wait()
+        .expect_consistent();
+    assert_eq!(code, Ok("0x6080604052".to_string()));
+    assert_eq!(controller.get_response_cache_stats().entries, 1);
+
+    // Served without HTTP outcalls, regardless of the address checksum
+    let cached = get_code("0xdac17f958d2ee523a2206206994597c13d831ec7")
+        .wait()
+        .expect_consistent();
+    assert_eq!(cached, code);
+    assert_eq!(
+        setup.get_metrics().cache_hits,
+        hashmap! { RpcMethod::EthGetCode.into() => 1 }
+    );
+
+    assert_eq!(
+        get_code("0xinvalid").wait().expect_consistent(),
+        Err(RpcError::ValidationError(ValidationError::InvalidHex(
+            "0xinvalid".to_string()
+        )))
+    );
+}
+
+#[test]
+fn should_not_learn_finalized_head_from_single_provider() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let controller = setup.clone().as_controller();
+    controller.set_response_cache_size(10);
+    let get_block = |block| {
+        setup
+            .eth_get_block_by_number(
+                RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+                None,
+                block,
+            )
+            .mock_http(MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE))
+            .wait()
+            .expect_consistent()
+            .unwrap()
+    };
+    let finalized = get_block(candid_types::BlockTag::Finalized);
+    get_block(candid_types::BlockTag::Number(finalized.number));
+    assert_eq!(controller.get_response_cache_stats().entries, 0);
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_unauthorized_set_response_cache_size() {
+    let setup = EvmRpcSetup::new();
+    setup.set_response_cache_size(10);
+}
+
+#[test]
+fn should_track_chain_head() {
+    let setup = EvmRpcSetup::new();
+    setup.clone().as_controller().set_chain_head_services(
+        ETH_MAINNET_CHAIN_ID,
+        Some(RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr]))),
+    );
+    assert_eq!(setup.get_chain_head(ETH_MAINNET_CHAIN_ID), None);
+
+    setup.env.advance_time(CHAIN_HEAD_POLL_INTERVAL);
+    // Latest and finalized blocks
+    for _ in 0..2 {
+        setup.tick_until_http_request();
+        assert!(setup
+            .mock_pending_http_request(&MockOutcallBuilder::new(200, MOCK_BLOCK_RESPONSE).into()));
+    }
+    setup.env.tick();
+
+    let head = setup.get_chain_head(ETH_MAINNET_CHAIN_ID).unwrap();
+    let latest = head.latest.unwrap();
+    assert_eq!(latest.number, BlockNumber::new(18_722_845));
+    assert_eq!(latest.transactions, Vec::<String>::new());
+    assert_eq!(head.finalized.unwrap().number, latest.number);
+    assert_eq!(setup.get_chain_head(ETH_SEPOLIA_CHAIN_ID), None);
+
+    // Background polls are charged to the canister itself, crediting the providers
+    let usage = setup
+        .clone()
+        .as_controller()
+        .get_usage(&setup.canister_id.get())
+        .unwrap();
+    assert_eq!(usage.requests, 2);
+    assert!(usage.cycles_charged > 0);
+
+    setup
+        .clone()
+        .as_controller()
+        .set_chain_head_services(ETH_MAINNET_CHAIN_ID, None);
+    assert_eq!(setup.get_chain_head(ETH_MAINNET_CHAIN_ID), None);
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_unauthorized_set_chain_head_services() {
+    let setup = EvmRpcSetup::new();
+    setup.set_chain_head_services(ETH_MAINNET_CHAIN_ID, None);
+}
+
+/// Returns a setup whose caller can subscribe with a free authorization which expires before the
+/// first poll.
+fn setup_expiring_subscriber() -> EvmRpcSetup {
+    let setup = EvmRpcSetup::new();
+    let now = setup
+        .env
+        .time()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    let expires_at = now + Duration::from_secs(1).as_nanos() as u64;
+    assert!(setup.clone().as_controller().authorize_with(
+        &setup.caller,
+        Auth::FreeRpc,
+        Some(expires_at),
+        None,
+    ));
+    setup
+}
+
+#[test]
+fn should_pause_unpaid_log_subscriptions() {
+    let setup = setup_expiring_subscriber();
+    setup.subscribe_logs(SubscribeLogsArgs {
+        services: RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        filter: candid_types::GetLogsArgs {
+            addresses: vec!["0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()],
+            from_block: None,
+            to_block: None,
+            topics: None,
+        },
+        callback: "onLogs".to_string(),
+    });
+
+    // Polls are paused without making HTTP outcalls
+    setup.env.advance_time(SUBSCRIPTION_POLL_INTERVAL);
+    setup.tick_until_http_request();
+    assert!(setup.env.canister_http_request_contexts().is_empty());
+    let subscription = setup.get_log_subscriptions().pop().unwrap();
+    assert!(subscription.unpaid_since.is_some());
+
+    // Removed once unpaid for too long
+    setup.env.advance_time(SUBSCRIPTION_UNPAID_EXPIRY);
+    setup.tick_until_http_request();
+    assert!(setup.env.canister_http_request_contexts().is_empty());
+    assert!(setup.get_log_subscriptions().is_empty());
+}
+
+#[test]
+fn should_pause_unpaid_block_subscriptions() {
+    let setup = setup_expiring_subscriber();
+    setup.subscribe_blocks(SubscribeBlocksArgs {
+        services: RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        from_block: None,
+        callback: "onBlocks".to_string(),
+    });
+
+    // Polls are paused without making HTTP outcalls
+    setup.env.advance_time(SUBSCRIPTION_POLL_INTERVAL);
+    setup.tick_until_http_request();
+    assert!(setup.env.canister_http_request_contexts().is_empty());
+    let subscription = setup.get_block_subscriptions().pop().unwrap();
+    assert!(subscription.unpaid_since.is_some());
+
+    // Removed once unpaid for too long
+    setup.env.advance_time(SUBSCRIPTION_UNPAID_EXPIRY);
+    setup.tick_until_http_request();
+    assert!(setup.env.canister_http_request_contexts().is_empty());
+    assert!(setup.get_block_subscriptions().is_empty());
+}
+
+#[test]
+fn should_deliver_subscribed_logs() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let id = setup.subscribe_logs(SubscribeLogsArgs {
+        services: RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        filter: candid_types::GetLogsArgs {
+            addresses: vec!["0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()],
+            from_block: Some(candid_types::BlockTag::Number(BlockNumber::new(18_722_800))),
+            to_block: None,
+            topics: None,
+        },
+        callback: "onLogs".to_string(),
+    });
+    let subscription = setup.get_log_subscriptions().pop().unwrap();
+    assert_eq!(subscription.id, id);
+    assert_eq!(subscription.confirmation, candid_types::BlockTag::Finalized);
+    assert_eq!(subscription.next_block, Some(18_722_800));
+
+    setup.env.advance_time(SUBSCRIPTION_POLL_INTERVAL);
+    // Finalized block, then logs up to that block
+    for response in [MOCK_BLOCK_RESPONSE, MOCK_LOGS_RESPONSE] {
+        setup.tick_until_http_request();
+        assert!(setup.mock_pending_http_request(&MockOutcallBuilder::new(200, response).into()));
+    }
+    setup.env.tick();
+
+    let subscription = setup.get_log_subscriptions().pop().unwrap();
+    assert_eq!(subscription.delivered, Some(18_722_845));
+    assert_eq!(subscription.next_block, Some(18_722_800));
+    assert_eq!(subscription.last_error, None);
+
+    setup.acknowledge_logs(id, 18_722_845);
+    let subscription = setup.get_log_subscriptions().pop().unwrap();
+    assert_eq!(subscription.delivered, None);
+    assert_eq!(subscription.next_block, Some(18_722_846));
+}
+
+#[test]
+#[should_panic(expected = "You are not authorized")]
+fn should_panic_if_unauthorized_subscribe_logs() {
+    let setup = EvmRpcSetup::new();
+    setup.clone().as_controller().set_open_rpc_access(false);
+    setup.subscribe_logs(SubscribeLogsArgs {
+        services: RpcServices::EthMainnet(None),
+        filter: candid_types::GetLogsArgs {
+            addresses: vec![],
+            from_block: None,
+            to_block: None,
+            topics: None,
+        },
+        callback: "onLogs".to_string(),
+    });
+}
+
+#[test]
+#[should_panic(expected = "Subscriptions require a prepaid balance or a free RPC authorization")]
+fn should_panic_if_unpaid_subscribe_blocks() {
+    let setup = EvmRpcSetup::new();
+    setup.subscribe_blocks(SubscribeBlocksArgs {
+        services: RpcServices::EthMainnet(None),
+        from_block: None,
+        callback: "onBlocks".to_string(),
+    });
+}
+
+#[test]
+#[should_panic(expected = "Subscriptions require a prepaid balance or a free RPC authorization")]
+fn should_panic_if_subscribe_logs_outside_free_rpc_scope() {
+    let setup = EvmRpcSetup::new();
+    assert!(setup.clone().as_controller().authorize_with(
+        &setup.caller,
+        Auth::FreeRpc,
+        None,
+        Some(AuthScope {
+            chain_ids: Some(vec![ETH_SEPOLIA_CHAIN_ID]),
+            methods: None,
+            provider_ids: None,
+        }),
+    ));
+    setup.subscribe_logs(SubscribeLogsArgs {
+        services: RpcServices::EthMainnet(None),
+        filter: candid_types::GetLogsArgs {
+            addresses: vec![],
+            from_block: None,
+            to_block: None,
+            topics: None,
+        },
+        callback: "onLogs".to_string(),
+    });
+}
+
+fn mock_block_response(number: u64, hash: u64, parent_hash: u64) -> String {
+    MOCK_BLOCK_RESPONSE
+        .replace(
+            r#""number":"0x11db01d""#,
+            &format!(r#""number":"{:#x}""#, number),
+        )
+        .replace(
+            "0xc3674be7b9d95580d7f23c03d32e946f2b453679ee6505e3a778f003c5a3cfae",
+            &format!("0x{:064x}", hash),
+        )
+        .replace(
+            "0x43325027f6adf9befb223f8ae80db057daddcd7b48e41f60cd94bfa8877181ae",
+            &format!("0x{:064x}", parent_hash),
+        )
+}
+
+#[test]
+fn should_deliver_subscribed_blocks_and_detect_reorgs() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    setup.subscribe_blocks(SubscribeBlocksArgs {
+        services: RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        from_block: Some(100),
+        callback: "onBlocks".to_string(),
+    });
+    let poll = |responses: &[String]| {
+        setup.env.advance_time(SUBSCRIPTION_POLL_INTERVAL);
+        for response in responses {
+            setup.tick_until_http_request();
+            assert!(setup.mock_pending_http_request(
+                &MockOutcallBuilder::new(200, response.as_str()).into()
+            ));
+        }
+        setup.env.tick();
+        setup.get_block_subscriptions().pop().unwrap()
+    };
+
+    // Latest block, then block 100
+    let subscription = poll(&[
+        mock_block_response(100, 0xa100, 0xa099),
+        mock_block_response(100, 0xa100, 0xa099),
+    ]);
+    assert_eq!(subscription.next_block, Some(101));
+    assert_eq!(
+        subscription.recent_blocks,
+        vec![(100, format!("0x{:064x}", 0xa100))]
+    );
+    assert_eq!(subscription.last_error, None);
+
+    // Block 101 has a different parent: block 100 was orphaned and is delivered again
+    let block_101 = mock_block_response(101, 0xb101, 0xb100);
+    let subscription = poll(&[
+        block_101.clone(),
+        block_101.clone(),
+        mock_block_response(100, 0xb100, 0xa099),
+        block_101,
+    ]);
+    assert_eq!(subscription.next_block, Some(102));
+    assert_eq!(
+        subscription.recent_blocks,
+        vec![
+            (100, format!("0x{:064x}", 0xb100)),
+            (101, format!("0x{:064x}", 0xb101))
+        ]
+    );
+    assert_eq!(subscription.last_error, None);
+}
+
+#[test]
+fn should_report_removed_logs_of_log_cursor() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let cursor_id = setup.create_log_cursor(
+        RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        candid_types::GetLogsArgs {
+            addresses: vec!["0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()],
+            from_block: Some(candid_types::BlockTag::Number(BlockNumber::new(0x11dc770))),
+            to_block: None,
+            topics: None,
+        },
+    );
+    let head = mock_block_response(0x11dc780, 0xa780, 0xa77f);
+
+    // Latest block, then logs
+    let logs = setup
+        .poll_log_cursor(cursor_id)
+        .mock_http_once(MockOutcallBuilder::new(200, head.as_str()))
+        .mock_http_once(MockOutcallBuilder::new(200, MOCK_LOGS_RESPONSE))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(logs.len(), 1);
+    assert!(!logs[0].removed);
+    let cursor = setup.get_log_cursors().pop().unwrap();
+    assert_eq!(cursor.next_block, Some(0x11dc781));
+
+    // Unchanged logs are not returned again
+    let logs = setup
+        .poll_log_cursor(cursor_id)
+        .mock_http_once(MockOutcallBuilder::new(200, head.as_str()))
+        .mock_http_once(MockOutcallBuilder::new(200, MOCK_LOGS_RESPONSE))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(logs, vec![]);
+
+    // The block of the log was reorged
+    let reorged_hash = format!("0x{:064x}", 0xb77e);
+    let reorged_logs = MOCK_LOGS_RESPONSE.replace(
+        "0xd5c72ad752b2f0144a878594faf8bd9f570f2f72af8e7f0940d3545a6388f629",
+        &reorged_hash,
+    );
+    let logs = setup
+        .poll_log_cursor(cursor_id)
+        .mock_http_once(MockOutcallBuilder::new(200, head.as_str()))
+        .mock_http_once(MockOutcallBuilder::new(200, reorged_logs))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(logs.len(), 2);
+    assert!(logs[0].removed);
+    assert_eq!(
+        logs[0].block_hash,
+        Some(
+            Hash::from_str("0xd5c72ad752b2f0144a878594faf8bd9f570f2f72af8e7f0940d3545a6388f629")
+                .unwrap()
+        )
+    );
+    assert!(!logs[1].removed);
+    assert_eq!(
+        logs[1].block_hash,
+        Some(Hash::from_str(&reorged_hash).unwrap())
+    );
+}
+
+#[test]
+fn should_limit_log_cursor_range() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let from_block = 0x11dc780 - 1000;
+    let cursor_id = setup.create_log_cursor(
+        RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        candid_types::GetLogsArgs {
+            addresses: vec!["0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()],
+            from_block: Some(candid_types::BlockTag::Number(BlockNumber::new(from_block))),
+            to_block: None,
+            topics: None,
+        },
+    );
+    setup
+        .poll_log_cursor(cursor_id)
+        .mock_http_once(MockOutcallBuilder::new(
+            200,
+            mock_block_response(0x11dc780, 0xa780, 0xa77f).as_str(),
+        ))
+        .mock_http_once(MockOutcallBuilder::new(200, MOCK_LOGS_RESPONSE))
+        .wait()
+        .expect_consistent()
+        .unwrap();
+    // Blocks `from_block` to `from_block + 499`
+    let cursor = setup.get_log_cursors().pop().unwrap();
+    assert_eq!(
+        cursor.next_block,
+        Some(from_block as u64 + ETH_GET_LOGS_MAX_BLOCKS as u64)
+    );
+}
+
+#[test]
+fn should_track_transaction_until_finalized() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    let hash = setup.track_transaction(TrackTransactionArgs {
+        services: RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        raw_signed_transaction_hex: MOCK_TRANSACTION.to_string(),
+        callback: None,
+    });
+    assert_eq!(hash, MOCK_TRANSACTION_HASH);
+    assert_eq!(
+        setup.get_transaction_status(&hash),
+        Some(TransactionStatus::Pending)
+    );
+
+    setup.env.advance_time(TRANSACTION_POLL_INTERVAL);
+    // Receipt, then finalized block
+    for response in [MOCK_RECEIPT_RESPONSE, MOCK_BLOCK_RESPONSE] {
+        setup.tick_until_http_request();
+        assert!(setup.mock_pending_http_request(&MockOutcallBuilder::new(200, response).into()));
+    }
+    setup.env.tick();
+
+    assert_eq!(
+        setup.get_transaction_status(&hash),
+        Some(TransactionStatus::Finalized(0x11a85ab))
+    );
+}
+
+#[test]
+fn should_pause_unpaid_tracked_transactions() {
+    let setup = setup_expiring_subscriber();
+    let hash = setup.track_transaction(TrackTransactionArgs {
+        services: RpcServices::EthMainnet(Some(vec![EthMainnetService::Ankr])),
+        raw_signed_transaction_hex: MOCK_TRANSACTION.to_string(),
+        callback: None,
+    });
+
+    // Polls are paused without making HTTP outcalls
+    setup.env.advance_time(TRANSACTION_POLL_INTERVAL);
+    setup.tick_until_http_request();
+    assert!(setup.env.canister_http_request_contexts().is_empty());
+    assert_eq!(
+        setup.get_transaction_status(&hash),
+        Some(TransactionStatus::Pending)
+    );
+
+    // No longer tracked once unpaid for too long
+    setup.env.advance_time(SUBSCRIPTION_UNPAID_EXPIRY);
+    setup.tick_until_http_request();
+    assert!(setup.env.canister_http_request_contexts().is_empty());
+    assert_eq!(setup.get_transaction_status(&hash), None);
+}
+
+#[test]
+#[should_panic(expected = "Invalid raw signed transaction")]
+fn should_panic_when_tracking_invalid_transaction() {
+    let setup = EvmRpcSetup::new().authorize_caller(Auth::FreeRpc);
+    setup.track_transaction(TrackTransactionArgs {
+        services: RpcServices::EthMainnet(None),
+        raw_signed_transaction_hex: "0x1234".to_string(),
+        callback: None,
+    });
 }